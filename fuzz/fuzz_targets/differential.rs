@@ -0,0 +1,58 @@
+// The differential test of tests/differential.rs, driven by libFuzzer's
+// coverage feedback instead of random scenarios.
+
+#![no_main]
+
+#[path = "../../tests/reference/mod.rs"]
+mod reference;
+
+use arbitrary::{Result, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use mch8::memory::MemoryPolicy;
+use mch8::quirks::Quirks;
+use reference::{find_divergence, Scenario, MEMORY_SIZE, PROGRAM_START};
+
+const MAX_INSTRUCTIONS: usize = 1000;
+
+// The input's first bytes set the machine up, the rest is the program.
+fn scenario(data: &[u8]) -> Result<Scenario> {
+    let mut input = Unstructured::new(data);
+    let flags: [bool; 4] = input.arbitrary()?;
+    let memory_policy = *input.choose(&[
+        MemoryPolicy::Wrap,
+        MemoryPolicy::Fault,
+        MemoryPolicy::Warn,
+    ])?;
+
+    let mut scenario = Scenario {
+        quirks: Quirks {
+            shift_uses_vy: flags[0],
+            load_store_increments_index: flags[1],
+            jump_uses_vx: flags[2],
+            logic_resets_vf: flags[3],
+        },
+        memory_policy,
+        seed: input.arbitrary()?,
+        registers: input.arbitrary()?,
+        index_register: input.int_in_range(0..=MEMORY_SIZE as u16 - 1)?,
+        delay_timer: input.arbitrary()?,
+        sound_timer: input.arbitrary()?,
+        keys: input.arbitrary()?,
+        memory: vec![0; MEMORY_SIZE],
+        instructions: input.int_in_range(1..=MAX_INSTRUCTIONS)?,
+    };
+
+    let program = input.take_rest();
+    let length = program.len().min(MEMORY_SIZE - PROGRAM_START as usize);
+    scenario.memory[PROGRAM_START as usize..][..length].copy_from_slice(&program[..length]);
+
+    Ok(scenario)
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(scenario) = scenario(data) {
+        if let Some(divergence) = find_divergence(&scenario) {
+            panic!("{}", divergence);
+        }
+    }
+});
@@ -0,0 +1,30 @@
+// Any file given as a ROM must load and run without panicking : a ROM doing
+// something impossible faults instead.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mch8::globals::INSTRUCTIONS_PER_FRAME;
+use mch8::logger::{self, LogLevel};
+use mch8::virtual_machine::VirtualMachine;
+
+const FRAMES: usize = 600;
+
+fuzz_target!(|rom: &[u8]| {
+    logger::set_level(LogLevel::Off);
+
+    let mut virtual_machine = VirtualMachine::new();
+    if virtual_machine.load_rom_bytes(rom).is_err() {
+        return;
+    }
+
+    for frame in 0..FRAMES {
+        // Some ROMs wait for a key.
+        virtual_machine.set_key((frame % 16) as u8, frame % 30 < 15);
+        virtual_machine.run_frame(INSTRUCTIONS_PER_FRAME);
+
+        if virtual_machine.fault().is_some() {
+            return;
+        }
+    }
+});
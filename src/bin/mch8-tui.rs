@@ -0,0 +1,69 @@
+use mch8::config::{Config, OPTIONS_HELP};
+use mch8::frontend::{setup_tools, RunLoop};
+use mch8::globals::*;
+use mch8::logger;
+use mch8::tui_frontend::TuiFrontend;
+use mch8::virtual_machine::VirtualMachine;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, process};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let config = Config::from_args(&args);
+    if let Err(err_message) = config {
+        eprintln!("{}", err_message);
+        process::exit(1);
+    }
+    let config = config.unwrap();
+    logger::set_level(config.log_level);
+
+    let Some(rom_path) = config.rom_path.clone() else {
+        eprintln!("[Error] No input file provided !");
+        eprintln!("[Info] Usage : ./mch8-tui [options] <chip8.rom>");
+        eprintln!("{}", OPTIONS_HELP);
+        process::exit(1);
+    };
+
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(DEFAULT_RANDOM_SEED, |elapsed| elapsed.as_nanos() as u64);
+    let mut virtual_machine = VirtualMachine::with_seed(seed);
+
+    if let Err(err_message) = config.configure_machine(&mut virtual_machine) {
+        eprintln!("{}", err_message);
+        process::exit(1);
+    }
+
+    let r = virtual_machine.load_rom(&rom_path);
+    if r.is_err() {
+        let err_message = r.err().unwrap();
+        eprintln!("{}", err_message);
+        process::exit(1);
+    }
+
+    let mut run_loop = RunLoop::new();
+    run_loop.set_speed(config.speed);
+    run_loop.set_fast_forward_speed(config.fast_forward_speed);
+    let tools = setup_tools(&config, &mut virtual_machine, &mut run_loop);
+    if let Err(err_message) = tools {
+        eprintln!("{}", err_message);
+        process::exit(1);
+    }
+    let tools = tools.unwrap();
+
+    let frontend = TuiFrontend::new(&config);
+    if frontend.is_err() {
+        eprintln!("[Error] Unable to switch the terminal into raw mode !");
+        process::exit(1);
+    }
+    let mut frontend = frontend.unwrap();
+
+    run_loop.load_cheats(&mut virtual_machine, &mut frontend);
+    run_loop.run(&mut virtual_machine, &mut frontend);
+    drop(frontend); // Back to the normal terminal before reporting errors.
+
+    for err_message in tools.finish(&virtual_machine) {
+        eprintln!("{}", err_message);
+    }
+}
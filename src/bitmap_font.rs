@@ -0,0 +1,113 @@
+use crate::framebuffer::Framebuffer;
+use crate::palette::Rgb;
+
+pub const GLYPH_WIDTH: u32 = 5;
+pub const GLYPH_HEIGHT: u32 = 5;
+const GLYPH_SPACING: u32 = 1;
+
+// 5x5 glyphs stored like the CHIP-8 fontset, one byte per row, the leftmost
+// pixel being the most significant bit. Lowercase letters are drawn uppercase.
+const GLYPHS: [(char, [u8; 5]); 57] = [
+    ('A', [0x70, 0x88, 0xF8, 0x88, 0x88]),
+    ('B', [0xF0, 0x88, 0xF0, 0x88, 0xF0]),
+    ('C', [0x78, 0x80, 0x80, 0x80, 0x78]),
+    ('D', [0xF0, 0x88, 0x88, 0x88, 0xF0]),
+    ('E', [0xF8, 0x80, 0xF0, 0x80, 0xF8]),
+    ('F', [0xF8, 0x80, 0xF0, 0x80, 0x80]),
+    ('G', [0x78, 0x80, 0x98, 0x88, 0x70]),
+    ('H', [0x88, 0x88, 0xF8, 0x88, 0x88]),
+    ('I', [0xF8, 0x20, 0x20, 0x20, 0xF8]),
+    ('J', [0x38, 0x10, 0x10, 0x90, 0x60]),
+    ('K', [0x88, 0x90, 0xE0, 0x90, 0x88]),
+    ('L', [0x80, 0x80, 0x80, 0x80, 0xF8]),
+    ('M', [0x88, 0xD8, 0xA8, 0x88, 0x88]),
+    ('N', [0x88, 0xC8, 0xA8, 0x98, 0x88]),
+    ('O', [0x70, 0x88, 0x88, 0x88, 0x70]),
+    ('P', [0xF0, 0x88, 0xF0, 0x80, 0x80]),
+    ('Q', [0x70, 0x88, 0xA8, 0x90, 0x68]),
+    ('R', [0xF0, 0x88, 0xF0, 0x90, 0x88]),
+    ('S', [0x78, 0x80, 0x70, 0x08, 0xF0]),
+    ('T', [0xF8, 0x20, 0x20, 0x20, 0x20]),
+    ('U', [0x88, 0x88, 0x88, 0x88, 0x70]),
+    ('V', [0x88, 0x88, 0x88, 0x50, 0x20]),
+    ('W', [0x88, 0x88, 0xA8, 0xD8, 0x88]),
+    ('X', [0x88, 0x50, 0x20, 0x50, 0x88]),
+    ('Y', [0x88, 0x50, 0x20, 0x20, 0x20]),
+    ('Z', [0xF8, 0x10, 0x20, 0x40, 0xF8]),
+    ('0', [0x70, 0x98, 0xA8, 0xC8, 0x70]),
+    ('1', [0x20, 0x60, 0x20, 0x20, 0x70]),
+    ('2', [0xF0, 0x08, 0x70, 0x80, 0xF8]),
+    ('3', [0xF0, 0x08, 0x30, 0x08, 0xF0]),
+    ('4', [0x90, 0x90, 0xF8, 0x10, 0x10]),
+    ('5', [0xF8, 0x80, 0xF0, 0x08, 0xF0]),
+    ('6', [0x70, 0x80, 0xF0, 0x88, 0x70]),
+    ('7', [0xF8, 0x08, 0x10, 0x20, 0x20]),
+    ('8', [0x70, 0x88, 0x70, 0x88, 0x70]),
+    ('9', [0x70, 0x88, 0x78, 0x08, 0x70]),
+    (' ', [0x00, 0x00, 0x00, 0x00, 0x00]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x20]),
+    (',', [0x00, 0x00, 0x00, 0x20, 0x40]),
+    (':', [0x00, 0x20, 0x00, 0x20, 0x00]),
+    ('-', [0x00, 0x00, 0x70, 0x00, 0x00]),
+    ('+', [0x00, 0x20, 0x70, 0x20, 0x00]),
+    ('=', [0x00, 0x70, 0x00, 0x70, 0x00]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0xF8]),
+    ('/', [0x08, 0x10, 0x20, 0x40, 0x80]),
+    ('%', [0xC8, 0xD0, 0x20, 0x58, 0x98]),
+    ('!', [0x20, 0x20, 0x20, 0x00, 0x20]),
+    ('?', [0x70, 0x08, 0x30, 0x00, 0x20]),
+    ('\'', [0x20, 0x20, 0x00, 0x00, 0x00]),
+    ('(', [0x10, 0x20, 0x20, 0x20, 0x10]),
+    (')', [0x40, 0x20, 0x20, 0x20, 0x40]),
+    ('[', [0x30, 0x20, 0x20, 0x20, 0x30]),
+    (']', [0x60, 0x20, 0x20, 0x20, 0x60]),
+    ('<', [0x10, 0x20, 0x40, 0x20, 0x10]),
+    ('>', [0x40, 0x20, 0x10, 0x20, 0x40]),
+    ('*', [0x00, 0xA8, 0x70, 0xA8, 0x00]),
+    ('#', [0x50, 0xF8, 0x50, 0xF8, 0x50]),
+];
+
+fn glyph(c: char) -> [u8; 5] {
+    let c = c.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .find(|(glyph_char, _)| *glyph_char == c)
+        .map_or(GLYPHS[GLYPHS.len() - 1].1, |(_, rows)| *rows)
+}
+
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    let length = text.chars().count() as u32;
+    (length * (GLYPH_WIDTH + GLYPH_SPACING)).saturating_sub(GLYPH_SPACING) * scale
+}
+
+pub fn text_height(scale: u32) -> u32 {
+    GLYPH_HEIGHT * scale
+}
+
+// Unknown characters are drawn as '#'.
+pub fn draw_text(
+    framebuffer: &mut Framebuffer,
+    x: u32,
+    y: u32,
+    text: &str,
+    color: Rgb,
+    scale: u32,
+) {
+    for (index, c) in text.chars().enumerate() {
+        let glyph_x = x + index as u32 * (GLYPH_WIDTH + GLYPH_SPACING) * scale;
+
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (0x80 >> column) != 0 {
+                    framebuffer.fill_rect(
+                        glyph_x + column * scale,
+                        y + row as u32 * scale,
+                        scale,
+                        scale,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::config::Config;
+use crate::memory::Memory;
+use crate::rom_database::rom_hash;
+use crate::tracer::parse_address;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+const CHEATS_DIRECTORY_NAME: &str = "cheats";
+const CHEAT_LINE_FORMAT: &str =
+    "on|off freeze ADDRESS VALUE NAME or on|off patch ADDRESS OPCODE NAME";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CheatKind {
+    Freeze { address: u16, value: u8 },  // Written back every frame.
+    Patch { address: u16, opcode: u16 }, // Replaces the instruction at `address`.
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Cheat {
+    pub name: String,
+    pub kind: CheatKind,
+    pub is_enabled: bool,
+    original: Option<[u8; 2]>, // Bytes under a patch while it is applied.
+}
+
+impl Cheat {
+    pub fn new(name: &str, kind: CheatKind) -> Cheat {
+        Cheat {
+            name: name.to_string(),
+            kind,
+            is_enabled: true,
+            original: None,
+        }
+    }
+}
+
+// $XDG_CONFIG_HOME/mch8/cheats/<hash>.cht, so a renamed ROM keeps its cheats.
+pub fn cheat_path(rom: &[u8]) -> Option<PathBuf> {
+    Config::user_dir().map(|dir| {
+        dir.join(CHEATS_DIRECTORY_NAME)
+            .join(format!("{:016X}.cht", rom_hash(rom)))
+    })
+}
+
+// The cheats of the loaded ROM, applied by the machine at the end of every
+// frame and after a reset or a loaded state.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn new() -> Cheats {
+        Cheats::default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    pub fn add(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+
+    // Returns the cheat toggled, None when there is no such cheat.
+    pub fn toggle(&mut self, index: usize) -> Option<&Cheat> {
+        let cheat = self.cheats.get_mut(index)?;
+        cheat.is_enabled = !cheat.is_enabled;
+        Some(cheat)
+    }
+
+    // A disabled patch puts back the bytes it replaced.
+    pub fn apply(&mut self, memory: &mut Memory) {
+        for cheat in &mut self.cheats {
+            match (cheat.kind, cheat.is_enabled) {
+                (CheatKind::Freeze { address, value }, true) => memory.write(address, value),
+                (CheatKind::Freeze { .. }, false) => {}
+                (CheatKind::Patch { address, opcode }, true) => {
+                    if cheat.original.is_none() {
+                        cheat.original = Some([memory.read(address), memory.read(address + 1)]);
+                    }
+                    for (offset, byte) in opcode.to_be_bytes().into_iter().enumerate() {
+                        memory.write(address + offset as u16, byte);
+                    }
+                }
+                (CheatKind::Patch { address, .. }, false) => {
+                    if let Some(original) = cheat.original.take() {
+                        for (offset, byte) in original.into_iter().enumerate() {
+                            memory.write(address + offset as u16, byte);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // One cheat per line : "on freeze 0x2F0 0x09 Infinite lives".
+    pub fn parse(text: &str) -> Result<Cheats, String> {
+        let mut cheats = Cheats::new();
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid_line = || {
+                format!(
+                    "[Error] Line {} : expected '{}' !",
+                    line_index + 1,
+                    CHEAT_LINE_FORMAT
+                )
+            };
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [state, kind, address, value, name @ ..] = fields.as_slice() else {
+                return Err(invalid_line());
+            };
+            let is_enabled = match *state {
+                "on" => true,
+                "off" => false,
+                _ => return Err(invalid_line()),
+            };
+            let address = parse_address(address)
+                .ok()
+                .filter(|address| *address < 0x1000)
+                .ok_or_else(invalid_line)?;
+            let value = parse_address(value).map_err(|_| invalid_line())?;
+
+            let kind = match *kind {
+                "freeze" => CheatKind::Freeze {
+                    address,
+                    value: u8::try_from(value).map_err(|_| invalid_line())?,
+                },
+                "patch" if address < 0xFFF => CheatKind::Patch {
+                    address,
+                    opcode: value,
+                },
+                _ => return Err(invalid_line()),
+            };
+
+            let mut cheat = Cheat::new(&name.join(" "), kind);
+            cheat.is_enabled = is_enabled;
+            cheats.add(cheat);
+        }
+
+        Ok(cheats)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("# {}\n", CHEAT_LINE_FORMAT);
+        for cheat in &self.cheats {
+            let state = if cheat.is_enabled { "on" } else { "off" };
+            let _ = match cheat.kind {
+                CheatKind::Freeze { address, value } => writeln!(
+                    text,
+                    "{} freeze {:#05X} {:#04X} {}",
+                    state, address, value, cheat.name
+                ),
+                CheatKind::Patch { address, opcode } => writeln!(
+                    text,
+                    "{} patch {:#05X} {:#06X} {}",
+                    state, address, opcode, cheat.name
+                ),
+            };
+        }
+
+        text
+    }
+
+    // A ROM without a cheat file has no cheats.
+    pub fn load_for_rom(rom: &[u8]) -> Result<Cheats, String> {
+        let Some(path) = cheat_path(rom).filter(|path| path.is_file()) else {
+            return Ok(Cheats::new());
+        };
+
+        let text = fs::read_to_string(&path).map_err(|_| {
+            format!(
+                "[Error] Unable to read the cheat file '{}' !",
+                path.display()
+            )
+        })?;
+        Cheats::parse(&text).map_err(|err_message| format!("{} ({})", err_message, path.display()))
+    }
+
+    pub fn save_for_rom(&self, rom: &[u8]) -> Result<(), String> {
+        let path = cheat_path(rom).ok_or("[Error] No user directory to keep the cheats in !")?;
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+
+        fs::write(&path, self.to_text()).map_err(|_| {
+            format!(
+                "[Error] Unable to write the cheat file '{}' !",
+                path.display()
+            )
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SearchComparison {
+    Equal(u8),
+    Changed,
+    Unchanged,
+    Greater,
+    Less,
+}
+
+impl SearchComparison {
+    fn matches(&self, previous: u8, current: u8) -> bool {
+        match self {
+            SearchComparison::Equal(value) => current == *value,
+            SearchComparison::Changed => current != previous,
+            SearchComparison::Unchanged => current == previous,
+            SearchComparison::Greater => current > previous,
+            SearchComparison::Less => current < previous,
+        }
+    }
+}
+
+// Narrows down where a ROM keeps a variable such as its lives or score : every
+// byte is a candidate at first, each filter keeps the ones whose value compares
+// as asked with the previous snapshot of memory.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RamSearch {
+    snapshot: Vec<u8>,
+    candidates: Vec<u16>,
+}
+
+impl RamSearch {
+    pub fn new(memory: &[u8]) -> RamSearch {
+        RamSearch {
+            snapshot: memory.to_vec(),
+            candidates: (0..memory.len() as u16).collect(),
+        }
+    }
+
+    pub fn filter(&mut self, memory: &[u8], comparison: SearchComparison) {
+        let snapshot = &self.snapshot;
+        self.candidates.retain(|address| {
+            let address = *address as usize;
+            comparison.matches(snapshot[address], memory[address])
+        });
+        self.snapshot = memory.to_vec();
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        &self.candidates
+    }
+
+    // As of the last snapshot.
+    pub fn value(&self, address: u16) -> u8 {
+        self.snapshot[address as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,87 @@
+use super::*;
+
+const CHEAT_FILE: &str = "# on|off freeze ADDRESS VALUE NAME or on|off patch ADDRESS OPCODE NAME
+on freeze 0x2F0 0x09 Infinite lives
+off patch 0x2A4 0x1300 Skip the title
+";
+
+#[test]
+fn cheat_files_round_trip() {
+    let cheats = Cheats::parse(CHEAT_FILE).unwrap();
+    assert_eq!(
+        cheats.iter().map(|cheat| cheat.kind).collect::<Vec<_>>(),
+        [
+            CheatKind::Freeze {
+                address: 0x2F0,
+                value: 0x09
+            },
+            CheatKind::Patch {
+                address: 0x2A4,
+                opcode: 0x1300
+            },
+        ]
+    );
+    assert_eq!(cheats.iter().nth(1).unwrap().name, "Skip the title");
+    assert!(!cheats.iter().nth(1).unwrap().is_enabled);
+    assert_eq!(cheats.to_text(), CHEAT_FILE);
+}
+
+#[test]
+fn invalid_cheat_lines_are_reported() {
+    for line in [
+        "on freeze 0x2F0",
+        "maybe freeze 0x2F0 0x09",
+        "on freeze 0x1000 0x09",
+        "on freeze 0x2F0 0x100",
+        "on patch 0xFFF 0x1200",
+        "on poke 0x2F0 0x09",
+    ] {
+        let err_message = Cheats::parse(&format!("\n{}", line)).unwrap_err();
+        assert!(err_message.starts_with("[Error] Line 2 :"), "{}", line);
+    }
+}
+
+#[test]
+fn disabled_patches_put_the_code_back() {
+    let mut memory = Memory::new();
+    memory.write(0x2A4, 0x6A);
+    memory.write(0x2A5, 0x02);
+    let mut cheats = Cheats::parse(CHEAT_FILE).unwrap();
+
+    cheats.apply(&mut memory);
+    assert_eq!(memory.read(0x2F0), 0x09);
+    assert_eq!(memory.read(0x2A4), 0x6A);
+
+    cheats.toggle(1);
+    cheats.apply(&mut memory);
+    assert_eq!([memory.read(0x2A4), memory.read(0x2A5)], [0x13, 0x00]);
+
+    cheats.toggle(1);
+    cheats.apply(&mut memory);
+    assert_eq!([memory.read(0x2A4), memory.read(0x2A5)], [0x6A, 0x02]);
+}
+
+#[test]
+fn ram_search_narrows_down_candidates() {
+    let mut memory = vec![0x00; 8];
+    memory[3] = 3;
+    memory[5] = 3;
+    let mut search = RamSearch::new(&memory);
+    assert_eq!(search.candidates().len(), 8);
+
+    search.filter(&memory, SearchComparison::Equal(3));
+    assert_eq!(search.candidates(), [3, 5]);
+
+    memory[3] = 2;
+    memory[6] = 1;
+    search.filter(&memory, SearchComparison::Changed);
+    assert_eq!(search.candidates(), [3]);
+    assert_eq!(search.value(3), 2);
+
+    search.filter(&memory, SearchComparison::Unchanged);
+    assert_eq!(search.candidates(), [3]);
+
+    memory[3] = 1;
+    search.filter(&memory, SearchComparison::Greater);
+    assert!(search.candidates().is_empty());
+}
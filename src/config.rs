@@ -0,0 +1,466 @@
+use crate::crt_filter::CrtSettings;
+use crate::flicker_filter::{FlickerMode, DEFAULT_FLICKER_DECAY};
+use crate::fontset::Fontset;
+use crate::frontend::Speed;
+use crate::logger::LogLevel;
+use crate::memory::{MemoryPolicy, MemoryProtection};
+use crate::palette::{Palette, PixelStyle, Rgb};
+use crate::tracer::{parse_address, TraceFilter, TraceFormat, Tracer};
+use crate::viewport::ScalingMode;
+use crate::virtual_machine::VirtualMachine;
+use crate::virtual_processor::StackDepth;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE_NAME: &str = "mch8.cfg";
+const BOOLEAN_KEYS: [&str; 5] = ["silent", "crt", "fullscreen", "show_fps", "stack_in_memory"];
+
+pub const OPTIONS_HELP: &str = "\
+[Info] Options :
+  --palette <name>                 classic, amber, green-phosphor, lcd, gameboy, high-contrast, octo, cga, silicon8
+  --background <#RRGGBB>           Custom background colour, #RGB also works (also --foreground, --color2, --color3)
+  --pixel-style <solid|grid|gap>   How each CHIP-8 pixel is drawn
+  --flicker <mode>                 Flicker reduction : off, blend, or-last-two or persistence
+  --flicker-decay <0-255>          How much of a pixel stays lit after a frame with persistence
+  --scaling <integer|fit|stretch>  SDL only, how the screen is scaled to the window
+  --fullscreen                     SDL only, start in fullscreen (toggled with F11 or Alt+Enter)
+  --speed <speed>                  A multiplier (0.25, 0.5, 2...) or uncapped, changed with [ and ]
+  --fast-forward <speed>           Speed while Tab is held, uncapped by default
+  --stack-depth <levels>           Nested calls before a stack overflow : vip (12), schip (16, default), a number or unlimited
+  --stack-in-memory                Keep the return addresses at 0xEA0-0xECF as the COSMAC VIP did
+  --memory-policy <policy>         Accesses past 4 KiB : wrap (default), fault or warn (ignored, warned once)
+  --font <name|file>               vip, dream6800, eti660, schip (default) or a file of 80 bytes, 240 with the big font
+  --font-address <address>         Where the font is loaded, 0x000 by default and up to 0x110
+  --memory-protection <mode>       Writes into the interpreter area or executed code : off (default), warn or break
+  --crt                            SDL only, emulate a CRT screen (scanlines, bloom, curvature, vignette)
+  --scanlines <0-100>              Strength of a single CRT effect (also --bloom, --curvature, --vignette)
+  --show-fps                       SDL only, show the frames and instructions per second (toggled with F3)
+  --rom-dir <directory>            SDL only, where the ROM browser looks for ROMs besides ./roms
+  --silent                         mch8-tui only, show a [BEEP] indicator instead of ringing the bell
+  --log-level <level>              off, error, warning, info (default) or debug
+  --trace <file>                   Write every executed instruction to a file
+  --trace-format <text|binary>     Format of the trace, text by default
+  --trace-range <0x200-0x2FF>      Only trace the instructions in this address range
+  --trace-kinds <kind,...>         Only trace flow, skip, arithmetic, memory, display, input, timer or unknown instructions
+  --profile <file>                 Write where the cycles went (top addresses, subroutines...) on exit
+  --profile-folded <file>          Write the call stacks in the folded format of flamegraph.pl on exit
+  --coverage <file>                Write the ROM's disassembly split into code and data on exit
+  --coverage-html <file>           Write a heatmap of the memory executed, read and written on exit
+  --gdb <host:port|unix:path>      Wait for GDB to connect before running the ROM
+  --config <file>                  Read the options from a file instead of ./mch8.cfg or ~/.config/mch8/mch8.cfg";
+
+// Settings shared by all frontends. They come from the configuration file, then
+// from the file's section named after the ROM (e.g. `[pong.rom]`), then from the
+// command line : every `key = value` of the file can also be given as
+// `--key value` (dashes and underscores are interchangeable).
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub rom_path: Option<String>,
+    pub pixel_style: PixelStyle,
+    pub flicker_mode: FlickerMode,
+    pub flicker_decay: u8,
+    pub crt_settings: CrtSettings,
+    pub scaling_mode: ScalingMode,
+    pub speed: Speed,
+    pub fast_forward_speed: Speed,
+    pub is_fullscreen: bool,
+    pub is_fps_visible: bool,
+    pub is_silent: bool,
+    pub stack_depth: StackDepth,
+    pub is_stack_in_memory: bool,
+    pub memory_policy: MemoryPolicy,
+    pub memory_protection: MemoryProtection,
+    pub fontset: Fontset,
+    pub font_address: u16,
+    pub rom_directory: Option<String>,
+    pub log_level: LogLevel,
+    pub trace_path: Option<String>,
+    pub trace_format: TraceFormat,
+    pub trace_filter: TraceFilter,
+    pub profile_path: Option<String>,
+    pub profile_folded_path: Option<String>,
+    pub coverage_path: Option<String>,
+    pub coverage_html_path: Option<String>,
+    pub gdb_address: Option<String>,
+    base_palette: Palette,
+    custom_colors: [Option<Rgb>; 4],
+    global_options: Vec<(String, String)>, // The file's options outside any section.
+    rom_sections: Vec<(String, Vec<(String, String)>)>,
+    command_line_options: Vec<(String, String)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(format!(
+            "[Error] Invalid value '{}' for '{}', expected true or false !",
+            value, key
+        )),
+    }
+}
+
+fn parse_percentage(key: &str, value: &str) -> Result<u8, String> {
+    match value.parse::<u8>() {
+        Ok(percentage) if percentage <= 100 => Ok(percentage),
+        _ => Err(format!(
+            "[Error] Invalid value '{}' for '{}', expected 0-100 !",
+            value, key
+        )),
+    }
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            rom_path: None,
+            pixel_style: PixelStyle::default(),
+            flicker_mode: FlickerMode::default(),
+            flicker_decay: DEFAULT_FLICKER_DECAY,
+            crt_settings: CrtSettings::default(),
+            scaling_mode: ScalingMode::default(),
+            speed: Speed::default(),
+            fast_forward_speed: Speed::Uncapped,
+            is_fullscreen: false,
+            is_fps_visible: false,
+            is_silent: false,
+            stack_depth: StackDepth::default(),
+            is_stack_in_memory: false,
+            memory_policy: MemoryPolicy::default(),
+            memory_protection: MemoryProtection::default(),
+            fontset: Fontset::default(),
+            font_address: 0x0,
+            rom_directory: None,
+            log_level: LogLevel::default(),
+            trace_path: None,
+            trace_format: TraceFormat::default(),
+            trace_filter: TraceFilter::default(),
+            profile_path: None,
+            profile_folded_path: None,
+            coverage_path: None,
+            coverage_html_path: None,
+            gdb_address: None,
+            base_palette: Palette::default(),
+            custom_colors: [None; 4],
+            global_options: Vec::new(),
+            rom_sections: Vec::new(),
+            command_line_options: Vec::new(),
+        }
+    }
+
+    // Arguments without the program name : options and the ROM path.
+    pub fn from_args(args: &[String]) -> Result<Config, String> {
+        let mut config = Config::new();
+        let mut rom_path = None;
+        let mut config_path = None;
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let Some(option) = arg.strip_prefix("--") else {
+                if rom_path.is_some() {
+                    return Err(format!("[Error] Unexpected argument '{}' !", arg));
+                }
+                rom_path = Some(arg.clone());
+                continue;
+            };
+
+            let (key, value) = if let Some((key, value)) = option.split_once('=') {
+                (key.replace('-', "_"), value.to_string())
+            } else if BOOLEAN_KEYS.contains(&option.replace('-', "_").as_str()) {
+                (option.replace('-', "_"), "true".to_string())
+            } else {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("[Error] Missing value for '--{}' !", option))?;
+                (option.replace('-', "_"), value.clone())
+            };
+
+            if key == "config" {
+                config_path = Some(value);
+            } else {
+                config.command_line_options.push((key, value));
+            }
+        }
+
+        match config_path {
+            Some(path) => config.load_file(&path)?,
+            None => config.load_default_file()?,
+        }
+
+        match rom_path {
+            Some(rom_path) => config.select_rom(&rom_path)?,
+            None => config.apply_command_line_options()?,
+        }
+
+        Ok(config)
+    }
+
+    // Applies the ROM's own section of the config file, then the command line
+    // options again so they keep the last word.
+    pub fn select_rom(&mut self, rom_path: &str) -> Result<(), String> {
+        self.rom_path = Some(rom_path.to_string());
+
+        let rom_file_name = Path::new(rom_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let rom_options = self
+            .rom_sections
+            .iter()
+            .find(|(section, _)| section.eq_ignore_ascii_case(&rom_file_name))
+            .map(|(_, options)| options.clone())
+            .unwrap_or_default();
+
+        for (key, value) in rom_options {
+            self.set(&key, &value)?;
+        }
+
+        self.apply_command_line_options()
+    }
+
+    // The settings before any ROM section was applied, for switching to another
+    // ROM without keeping the options of the previous one's section.
+    pub fn base(&self) -> Result<Config, String> {
+        let mut config = Config::new();
+        config.global_options = self.global_options.clone();
+        config.rom_sections = self.rom_sections.clone();
+        config.command_line_options = self.command_line_options.clone();
+
+        for (key, value) in &self.global_options {
+            config.set(key, value)?;
+        }
+        config.apply_command_line_options()?;
+
+        Ok(config)
+    }
+
+    fn apply_command_line_options(&mut self) -> Result<(), String> {
+        for (key, value) in self.command_line_options.clone() {
+            self.set(&key, &value)?;
+        }
+
+        Ok(())
+    }
+
+    // The named palette with the custom colours applied on top of it.
+    pub fn palette(&self) -> Palette {
+        let mut palette = self.base_palette;
+
+        for (color, custom_color) in palette.colors.iter_mut().zip(self.custom_colors) {
+            if let Some(custom_color) = custom_color {
+                *color = custom_color;
+            }
+        }
+
+        palette
+    }
+
+    // The font only reaches memory once the machine is reset or loads a ROM.
+    pub fn configure_machine(
+        &self,
+        virtual_machine: &mut VirtualMachine,
+    ) -> Result<(), &'static str> {
+        virtual_machine.set_fontset(self.fontset.clone());
+        virtual_machine.set_font_address(self.font_address)?;
+        virtual_machine.set_stack_depth(self.stack_depth);
+        virtual_machine.set_stack_in_memory(self.is_stack_in_memory);
+        virtual_machine.set_memory_policy(self.memory_policy);
+        virtual_machine.set_memory_protection(self.memory_protection);
+        Ok(())
+    }
+
+    // None unless a trace file was asked for.
+    pub fn create_tracer(&self) -> Result<Option<Tracer>, String> {
+        self.trace_path
+            .as_ref()
+            .map(|path| Tracer::create(path, self.trace_format, self.trace_filter.clone()))
+            .transpose()
+    }
+
+    pub fn is_profiling(&self) -> bool {
+        self.profile_path.is_some() || self.profile_folded_path.is_some()
+    }
+
+    pub fn is_recording_coverage(&self) -> bool {
+        self.coverage_path.is_some() || self.coverage_html_path.is_some()
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key.replace('-', "_").as_str() {
+            "palette" => {
+                self.base_palette = Palette::by_name(value)
+                    .ok_or_else(|| format!("[Error] Unknown palette '{}' !", value))?;
+            }
+            "background" => self.custom_colors[0] = Some(Rgb::from_hex(value)?),
+            "foreground" => self.custom_colors[1] = Some(Rgb::from_hex(value)?),
+            "color2" => self.custom_colors[2] = Some(Rgb::from_hex(value)?),
+            "color3" => self.custom_colors[3] = Some(Rgb::from_hex(value)?),
+            "pixel_style" => {
+                self.pixel_style = PixelStyle::from_name(value)
+                    .ok_or_else(|| format!("[Error] Unknown pixel style '{}' !", value))?;
+            }
+            "flicker" => {
+                self.flicker_mode = FlickerMode::from_name(value)
+                    .ok_or_else(|| format!("[Error] Unknown flicker mode '{}' !", value))?;
+            }
+            "flicker_decay" => {
+                self.flicker_decay = value.parse().map_err(|_| {
+                    format!(
+                        "[Error] Invalid flicker decay '{}', expected 0-255 !",
+                        value
+                    )
+                })?;
+            }
+            "crt" => {
+                self.crt_settings = if parse_bool(key, value)? {
+                    CrtSettings::PRESET
+                } else {
+                    CrtSettings::default()
+                };
+            }
+            "scanlines" => self.crt_settings.scanlines = parse_percentage(key, value)?,
+            "bloom" => self.crt_settings.bloom = parse_percentage(key, value)?,
+            "curvature" => self.crt_settings.curvature = parse_percentage(key, value)?,
+            "vignette" => self.crt_settings.vignette = parse_percentage(key, value)?,
+            "scaling" => {
+                self.scaling_mode = ScalingMode::from_name(value)
+                    .ok_or_else(|| format!("[Error] Unknown scaling mode '{}' !", value))?;
+            }
+            "speed" => {
+                self.speed = Speed::from_name(value)
+                    .ok_or_else(|| format!("[Error] Invalid speed '{}' !", value))?;
+            }
+            "fast_forward" => {
+                self.fast_forward_speed = Speed::from_name(value)
+                    .ok_or_else(|| format!("[Error] Invalid speed '{}' !", value))?;
+            }
+            "fullscreen" => self.is_fullscreen = parse_bool(key, value)?,
+            "show_fps" => self.is_fps_visible = parse_bool(key, value)?,
+            "silent" => self.is_silent = parse_bool(key, value)?,
+            "stack_depth" => {
+                self.stack_depth = StackDepth::from_name(value)
+                    .ok_or_else(|| format!("[Error] Invalid stack depth '{}' !", value))?;
+            }
+            "stack_in_memory" => self.is_stack_in_memory = parse_bool(key, value)?,
+            "memory_policy" => {
+                self.memory_policy = MemoryPolicy::from_name(value)
+                    .ok_or_else(|| format!("[Error] Unknown memory policy '{}' !", value))?;
+            }
+            "font" => self.fontset = Fontset::from_name_or_file(value)?,
+            "font_address" => self.font_address = parse_address(value)?,
+            "memory_protection" => {
+                self.memory_protection = MemoryProtection::from_name(value)
+                    .ok_or_else(|| format!("[Error] Unknown memory protection '{}' !", value))?;
+            }
+            "rom_dir" => self.rom_directory = Some(value.to_string()),
+            "log_level" => {
+                self.log_level = LogLevel::from_name(value)
+                    .ok_or_else(|| format!("[Error] Unknown log level '{}' !", value))?;
+            }
+            "trace" => self.trace_path = Some(value.to_string()),
+            "trace_format" => {
+                self.trace_format = TraceFormat::from_name(value)
+                    .ok_or_else(|| format!("[Error] Unknown trace format '{}' !", value))?;
+            }
+            "trace_range" => self.trace_filter.set_address_range(value)?,
+            "trace_kinds" => self.trace_filter.set_kinds(value)?,
+            "profile" => self.profile_path = Some(value.to_string()),
+            "profile_folded" => self.profile_folded_path = Some(value.to_string()),
+            "coverage" => self.coverage_path = Some(value.to_string()),
+            "coverage_html" => self.coverage_html_path = Some(value.to_string()),
+            "gdb" => self.gdb_address = Some(value.to_string()),
+            _ => return Err(format!("[Error] Unknown option '{}' !", key)),
+        }
+
+        Ok(())
+    }
+
+    // Global options are applied right away, the ones in a ROM section are kept
+    // until that ROM is selected.
+    pub fn parse(&mut self, text: &str) -> Result<(), String> {
+        let mut current_section: Option<usize> = None;
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(section) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                self.rom_sections
+                    .push((section.trim().to_string(), Vec::new()));
+                current_section = Some(self.rom_sections.len() - 1);
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!(
+                    "[Error] Line {} : expected 'key = value' !",
+                    line_index + 1
+                ));
+            };
+
+            let (key, value) = (key.trim(), value.trim());
+            match current_section {
+                Some(section) => self.rom_sections[section]
+                    .1
+                    .push((key.to_string(), value.to_string())),
+                None => {
+                    self.set(key, value).map_err(|err_message| {
+                        format!("{} (line {})", err_message, line_index + 1)
+                    })?;
+                    self.global_options
+                        .push((key.to_string(), value.to_string()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn load_file(&mut self, path: &str) -> Result<(), String> {
+        let text = fs::read_to_string(path)
+            .map_err(|_| format!("[Error] Unable to read the config file '{}' !", path))?;
+
+        self.parse(&text)
+    }
+
+    // $XDG_CONFIG_HOME/mch8 (~/.config/mch8), where the user's files are kept.
+    pub fn user_dir() -> Option<PathBuf> {
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        Some(config_dir.join("mch8"))
+    }
+
+    // ./mch8.cfg, then $XDG_CONFIG_HOME/mch8/mch8.cfg (~/.config/mch8/mch8.cfg).
+    pub fn default_path() -> Option<PathBuf> {
+        let local_path = PathBuf::from(CONFIG_FILE_NAME);
+        if local_path.is_file() {
+            return Some(local_path);
+        }
+
+        let user_path = Config::user_dir()?.join(CONFIG_FILE_NAME);
+
+        user_path.is_file().then_some(user_path)
+    }
+
+    // A missing default config file is not an error, an invalid one is.
+    pub fn load_default_file(&mut self) -> Result<(), String> {
+        match Config::default_path() {
+            Some(path) => self.load_file(&path.to_string_lossy()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,126 @@
+use super::*;
+
+fn parsed(text: &str) -> Result<Config, String> {
+    let mut config = Config::new();
+    config.parse(text)?;
+    Ok(config)
+}
+
+#[test]
+fn palette_and_custom_colours() {
+    let config = parsed("palette = amber\nforeground = #F80\nbackground = 102030\n").unwrap();
+    let palette = config.palette();
+    assert_eq!(palette.background(), Rgb::from_u32(0x102030));
+    assert_eq!(palette.foreground(), Rgb::from_u32(0xFF8800));
+    assert_eq!(
+        palette.colors[2..],
+        Palette::by_name("amber").unwrap().colors[2..]
+    );
+
+    // The custom colours stay over whichever palette comes after them.
+    let config = parsed("foreground = #00ff00\npalette = lcd\n").unwrap();
+    assert_eq!(config.palette().foreground(), Rgb::from_u32(0x00FF00));
+    assert_eq!(
+        config.palette().background(),
+        Palette::by_name("lcd").unwrap().background()
+    );
+}
+
+#[test]
+fn invalid_palettes_and_colours() {
+    assert_eq!(
+        parsed("palette = sepia").err(),
+        Some("[Error] Unknown palette 'sepia' ! (line 1)".to_string())
+    );
+    assert_eq!(
+        parsed("\nforeground = #12345").err(),
+        Some("[Error] Invalid colour '#12345', expected #RRGGBB or #RGB ! (line 2)".to_string())
+    );
+    assert!(parsed("background = blue").is_err());
+    assert!(parsed("background").is_err());
+}
+
+#[test]
+fn command_line_colours() {
+    let config_path = std::env::temp_dir().join(format!("mch8-test-{}.cfg", std::process::id()));
+    fs::write(&config_path, "palette = cga\nbackground = #111\n").unwrap();
+
+    let args: Vec<String> = [
+        &format!("--config={}", config_path.display()),
+        "--palette",
+        "gameboy",
+        "--foreground=#abc",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    let config = Config::from_args(&args);
+    let _ = fs::remove_file(&config_path);
+
+    let palette = config.unwrap().palette();
+    assert_eq!(palette.background(), Rgb::from_u32(0x111111));
+    assert_eq!(palette.foreground(), Rgb::from_u32(0xAABBCC));
+    assert_eq!(
+        palette.colors[2],
+        Palette::by_name("gameboy").unwrap().colors[2]
+    );
+
+    let args = ["--background".to_string(), "#ABCDEFG".to_string()];
+    assert!(Config::from_args(&args).is_err());
+}
+
+#[test]
+fn machine_settings_are_applied() {
+    let config =
+        parsed("stack_depth = vip\nstack_in_memory = on\nmemory_policy = fault\nmemory_protection = break\nfont_address = 0x50\n")
+            .unwrap();
+    let mut virtual_machine = VirtualMachine::new();
+    config.configure_machine(&mut virtual_machine).unwrap();
+    virtual_machine.reset();
+
+    let virtual_processor = virtual_machine.virtual_processor();
+    assert_eq!(virtual_processor.stack_depth(), StackDepth::Limited(12));
+    assert!(virtual_processor.is_stack_in_memory());
+    assert_eq!(virtual_processor.memory_policy(), MemoryPolicy::Fault);
+    assert_eq!(
+        virtual_processor.memory_protection(),
+        MemoryProtection::Break
+    );
+    assert_eq!(virtual_processor.font_address(), 0x50);
+
+    let config = parsed("font_address = 0x1F0\n").unwrap();
+    assert!(config.configure_machine(&mut virtual_machine).is_err());
+}
+
+#[test]
+fn switching_roms_drops_the_previous_rom_section() {
+    let mut config = parsed(
+        "speed = 30\n\
+         [a.rom]\n\
+         flicker = blend\n\
+         stack_depth = vip\n\
+         memory_policy = fault\n\
+         font = vip\n\
+         font_address = 0x50\n\
+         palette = amber\n\
+         [b.rom]\n\
+         pixel_style = grid\n",
+    )
+    .unwrap();
+    config.select_rom("roms/a.rom").unwrap();
+    assert_eq!(config.font_address, 0x50);
+
+    let mut config = config.base().unwrap();
+    config.select_rom("roms/b.rom").unwrap();
+    let default = Config::new();
+    assert_eq!(config.rom_path.as_deref(), Some("roms/b.rom"));
+    assert_eq!(config.flicker_mode, default.flicker_mode);
+    assert_eq!(config.stack_depth, default.stack_depth);
+    assert_eq!(config.memory_policy, default.memory_policy);
+    assert_eq!(config.fontset, default.fontset);
+    assert_eq!(config.font_address, default.font_address);
+    assert_eq!(config.palette(), default.palette());
+    // The global options and B's own section still apply.
+    assert_eq!(config.speed, Speed::from_name("30").unwrap());
+    assert_eq!(config.pixel_style, PixelStyle::Grid);
+}
@@ -0,0 +1,278 @@
+use crate::disassembler::decode;
+use crate::globals::*;
+use crate::memory::TOTAL_MEMORY_BYTES;
+use std::fmt::Write;
+use std::fs;
+
+const HEATMAP_COLUMNS: usize = 64;
+const UNTOUCHED_COLOR: &str = "#202020";
+
+// How each byte of memory was used while the ROM ran.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ByteCoverage {
+    pub executed: u64, // Instructions starting at this address.
+    pub read: u64,     // By DXYN and FX65.
+    pub written: u64,  // By FX33 and FX55.
+}
+
+impl ByteCoverage {
+    pub fn is_touched(&self) -> bool {
+        self.executed > 0 || self.read > 0 || self.written > 0
+    }
+}
+
+fn wrap_address(address: u16) -> usize {
+    address as usize % TOTAL_MEMORY_BYTES as usize
+}
+
+// Records which bytes of memory are code and which are data, to tell them
+// apart in the disassembly and to spot the parts of a ROM never run.
+pub struct Coverage {
+    bytes: Vec<ByteCoverage>,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        Coverage {
+            bytes: vec![ByteCoverage::default(); TOTAL_MEMORY_BYTES as usize],
+        }
+    }
+
+    pub fn byte(&self, address: u16) -> ByteCoverage {
+        self.bytes[wrap_address(address)]
+    }
+
+    // To be called before each instruction is executed, the index register
+    // still pointing where the instruction reads or writes.
+    pub fn record_instruction(&mut self, program_counter: u16, opcode: u16, index_register: u16) {
+        self.bytes[wrap_address(program_counter)].executed += 1;
+
+        let x = (opcode & 0x0F00) >> 8;
+        let n = opcode & 0x000F;
+        match opcode & 0xF0FF {
+            0xF033 => self.record_range(index_register, 3, |byte| &mut byte.written),
+            0xF055 => self.record_range(index_register, x + 1, |byte| &mut byte.written),
+            0xF065 => self.record_range(index_register, x + 1, |byte| &mut byte.read),
+            _ if opcode & 0xF000 == 0xD000 => {
+                self.record_range(index_register, n, |byte| &mut byte.read)
+            }
+            _ => {}
+        }
+    }
+
+    fn record_range(
+        &mut self,
+        start: u16,
+        length: u16,
+        counter: fn(&mut ByteCoverage) -> &mut u64,
+    ) {
+        for offset in 0..length {
+            *counter(&mut self.bytes[wrap_address(start.wrapping_add(offset))]) += 1;
+        }
+    }
+
+    // Whether the byte belongs to an executed instruction, as its first or second half.
+    fn is_code(&self, address: usize) -> bool {
+        self.bytes[address].executed > 0 || (address > 0 && self.bytes[address - 1].executed > 0)
+    }
+
+    // The ROM's bytes, as instructions where they were executed and as data
+    // everywhere else.
+    pub fn annotated_disassembly(&self, rom: &[u8]) -> String {
+        let start = PROCESSOR_INTERNAL_PROGRAM_COUNTER_START as usize;
+        let end = (start + rom.len()).min(TOTAL_MEMORY_BYTES as usize);
+        let rom_byte = |address: usize| rom[address - start];
+
+        let code_bytes = (start..end)
+            .filter(|address| self.is_code(*address))
+            .count();
+        let data_bytes = (start..end)
+            .filter(|address| !self.is_code(*address) && self.bytes[*address].is_touched())
+            .count();
+        let untouched_bytes = end - start - code_bytes - data_bytes;
+
+        let mut listing = String::new();
+        let _ = writeln!(
+            listing,
+            "; Coverage of {} bytes : {} executed, {} used as data, {} never touched",
+            end - start,
+            code_bytes,
+            data_bytes,
+            untouched_bytes
+        );
+        let _ = writeln!(
+            listing,
+            "; A `!` marks the instructions overwritten while the ROM ran.\n"
+        );
+
+        let mut address = start;
+        while address < end {
+            let coverage = self.bytes[address];
+
+            if coverage.executed > 0 && address + 1 < end {
+                let opcode = (rom_byte(address) as u16) << 8 | rom_byte(address + 1) as u16;
+                let is_modified = coverage.written > 0 || self.bytes[address + 1].written > 0;
+                let _ = writeln!(
+                    listing,
+                    "{:#05X}  {:04X}  {:<18} ; executed {}{}",
+                    address,
+                    opcode,
+                    decode(opcode).mnemonic,
+                    coverage.executed,
+                    if is_modified { " !" } else { "" }
+                );
+                address += 2;
+                continue;
+            }
+
+            let byte = rom_byte(address);
+            let mut comment = Vec::new();
+            if coverage.read > 0 {
+                comment.push(format!("read {}", coverage.read));
+            }
+            if coverage.written > 0 {
+                comment.push(format!("written {}", coverage.written));
+            }
+
+            // Sprite rows are easier to recognize drawn than in hexadecimal.
+            let sprite_row: String = (0..8)
+                .map(|bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' })
+                .collect();
+
+            let mnemonic = format!("DB {:#04X}", byte);
+            let _ = writeln!(
+                listing,
+                "{:#05X}  {:02X}    {:<18} ; {} {}",
+                address,
+                byte,
+                mnemonic,
+                sprite_row,
+                if comment.is_empty() {
+                    "unused".to_string()
+                } else {
+                    comment.join(", ")
+                }
+            );
+            address += 1;
+        }
+
+        listing
+    }
+
+    // Intensity of a counter relative to the busiest byte, on a log scale so
+    // the rarely run code still shows.
+    fn color_channel(count: u64, max_count: u64) -> u8 {
+        if count == 0 {
+            return 0;
+        }
+
+        let ratio = ((count + 1) as f64).ln() / ((max_count + 1) as f64).ln();
+        (80.0 + ratio * 175.0) as u8
+    }
+
+    // All of memory as a grid of cells : green for code, blue for data read,
+    // red for data written.
+    pub fn html_heatmap(&self, rom: &[u8]) -> String {
+        let max_count =
+            |counter: fn(&ByteCoverage) -> u64| self.bytes.iter().map(counter).max().unwrap_or(0);
+        let max_executed = max_count(|byte| byte.executed);
+        let max_read = max_count(|byte| byte.read);
+        let max_written = max_count(|byte| byte.written);
+
+        let rom_start = PROCESSOR_INTERNAL_PROGRAM_COUNTER_START as usize;
+        let mut html = String::new();
+        html.push_str(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>mch8 coverage</title>\n\
+             <style>\n\
+             body { background: #101010; color: #E0E0E0; font-family: monospace; }\n\
+             table { border-collapse: collapse; }\n\
+             td { width: 12px; height: 12px; padding: 0; border: 1px solid #101010; }\n\
+             th { font-weight: normal; padding-right: 8px; text-align: right; }\n\
+             .legend span { display: inline-block; width: 12px; height: 12px; margin: 0 4px 0 16px; }\n\
+             </style>\n</head>\n<body>\n<h1>Memory coverage</h1>\n",
+        );
+        let _ = writeln!(
+            html,
+            "<p class=\"legend\"><span style=\"background: #00FF00\"></span>executed\
+             <span style=\"background: #0000FF\"></span>read\
+             <span style=\"background: #FF0000\"></span>written\
+             <span style=\"background: {}\"></span>untouched</p>",
+            UNTOUCHED_COLOR
+        );
+        html.push_str("<table>\n");
+
+        for row_start in (0..self.bytes.len()).step_by(HEATMAP_COLUMNS) {
+            let _ = write!(html, "<tr><th>{:#05X}</th>", row_start);
+
+            for address in row_start..row_start + HEATMAP_COLUMNS {
+                let coverage = self.bytes[address];
+                let color = if coverage.is_touched() {
+                    format!(
+                        "#{:02X}{:02X}{:02X}",
+                        Self::color_channel(coverage.written, max_written),
+                        Self::color_channel(coverage.executed, max_executed),
+                        Self::color_channel(coverage.read, max_read)
+                    )
+                } else {
+                    UNTOUCHED_COLOR.to_string()
+                };
+
+                let mut title = format!(
+                    "{:#05X} : executed {}, read {}, written {}",
+                    address, coverage.executed, coverage.read, coverage.written
+                );
+                if coverage.executed > 0
+                    && address >= rom_start
+                    && address + 1 < rom_start + rom.len()
+                {
+                    let opcode = (rom[address - rom_start] as u16) << 8
+                        | rom[address + 1 - rom_start] as u16;
+                    let _ = write!(title, " ({})", decode(opcode).mnemonic);
+                }
+
+                let _ = write!(
+                    html,
+                    "<td style=\"background: {}\" title=\"{}\"></td>",
+                    color, title
+                );
+            }
+            html.push_str("</tr>\n");
+        }
+
+        html.push_str("</table>\n</body>\n</html>\n");
+        html
+    }
+
+    pub fn save(
+        &self,
+        rom: &[u8],
+        disassembly_path: Option<&str>,
+        html_path: Option<&str>,
+    ) -> Result<(), String> {
+        if let Some(path) = disassembly_path {
+            fs::write(path, self.annotated_disassembly(rom)).map_err(|_| {
+                format!(
+                    "[Error] Unable to write the coverage disassembly '{}' !",
+                    path
+                )
+            })?;
+        }
+
+        if let Some(path) = html_path {
+            fs::write(path, self.html_heatmap(rom)).map_err(|_| {
+                format!("[Error] Unable to write the coverage heatmap '{}' !", path)
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
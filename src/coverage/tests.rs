@@ -0,0 +1,135 @@
+use super::*;
+use crate::virtual_machine::VirtualMachine;
+
+// Draws a sprite, writes the BCD of V0 past it and loops.
+const PROGRAM: [u8; 20] = [
+    0xA2, 0x0C, // 0x200 LD I, 0x20C
+    0xD0, 0x12, // 0x202 DRW V0, V1, 2
+    0xA2, 0x10, // 0x204 LD I, 0x210
+    0xF0, 0x33, // 0x206 LD B, V0
+    0x12, 0x08, // 0x208 JP 0x208
+    0x00, 0x00, // 0x20A never touched
+    0xF0, 0x90, // 0x20C sprite
+    0x00, 0x00, // 0x20E never touched
+    0x00, 0x00, 0x00, // 0x210 BCD
+    0x00, // 0x213 never touched
+];
+
+fn coverage_of(program: &[u8], instructions: u16) -> VirtualMachine {
+    let mut virtual_machine = VirtualMachine::new();
+    virtual_machine.load_rom_bytes(program).unwrap();
+    virtual_machine.set_coverage(Some(Coverage::new()));
+    virtual_machine.run_frame(instructions);
+    virtual_machine
+}
+
+#[test]
+fn bytes_are_counted_by_use() {
+    let virtual_machine = coverage_of(&PROGRAM, 7);
+    let coverage = virtual_machine.coverage().unwrap();
+
+    assert_eq!(coverage.byte(0x200).executed, 1);
+    assert_eq!(coverage.byte(0x208).executed, 3);
+    assert_eq!(coverage.byte(0x209), ByteCoverage::default());
+    assert_eq!(coverage.byte(0x20C).read, 1);
+    assert_eq!(coverage.byte(0x20D).read, 1);
+    assert_eq!(coverage.byte(0x212).written, 1);
+    assert!(!coverage.byte(0x20E).is_touched());
+    assert!(!coverage.byte(0x213).is_touched());
+}
+
+#[test]
+fn annotated_disassembly() {
+    let virtual_machine = coverage_of(&PROGRAM, 7);
+    let listing = virtual_machine
+        .coverage()
+        .unwrap()
+        .annotated_disassembly(&PROGRAM);
+    let lines: Vec<&str> = listing.lines().collect();
+
+    assert_eq!(
+        lines[0],
+        "; Coverage of 20 bytes : 10 executed, 5 used as data, 5 never touched"
+    );
+    assert_eq!(
+        lines[3],
+        format!(
+            "{:#05X}  {:04X}  {:<18} ; executed {}",
+            0x200, 0xA20C, "LD I, 0x20C", 1
+        )
+    );
+    assert_eq!(
+        lines[7],
+        format!(
+            "{:#05X}  {:04X}  {:<18} ; executed {}",
+            0x208, 0x1208, "JP 0x208", 3
+        )
+    );
+    assert_eq!(
+        lines[8],
+        format!(
+            "{:#05X}  {:02X}    {:<18} ; ........ unused",
+            0x20A, 0x00, "DB 0x00"
+        )
+    );
+    assert_eq!(
+        lines[10],
+        format!(
+            "{:#05X}  {:02X}    {:<18} ; ####.... read 1",
+            0x20C, 0xF0, "DB 0xF0"
+        )
+    );
+    assert_eq!(
+        lines[14],
+        format!(
+            "{:#05X}  {:02X}    {:<18} ; ........ written 1",
+            0x210, 0x00, "DB 0x00"
+        )
+    );
+    assert_eq!(lines.len(), 3 + 5 + 10);
+}
+
+#[test]
+fn overwritten_instructions_are_marked() {
+    // LD I, 0x200 ; LD B, V0 over both instructions.
+    let program = [0xA2, 0x00, 0xF0, 0x33];
+    let virtual_machine = coverage_of(&program, 2);
+    let listing = virtual_machine
+        .coverage()
+        .unwrap()
+        .annotated_disassembly(&program);
+
+    assert!(listing.contains("; executed 1 !\n0x202"));
+    assert!(listing.ends_with("; executed 1 !\n"));
+}
+
+#[test]
+fn html_heatmap() {
+    let virtual_machine = coverage_of(&PROGRAM, 7);
+    let html = virtual_machine.coverage().unwrap().html_heatmap(&PROGRAM);
+
+    assert_eq!(html.matches("<tr>").count(), 4096 / HEATMAP_COLUMNS);
+    assert_eq!(html.matches("<td ").count(), 4096);
+    for (color, title) in [
+        (
+            "#00FF00",
+            "0x208 : executed 3, read 0, written 0 (JP 0x208)",
+        ),
+        ("#0000FF", "0x20C : executed 0, read 1, written 0"),
+        ("#FF0000", "0x210 : executed 0, read 0, written 1"),
+        (UNTOUCHED_COLOR, "0x20A : executed 0, read 0, written 0"),
+    ] {
+        let cell = format!(
+            "<td style=\"background: {}\" title=\"{}\"></td>",
+            color, title
+        );
+        assert!(html.contains(&cell), "{}", cell);
+    }
+
+    // Run once against the three times of the loop, on a log scale.
+    let cell = format!(
+        "<td style=\"background: #00{:02X}00\" title=\"0x200",
+        Coverage::color_channel(1, 3)
+    );
+    assert!(html.contains(&cell), "{}", cell);
+}
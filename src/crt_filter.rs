@@ -0,0 +1,177 @@
+use crate::framebuffer::Framebuffer;
+
+const OUTSIDE_SCREEN: u32 = u32::MAX;
+const BLOOM_RADIUS: usize = 3;
+
+// Strength of each effect, from 0 (disabled) to 100.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct CrtSettings {
+    pub scanlines: u8,
+    pub bloom: u8,
+    pub curvature: u8,
+    pub vignette: u8,
+}
+
+impl CrtSettings {
+    // What `--crt` turns on, a TV look that still keeps the game readable.
+    pub const PRESET: CrtSettings = CrtSettings {
+        scanlines: 40,
+        bloom: 30,
+        curvature: 20,
+        vignette: 40,
+    };
+
+    pub fn is_enabled(&self) -> bool {
+        *self != CrtSettings::default()
+    }
+}
+
+// Emulates a CRT screen on the CPU. The per pixel geometry (curvature) and
+// shading (scanlines, vignette) only depend on the image size, so they are
+// computed once and reused for every frame.
+pub struct CrtFilter {
+    settings: CrtSettings,
+    width: u32,
+    height: u32,
+    source_indices: Vec<u32>,
+    shades: Vec<u16>, // 256 means unchanged.
+    glow: Vec<u16>,
+}
+
+impl CrtFilter {
+    pub fn new(settings: CrtSettings) -> CrtFilter {
+        CrtFilter {
+            settings,
+            width: 0,
+            height: 0,
+            source_indices: Vec::new(),
+            shades: Vec::new(),
+            glow: Vec::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.settings.is_enabled()
+    }
+
+    fn prepare(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.source_indices.clear();
+        self.shades.clear();
+
+        let curvature = self.settings.curvature as f32 / 100.0 * 0.25;
+        let vignette = self.settings.vignette as f32 / 100.0;
+        let scanlines = self.settings.scanlines as f32 / 100.0;
+
+        for y in 0..height {
+            for x in 0..width {
+                // Coordinates from -1 to 1, the centre of the screen being 0.
+                let u = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                let v = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+
+                let source_u = u * (1.0 + curvature * v * v);
+                let source_v = v * (1.0 + curvature * u * u);
+                if source_u.abs() > 1.0 || source_v.abs() > 1.0 {
+                    self.source_indices.push(OUTSIDE_SCREEN);
+                    self.shades.push(0);
+                    continue;
+                }
+
+                let source_x = (((source_u + 1.0) / 2.0 * width as f32) as u32).min(width - 1);
+                let source_y = (((source_v + 1.0) / 2.0 * height as f32) as u32).min(height - 1);
+                self.source_indices.push(source_y * width + source_x);
+
+                let mut shade = 1.0 - vignette * (u * u + v * v) / 2.0;
+                if source_y % 2 == 1 {
+                    shade *= 1.0 - scanlines;
+                }
+                self.shades.push((shade.max(0.0) * 256.0) as u16);
+            }
+        }
+    }
+
+    // Box blur of the source, horizontally then vertically, into `self.glow`.
+    fn compute_glow(&mut self, source: &Framebuffer) {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let pixels = source.pixels();
+        let window = (BLOOM_RADIUS * 2 + 1) as u32;
+
+        let mut horizontal = vec![0u16; width * height * 3];
+        for y in 0..height {
+            for channel in 0..3 {
+                let mut sum: u32 = 0;
+                for x in 0..(width + BLOOM_RADIUS) {
+                    if x < width {
+                        sum += pixels[(y * width + x) * 4 + channel] as u32;
+                    }
+                    if x >= window as usize {
+                        sum -= pixels[(y * width + x - window as usize) * 4 + channel] as u32;
+                    }
+                    if x >= BLOOM_RADIUS {
+                        horizontal[(y * width + x - BLOOM_RADIUS) * 3 + channel] =
+                            (sum / window) as u16;
+                    }
+                }
+            }
+        }
+
+        self.glow.resize(width * height * 3, 0);
+        for x in 0..width {
+            for channel in 0..3 {
+                let mut sum: u32 = 0;
+                for y in 0..(height + BLOOM_RADIUS) {
+                    if y < height {
+                        sum += horizontal[(y * width + x) * 3 + channel] as u32;
+                    }
+                    if y >= window as usize {
+                        sum -= horizontal[((y - window as usize) * width + x) * 3 + channel] as u32;
+                    }
+                    if y >= BLOOM_RADIUS {
+                        self.glow[((y - BLOOM_RADIUS) * width + x) * 3 + channel] =
+                            (sum / window) as u16;
+                    }
+                }
+            }
+        }
+    }
+
+    // `output` must have the same size as `source`.
+    pub fn apply(&mut self, source: &Framebuffer, output: &mut Framebuffer) {
+        if source.width() != self.width || source.height() != self.height {
+            self.prepare(source.width(), source.height());
+        }
+
+        let bloom = self.settings.bloom as u32;
+        if bloom > 0 {
+            self.compute_glow(source);
+        }
+
+        let source_pixels = source.pixels();
+        let output_pixels = output.pixels_mut();
+
+        for (index, source_index) in self.source_indices.iter().enumerate() {
+            let output_pixel = &mut output_pixels[index * 4..index * 4 + 4];
+
+            if *source_index == OUTSIDE_SCREEN {
+                output_pixel.copy_from_slice(&[0, 0, 0, 0xFF]);
+                continue;
+            }
+
+            let source_index = *source_index as usize;
+            let shade = self.shades[index] as u32;
+            for channel in 0..3 {
+                let mut value = source_pixels[source_index * 4 + channel] as u32;
+                if bloom > 0 {
+                    value += self.glow[source_index * 3 + channel] as u32 * bloom / 100;
+                }
+                output_pixel[channel] = ((value.min(255) * shade) >> 8).min(255) as u8;
+            }
+            output_pixel[3] = 0xFF;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
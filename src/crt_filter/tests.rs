@@ -0,0 +1,120 @@
+use super::*;
+use crate::palette::Rgb;
+
+fn gray(value: u8) -> Rgb {
+    Rgb {
+        r: value,
+        g: value,
+        b: value,
+    }
+}
+
+fn filled(width: u32, height: u32, value: u8) -> Framebuffer {
+    let mut framebuffer = Framebuffer::new(width, height);
+    framebuffer.clear(gray(value));
+    framebuffer
+}
+
+// Glow of the red channel at (x, y).
+fn glow(filter: &CrtFilter, x: usize, y: usize) -> u16 {
+    filter.glow[(y * filter.width as usize + x) * 3]
+}
+
+fn glow_of(source: &Framebuffer) -> CrtFilter {
+    let mut filter = CrtFilter::new(CrtSettings::default());
+    filter.prepare(source.width(), source.height());
+    filter.compute_glow(source);
+    filter
+}
+
+#[test]
+fn default_settings_leave_the_image_unchanged() {
+    let mut source = Framebuffer::new(64, 32);
+    for y in 0..32 {
+        for x in 0..64 {
+            source.set_pixel(x, y, gray((x * 4 + y) as u8));
+        }
+    }
+
+    let mut filter = CrtFilter::new(CrtSettings::default());
+    assert!(!filter.is_enabled());
+    let mut output = Framebuffer::new(64, 32);
+    filter.apply(&source, &mut output);
+    assert_eq!(output.pixels(), source.pixels());
+}
+
+#[test]
+fn glow_averages_over_the_bloom_window() {
+    let filter = glow_of(&filled(20, 20, 70));
+    assert_eq!(glow(&filter, 10, 10), 70);
+
+    // Past the edges counts as black : 4 of the 7 pixels of the window.
+    assert_eq!(glow(&filter, 0, 10), 40);
+    assert_eq!(glow(&filter, 19, 10), 40);
+    assert_eq!(glow(&filter, 0, 0), 4 * 40 / 7);
+    assert_eq!(glow(&filter, 19, 19), 4 * 40 / 7);
+}
+
+#[test]
+fn glow_of_a_single_pixel_stops_at_the_radius() {
+    let mut source = filled(21, 21, 0);
+    source.set_pixel(10, 10, gray(0xFF));
+    let filter = glow_of(&source);
+
+    assert_eq!(glow(&filter, 10, 10), 0xFF / 7 / 7);
+    assert_eq!(glow(&filter, 13, 13), 0xFF / 7 / 7);
+    assert_eq!(glow(&filter, 7, 10), 0xFF / 7 / 7);
+    assert_eq!(glow(&filter, 14, 10), 0);
+    assert_eq!(glow(&filter, 10, 6), 0);
+}
+
+#[test]
+fn glow_of_images_smaller_than_the_window() {
+    let filter = glow_of(&filled(2, 2, 70));
+    assert_eq!(glow(&filter, 0, 0), 2 * (2 * 70 / 7) / 7);
+    assert_eq!(glow(&filter, 1, 1), glow(&filter, 0, 0));
+
+    let filter = glow_of(&filled(1, 1, 0xFF));
+    assert_eq!(glow(&filter, 0, 0), 0xFF / 7 / 7);
+}
+
+#[test]
+fn curvature_blacks_out_the_corners() {
+    let settings = CrtSettings {
+        curvature: 100,
+        ..CrtSettings::default()
+    };
+    let mut filter = CrtFilter::new(settings);
+    let source = filled(64, 32, 0xFF);
+    let mut output = Framebuffer::new(64, 32);
+    filter.apply(&source, &mut output);
+
+    for (x, y) in [(0, 0), (63, 0), (0, 31), (63, 31)] {
+        assert_eq!(
+            filter.source_indices[y * 64 + x],
+            OUTSIDE_SCREEN,
+            "{} {}",
+            x,
+            y
+        );
+        assert_eq!(output.get_pixel(x as u32, y as u32), gray(0));
+    }
+    assert_ne!(filter.source_indices[16 * 64 + 32], OUTSIDE_SCREEN);
+    assert_eq!(output.get_pixel(32, 16), gray(0xFF));
+}
+
+#[test]
+fn scanlines_darken_every_other_row() {
+    let settings = CrtSettings {
+        scanlines: 50,
+        ..CrtSettings::default()
+    };
+    let mut filter = CrtFilter::new(settings);
+    let source = filled(8, 4, 200);
+    let mut output = Framebuffer::new(8, 4);
+    filter.apply(&source, &mut output);
+
+    assert_eq!(output.get_pixel(3, 0), gray(200));
+    assert_eq!(output.get_pixel(3, 1), gray(100));
+    assert_eq!(output.get_pixel(3, 2), gray(200));
+}
@@ -0,0 +1,613 @@
+use crate::bitmap_font::{draw_text, text_height};
+use crate::cheats::{Cheat, CheatKind, RamSearch, SearchComparison};
+use crate::disassembler::disassemble;
+use crate::framebuffer::Framebuffer;
+use crate::frontend::{FrontendEvent, KEYPAD_LAYOUT};
+use crate::memory::TOTAL_MEMORY_BYTES;
+use crate::palette::{Palette, Rgb};
+use crate::virtual_machine::VirtualMachine;
+use crate::virtual_processor::StackDepth;
+use std::collections::BTreeSet;
+
+pub const DEBUG_VIEW_WIDTH: u32 = 1280;
+pub const DEBUG_VIEW_HEIGHT: u32 = 720;
+
+const TEXT_SCALE: u32 = 2;
+const CHARACTER_WIDTH: u32 = 6 * TEXT_SCALE;
+const LINE_HEIGHT: u32 = 14;
+const MARGIN: u32 = 8;
+const TITLE_SPACING: u32 = 18;
+
+const SCREEN_WIDTH: u32 = 640;
+const SCREEN_HEIGHT: u32 = 320;
+const REGISTERS_X: u32 = SCREEN_WIDTH + MARGIN * 3;
+const STACK_X: u32 = 1000;
+const LOWER_PANELS_Y: u32 = SCREEN_HEIGHT + MARGIN * 3;
+const MEMORY_X: u32 = 440;
+const SEARCH_X: u32 = 1112;
+
+const DISASSEMBLY_LINES: usize = 24;
+const DISASSEMBLY_LINES_BEFORE: usize = 8; // Instructions shown above the one followed.
+const MEMORY_ROWS: u16 = 24;
+const MEMORY_ROW_BYTES: u16 = 16;
+const STACK_ENTRIES: usize = 8;
+const SEARCH_HELP: [&str; 7] = [
+    "F6 NEW",
+    "F7 CHANGED",
+    "F8 SAME",
+    "G GREATER",
+    "L LESS",
+    "ENTER EQUAL",
+    "INS FREEZE",
+];
+const SPRITE_ROWS: u16 = 15;
+const SPRITE_PIXEL_SIZE: u32 = 10;
+const KEY_SIZE: u32 = 24;
+const RECENT_WRITE_FRAMES: u8 = 60;
+const WRITE_COLOR: Rgb = Rgb::from_u32(0xFF4040);
+
+// Keypad as laid out on the COSMAC VIP.
+const KEYPAD_ROWS: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+const HELP_TEXT: &str =
+    "F12 CLOSE  P PAUSE  F10 STEP  N FRAME  F2 BREAKPOINT  UP DOWN HOME CURSOR  PGUP PGDN MEMORY";
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DebugInput {
+    CursorUp,
+    CursorDown,
+    FollowProgramCounter,
+    ToggleBreakpoint,
+    MemoryPageUp,
+    MemoryPageDown,
+    NewSearch,
+    Search(SearchComparison), // Equal compares with the value set by the two below.
+    SearchValueDown,
+    SearchValueUp,
+    FreezeCandidate, // The first one, to its current value.
+}
+
+// Panels showing the inside of the machine next to its screen : disassembly,
+// registers, stack, memory, RAM search, sprite at I and keypad.
+pub struct DebugView {
+    breakpoints: BTreeSet<u16>,
+    cursor: Option<u16>, // None follows the program counter.
+    program_counter: u16,
+    memory_row: u16,
+    previous_memory: Vec<u8>,
+    write_ages: Vec<u8>, // Frames left to highlight each byte written to.
+    search: Option<RamSearch>,
+    search_value: u8,
+}
+
+impl Default for DebugView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_opcode(virtual_machine: &VirtualMachine, address: u16) -> u16 {
+    let memory = virtual_machine.memory();
+    (memory.read(address) as u16) << 8 | memory.read(address + 1) as u16
+}
+
+impl DebugView {
+    pub fn new() -> DebugView {
+        DebugView {
+            breakpoints: BTreeSet::new(),
+            cursor: None,
+            program_counter: 0,
+            memory_row: 0x200 / MEMORY_ROW_BYTES,
+            previous_memory: Vec::new(),
+            write_ages: vec![0; TOTAL_MEMORY_BYTES as usize],
+            search: None,
+            search_value: 0,
+        }
+    }
+
+    // To be called once per presented frame. Writes are spotted by comparing
+    // memory with the previous frame, a byte rewritten with the same value
+    // does not show.
+    pub fn update(&mut self, virtual_machine: &VirtualMachine) {
+        self.program_counter = virtual_machine.virtual_processor().program_counter();
+
+        let memory = virtual_machine.memory();
+        let current_memory: Vec<u8> = (0..TOTAL_MEMORY_BYTES)
+            .map(|address| memory.read(address))
+            .collect();
+
+        for (address, age) in self.write_ages.iter_mut().enumerate() {
+            if self
+                .previous_memory
+                .get(address)
+                .is_some_and(|byte| *byte != current_memory[address])
+            {
+                *age = RECENT_WRITE_FRAMES;
+            } else {
+                *age = age.saturating_sub(1);
+            }
+        }
+
+        self.previous_memory = current_memory;
+    }
+
+    // Returns the breakpoint or the cheat to forward to the RunLoop, if any.
+    // Searches compare the memory of the last frame shown.
+    pub fn handle_input(&mut self, input: DebugInput) -> Option<FrontendEvent> {
+        let cursor = self.cursor.unwrap_or(self.program_counter);
+        let last_memory_row = TOTAL_MEMORY_BYTES / MEMORY_ROW_BYTES - MEMORY_ROWS;
+
+        match input {
+            DebugInput::CursorUp => self.cursor = Some(cursor.saturating_sub(2)),
+            DebugInput::CursorDown => self.cursor = Some((cursor + 2).min(TOTAL_MEMORY_BYTES - 2)),
+            DebugInput::FollowProgramCounter => self.cursor = None,
+            DebugInput::ToggleBreakpoint => {
+                let is_set = !self.breakpoints.remove(&cursor);
+                if is_set {
+                    self.breakpoints.insert(cursor);
+                }
+                return Some(FrontendEvent::SetBreakpoint(cursor, is_set));
+            }
+            DebugInput::MemoryPageUp => {
+                self.memory_row = self.memory_row.saturating_sub(MEMORY_ROWS)
+            }
+            DebugInput::MemoryPageDown => {
+                self.memory_row = (self.memory_row + MEMORY_ROWS).min(last_memory_row)
+            }
+            DebugInput::NewSearch => self.search = Some(RamSearch::new(&self.previous_memory)),
+            DebugInput::Search(comparison) => {
+                let comparison = match comparison {
+                    SearchComparison::Equal(_) => SearchComparison::Equal(self.search_value),
+                    comparison => comparison,
+                };
+                let memory = &self.previous_memory;
+                self.search
+                    .get_or_insert_with(|| RamSearch::new(memory))
+                    .filter(memory, comparison);
+            }
+            DebugInput::SearchValueDown => self.search_value = self.search_value.wrapping_sub(1),
+            DebugInput::SearchValueUp => self.search_value = self.search_value.wrapping_add(1),
+            DebugInput::FreezeCandidate => {
+                let search = self.search.as_ref()?;
+                let address = *search.candidates().first()?;
+                let value = search.value(address);
+                return Some(FrontendEvent::AddCheat(Cheat::new(
+                    &format!("Freeze {:#05X}", address),
+                    CheatKind::Freeze { address, value },
+                )));
+            }
+        }
+
+        None
+    }
+
+    // The ROM's screen is taken already rendered, with the palette and filters.
+    pub fn draw(
+        &self,
+        framebuffer: &mut Framebuffer,
+        screen: &Framebuffer,
+        virtual_machine: &VirtualMachine,
+        palette: &Palette,
+    ) {
+        framebuffer.clear(palette.background());
+        framebuffer.fill_rect(
+            MARGIN - 1,
+            MARGIN - 1,
+            SCREEN_WIDTH + 2,
+            SCREEN_HEIGHT + 2,
+            palette.grid(),
+        );
+        framebuffer.draw_scaled(screen, MARGIN, MARGIN, SCREEN_WIDTH, SCREEN_HEIGHT);
+        Self::draw_fault(framebuffer, virtual_machine, palette);
+
+        self.draw_registers(framebuffer, virtual_machine, palette);
+        self.draw_keypad(framebuffer, virtual_machine, palette);
+        self.draw_stack(framebuffer, virtual_machine, palette);
+        self.draw_sprite(framebuffer, virtual_machine, palette);
+        self.draw_disassembly(framebuffer, virtual_machine, palette);
+        self.draw_memory(framebuffer, virtual_machine, palette);
+        self.draw_search(framebuffer, palette);
+
+        draw_text(
+            framebuffer,
+            MARGIN,
+            DEBUG_VIEW_HEIGHT - MARGIN - text_height(TEXT_SCALE),
+            HELP_TEXT,
+            palette.grid(),
+            TEXT_SCALE,
+        );
+    }
+
+    fn draw_title(framebuffer: &mut Framebuffer, x: u32, y: u32, title: &str, palette: &Palette) {
+        draw_text(framebuffer, x, y, title, palette.grid(), TEXT_SCALE);
+    }
+
+    // Over the bottom of the screen, the machine being stopped anyway.
+    fn draw_fault(
+        framebuffer: &mut Framebuffer,
+        virtual_machine: &VirtualMachine,
+        palette: &Palette,
+    ) {
+        let Some(fault) = virtual_machine.fault() else {
+            return;
+        };
+
+        let banner_height = text_height(TEXT_SCALE) + MARGIN * 2;
+        let banner_y = MARGIN + SCREEN_HEIGHT - banner_height;
+        framebuffer.fill_rect(
+            MARGIN,
+            banner_y,
+            SCREEN_WIDTH,
+            banner_height,
+            palette.background(),
+        );
+        draw_text(
+            framebuffer,
+            MARGIN * 2,
+            banner_y + MARGIN,
+            &format!("FAULT : {}", fault),
+            WRITE_COLOR,
+            TEXT_SCALE,
+        );
+    }
+
+    fn draw_registers(
+        &self,
+        framebuffer: &mut Framebuffer,
+        virtual_machine: &VirtualMachine,
+        palette: &Palette,
+    ) {
+        let processor = virtual_machine.virtual_processor();
+        Self::draw_title(framebuffer, REGISTERS_X, MARGIN, "REGISTERS", palette);
+
+        let mut lines: Vec<String> = processor
+            .registers()
+            .chunks(4)
+            .enumerate()
+            .map(|(row, registers)| {
+                registers
+                    .iter()
+                    .enumerate()
+                    .map(|(column, value)| format!("V{:X} {:02X}", row * 4 + column, value))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+            })
+            .collect();
+        lines.push(format!(
+            "I  {:#05X}  PC {:#05X}",
+            processor.index_register(),
+            processor.program_counter()
+        ));
+        lines.push(format!(
+            "DT {:03}    ST {:03}",
+            processor.delay_timer(),
+            processor.sound_timer()
+        ));
+
+        for (index, line) in lines.iter().enumerate() {
+            let y = MARGIN + TITLE_SPACING + index as u32 * LINE_HEIGHT;
+            draw_text(
+                framebuffer,
+                REGISTERS_X,
+                y,
+                line,
+                palette.foreground(),
+                TEXT_SCALE,
+            );
+        }
+    }
+
+    fn draw_keypad(
+        &self,
+        framebuffer: &mut Framebuffer,
+        virtual_machine: &VirtualMachine,
+        palette: &Palette,
+    ) {
+        let keys = virtual_machine.virtual_processor().keys();
+        let top = MARGIN + TITLE_SPACING + LINE_HEIGHT * 7;
+        Self::draw_title(framebuffer, REGISTERS_X, top, "KEYPAD", palette);
+
+        for (row, row_keys) in KEYPAD_ROWS.iter().enumerate() {
+            for (column, key) in row_keys.iter().enumerate() {
+                let x = REGISTERS_X + column as u32 * (KEY_SIZE + 4);
+                let y = top + TITLE_SPACING + row as u32 * (KEY_SIZE + 4);
+                let is_down = keys[*key as usize];
+
+                let (box_color, text_color) = if is_down {
+                    (palette.foreground(), palette.background())
+                } else {
+                    (palette.grid(), palette.foreground())
+                };
+                framebuffer.fill_rect(x, y, KEY_SIZE, KEY_SIZE, box_color);
+                draw_text(
+                    framebuffer,
+                    x + (KEY_SIZE - 5 * TEXT_SCALE) / 2,
+                    y + (KEY_SIZE - text_height(TEXT_SCALE)) / 2,
+                    &format!("{:X}", key),
+                    text_color,
+                    TEXT_SCALE,
+                );
+            }
+        }
+
+        // Which host key to press, the keypad being hard to guess on a keyboard.
+        let bindings: String = KEYPAD_ROWS
+            .iter()
+            .flatten()
+            .filter_map(|key| {
+                KEYPAD_LAYOUT
+                    .iter()
+                    .find(|(_, layout_key)| layout_key == key)
+            })
+            .map(|(c, _)| c.to_ascii_uppercase())
+            .collect();
+        for (row, row_bindings) in bindings.as_bytes().chunks(4).enumerate() {
+            let text: String = row_bindings
+                .iter()
+                .map(|c| format!("{} ", *c as char))
+                .collect();
+            draw_text(
+                framebuffer,
+                REGISTERS_X + 4 * (KEY_SIZE + 4) + MARGIN,
+                top + TITLE_SPACING
+                    + row as u32 * (KEY_SIZE + 4)
+                    + (KEY_SIZE - text_height(TEXT_SCALE)) / 2,
+                text.trim_end(),
+                palette.grid(),
+                TEXT_SCALE,
+            );
+        }
+    }
+
+    fn draw_stack(
+        &self,
+        framebuffer: &mut Framebuffer,
+        virtual_machine: &VirtualMachine,
+        palette: &Palette,
+    ) {
+        let processor = virtual_machine.virtual_processor();
+        let stack = processor.stack();
+        let title = match processor.stack_depth() {
+            StackDepth::Limited(levels) => format!("STACK {}/{}", stack.len(), levels),
+            StackDepth::Unlimited => format!("STACK {}", stack.len()),
+        };
+        Self::draw_title(framebuffer, STACK_X, MARGIN, &title, palette);
+
+        // Most recent call first, the deepest ones are the interesting ones.
+        for (index, return_address) in stack.iter().rev().take(STACK_ENTRIES).enumerate() {
+            let y = MARGIN + TITLE_SPACING + index as u32 * LINE_HEIGHT;
+            draw_text(
+                framebuffer,
+                STACK_X,
+                y,
+                &format!("{:#05X}", return_address),
+                palette.foreground(),
+                TEXT_SCALE,
+            );
+        }
+        if stack.len() > STACK_ENTRIES {
+            let y = MARGIN + TITLE_SPACING + STACK_ENTRIES as u32 * LINE_HEIGHT;
+            draw_text(framebuffer, STACK_X, y, "...", palette.grid(), TEXT_SCALE);
+        }
+    }
+
+    fn draw_sprite(
+        &self,
+        framebuffer: &mut Framebuffer,
+        virtual_machine: &VirtualMachine,
+        palette: &Palette,
+    ) {
+        let index_register = virtual_machine.virtual_processor().index_register();
+        let memory = virtual_machine.memory();
+        let top = MARGIN + TITLE_SPACING + LINE_HEIGHT * (STACK_ENTRIES as u32 + 2);
+        Self::draw_title(framebuffer, STACK_X, top, "SPRITE AT I", palette);
+
+        let sprite_y = top + TITLE_SPACING;
+        framebuffer.fill_rect(
+            STACK_X - 1,
+            sprite_y - 1,
+            8 * SPRITE_PIXEL_SIZE + 2,
+            SPRITE_ROWS as u32 * SPRITE_PIXEL_SIZE + 2,
+            palette.grid(),
+        );
+
+        for row in 0..SPRITE_ROWS {
+            let address = index_register + row;
+            let byte = if address < TOTAL_MEMORY_BYTES {
+                memory.read(address)
+            } else {
+                0
+            };
+
+            for bit in 0..8 {
+                let color = if byte & (0x80 >> bit) != 0 {
+                    palette.foreground()
+                } else {
+                    palette.background()
+                };
+                framebuffer.fill_rect(
+                    STACK_X + bit * SPRITE_PIXEL_SIZE,
+                    sprite_y + row as u32 * SPRITE_PIXEL_SIZE,
+                    SPRITE_PIXEL_SIZE,
+                    SPRITE_PIXEL_SIZE,
+                    color,
+                );
+            }
+
+            draw_text(
+                framebuffer,
+                STACK_X + 8 * SPRITE_PIXEL_SIZE + MARGIN,
+                sprite_y + row as u32 * SPRITE_PIXEL_SIZE,
+                &format!("{:02X}", byte),
+                palette.grid(),
+                1,
+            );
+        }
+    }
+
+    fn draw_disassembly(
+        &self,
+        framebuffer: &mut Framebuffer,
+        virtual_machine: &VirtualMachine,
+        palette: &Palette,
+    ) {
+        Self::draw_title(framebuffer, MARGIN, LOWER_PANELS_Y, "DISASSEMBLY", palette);
+
+        let followed_address = self.cursor.unwrap_or(self.program_counter) as i32;
+        let first_address = followed_address - DISASSEMBLY_LINES_BEFORE as i32 * 2;
+        let line_width = CHARACTER_WIDTH * 32;
+
+        for line in 0..DISASSEMBLY_LINES {
+            let address = first_address + line as i32 * 2;
+            if address < 0 || address >= TOTAL_MEMORY_BYTES as i32 - 1 {
+                continue;
+            }
+            let address = address as u16;
+            let y = LOWER_PANELS_Y + TITLE_SPACING + line as u32 * LINE_HEIGHT;
+
+            let opcode = read_opcode(virtual_machine, address);
+            let text = format!(
+                "{}{} {:#05X}  {:04X}  {}",
+                if self.breakpoints.contains(&address) {
+                    '*'
+                } else {
+                    ' '
+                },
+                if self.cursor == Some(address) {
+                    '>'
+                } else {
+                    ' '
+                },
+                address,
+                opcode,
+                disassemble(opcode)
+            );
+
+            let color = if address == self.program_counter {
+                framebuffer.fill_rect(
+                    MARGIN / 2,
+                    y - (LINE_HEIGHT - text_height(TEXT_SCALE)) / 2,
+                    line_width,
+                    LINE_HEIGHT,
+                    palette.foreground(),
+                );
+                palette.background()
+            } else {
+                palette.foreground()
+            };
+            draw_text(framebuffer, MARGIN, y, &text, color, TEXT_SCALE);
+        }
+    }
+
+    // The candidates with their value in the last snapshot, under the keys.
+    fn draw_search(&self, framebuffer: &mut Framebuffer, palette: &Palette) {
+        let title = match &self.search {
+            Some(search) => format!("SEARCH {}", search.candidates().len()),
+            None => "SEARCH".to_string(),
+        };
+        Self::draw_title(framebuffer, SEARCH_X, LOWER_PANELS_Y, &title, palette);
+
+        let value_line = format!("- + = {:#04X}", self.search_value);
+        let help_lines = SEARCH_HELP.iter().copied().chain([value_line.as_str()]);
+        let top = LOWER_PANELS_Y + TITLE_SPACING;
+        for (index, line) in help_lines.enumerate() {
+            let y = top + index as u32 * LINE_HEIGHT;
+            draw_text(framebuffer, SEARCH_X, y, line, palette.grid(), TEXT_SCALE);
+        }
+
+        let Some(search) = &self.search else {
+            return;
+        };
+        let first_row = SEARCH_HELP.len() as u16 + 2;
+        for (row, address) in search
+            .candidates()
+            .iter()
+            .take((MEMORY_ROWS - first_row) as usize)
+            .enumerate()
+        {
+            let y = top + (first_row as u32 + row as u32) * LINE_HEIGHT;
+            draw_text(
+                framebuffer,
+                SEARCH_X,
+                y,
+                &format!("{:#05X} {:02X}", address, search.value(*address)),
+                palette.foreground(),
+                TEXT_SCALE,
+            );
+        }
+    }
+
+    fn draw_memory(
+        &self,
+        framebuffer: &mut Framebuffer,
+        virtual_machine: &VirtualMachine,
+        palette: &Palette,
+    ) {
+        // Named after the region the first row is in.
+        let memory = virtual_machine.memory();
+        let title = format!(
+            "MEMORY ({})",
+            memory.region(self.memory_row * MEMORY_ROW_BYTES).name()
+        );
+        Self::draw_title(framebuffer, MEMORY_X, LOWER_PANELS_Y, &title, palette);
+
+        let index_register = virtual_machine.virtual_processor().index_register();
+        let bytes_x = MEMORY_X + CHARACTER_WIDTH * 7;
+
+        for row in 0..MEMORY_ROWS {
+            let row_address = (self.memory_row + row) * MEMORY_ROW_BYTES;
+            let y = LOWER_PANELS_Y + TITLE_SPACING + row as u32 * LINE_HEIGHT;
+            draw_text(
+                framebuffer,
+                MEMORY_X,
+                y,
+                &format!("{:#05X}", row_address),
+                palette.grid(),
+                TEXT_SCALE,
+            );
+
+            for column in 0..MEMORY_ROW_BYTES {
+                let address = row_address + column;
+                let x = bytes_x + column as u32 * CHARACTER_WIDTH * 3;
+
+                // Recent writes fade out, I is outlined by a box in the grid colour.
+                let age = self.write_ages[address as usize];
+                if age > 0 {
+                    let amount = (age as u32 * 255 / RECENT_WRITE_FRAMES as u32) as u8;
+                    framebuffer.fill_rect(
+                        x - 2,
+                        y - 2,
+                        CHARACTER_WIDTH * 2 + 2,
+                        LINE_HEIGHT,
+                        palette.background().blend(WRITE_COLOR, amount),
+                    );
+                } else if address == index_register {
+                    framebuffer.fill_rect(
+                        x - 2,
+                        y - 2,
+                        CHARACTER_WIDTH * 2 + 2,
+                        LINE_HEIGHT,
+                        palette.grid(),
+                    );
+                }
+
+                draw_text(
+                    framebuffer,
+                    x,
+                    y,
+                    &format!("{:02X}", memory.read(address)),
+                    palette.foreground(),
+                    TEXT_SCALE,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,125 @@
+use super::*;
+
+fn machine() -> VirtualMachine {
+    let mut virtual_machine = VirtualMachine::new();
+    virtual_machine.load_rom_bytes(&[0x12, 0x00]).unwrap();
+    virtual_machine
+}
+
+#[test]
+fn cursor_stays_within_memory() {
+    let mut debug_view = DebugView::new();
+    debug_view.update(&machine());
+    assert_eq!(debug_view.program_counter, 0x200);
+
+    debug_view.handle_input(DebugInput::CursorDown);
+    assert_eq!(debug_view.cursor, Some(0x202));
+    debug_view.handle_input(DebugInput::FollowProgramCounter);
+    assert_eq!(debug_view.cursor, None);
+
+    for _ in 0..0x200 {
+        debug_view.handle_input(DebugInput::CursorUp);
+    }
+    assert_eq!(debug_view.cursor, Some(0));
+    for _ in 0..TOTAL_MEMORY_BYTES {
+        debug_view.handle_input(DebugInput::CursorDown);
+    }
+    assert_eq!(debug_view.cursor, Some(TOTAL_MEMORY_BYTES - 2));
+}
+
+#[test]
+fn memory_pages_stay_within_memory() {
+    let mut debug_view = DebugView::new();
+    let last_memory_row = TOTAL_MEMORY_BYTES / MEMORY_ROW_BYTES - MEMORY_ROWS;
+
+    for _ in 0..TOTAL_MEMORY_BYTES / MEMORY_ROW_BYTES {
+        debug_view.handle_input(DebugInput::MemoryPageDown);
+    }
+    assert_eq!(debug_view.memory_row, last_memory_row);
+    debug_view.handle_input(DebugInput::MemoryPageUp);
+    assert_eq!(debug_view.memory_row, last_memory_row - MEMORY_ROWS);
+
+    for _ in 0..TOTAL_MEMORY_BYTES / MEMORY_ROW_BYTES {
+        debug_view.handle_input(DebugInput::MemoryPageUp);
+    }
+    assert_eq!(debug_view.memory_row, 0);
+}
+
+#[test]
+fn breakpoints_toggle_at_the_cursor() {
+    let mut debug_view = DebugView::new();
+    debug_view.update(&machine());
+
+    assert_eq!(
+        debug_view.handle_input(DebugInput::ToggleBreakpoint),
+        Some(FrontendEvent::SetBreakpoint(0x200, true))
+    );
+    debug_view.handle_input(DebugInput::CursorDown);
+    assert_eq!(
+        debug_view.handle_input(DebugInput::ToggleBreakpoint),
+        Some(FrontendEvent::SetBreakpoint(0x202, true))
+    );
+    debug_view.handle_input(DebugInput::FollowProgramCounter);
+    assert_eq!(
+        debug_view.handle_input(DebugInput::ToggleBreakpoint),
+        Some(FrontendEvent::SetBreakpoint(0x200, false))
+    );
+    assert_eq!(debug_view.breakpoints, BTreeSet::from([0x202]));
+}
+
+#[test]
+fn writes_are_highlighted_then_fade() {
+    let (mut debug_view, mut virtual_machine) = (DebugView::new(), machine());
+    debug_view.update(&virtual_machine);
+
+    virtual_machine.memory_mut().write(0x300, 1);
+    debug_view.update(&virtual_machine);
+    assert_eq!(debug_view.write_ages[0x300], RECENT_WRITE_FRAMES);
+    assert_eq!(debug_view.write_ages[0x301], 0);
+
+    debug_view.update(&virtual_machine);
+    assert_eq!(debug_view.write_ages[0x300], RECENT_WRITE_FRAMES - 1);
+}
+
+#[test]
+fn ram_search_narrows_down_and_freezes() {
+    let (mut debug_view, mut virtual_machine) = (DebugView::new(), machine());
+    assert_eq!(debug_view.handle_input(DebugInput::FreezeCandidate), None);
+
+    // Lives at 0x300 and 0x310, only the first one goes down.
+    virtual_machine.memory_mut().write(0x300, 3);
+    virtual_machine.memory_mut().write(0x310, 3);
+    debug_view.update(&virtual_machine);
+    debug_view.handle_input(DebugInput::NewSearch);
+
+    virtual_machine.memory_mut().write(0x300, 2);
+    debug_view.update(&virtual_machine);
+    debug_view.handle_input(DebugInput::Search(SearchComparison::Less));
+    assert_eq!(debug_view.search.as_ref().unwrap().candidates(), [0x300]);
+
+    // Equal uses the value set in the view, not the one in the input.
+    debug_view.handle_input(DebugInput::SearchValueUp);
+    debug_view.handle_input(DebugInput::SearchValueUp);
+    debug_view.handle_input(DebugInput::SearchValueUp);
+    debug_view.handle_input(DebugInput::SearchValueDown);
+    debug_view.handle_input(DebugInput::Search(SearchComparison::Equal(0)));
+    assert_eq!(debug_view.search.as_ref().unwrap().candidates(), [0x300]);
+
+    assert_eq!(
+        debug_view.handle_input(DebugInput::FreezeCandidate),
+        Some(FrontendEvent::AddCheat(Cheat::new(
+            "Freeze 0x300",
+            CheatKind::Freeze {
+                address: 0x300,
+                value: 2
+            },
+        )))
+    );
+
+    // A new search starts again from every byte.
+    debug_view.handle_input(DebugInput::NewSearch);
+    assert_eq!(
+        debug_view.search.as_ref().unwrap().candidates().len(),
+        TOTAL_MEMORY_BYTES as usize
+    );
+}
@@ -0,0 +1,147 @@
+// Mnemonics follow Cowgod's Chip-8 technical reference, the same names as the
+// comments of VirtualProcessor::execute_instruction.
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum InstructionKind {
+    Flow,       // SYS, JP, CALL, RET
+    Skip,       // SE, SNE
+    Arithmetic, // LD Vx, ADD, SUB, OR, AND, XOR, SHR, SHL, RND
+    Memory,     // Everything touching I or RAM.
+    Display,    // CLS, DRW
+    Input,      // SKP, SKNP, LD Vx, K
+    Timer,      // LD Vx, DT / LD DT, Vx / LD ST, Vx
+    Unknown,
+}
+
+pub const INSTRUCTION_KINDS: [InstructionKind; 8] = [
+    InstructionKind::Flow,
+    InstructionKind::Skip,
+    InstructionKind::Arithmetic,
+    InstructionKind::Memory,
+    InstructionKind::Display,
+    InstructionKind::Input,
+    InstructionKind::Timer,
+    InstructionKind::Unknown,
+];
+
+impl InstructionKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            InstructionKind::Flow => "flow",
+            InstructionKind::Skip => "skip",
+            InstructionKind::Arithmetic => "arithmetic",
+            InstructionKind::Memory => "memory",
+            InstructionKind::Display => "display",
+            InstructionKind::Input => "input",
+            InstructionKind::Timer => "timer",
+            InstructionKind::Unknown => "unknown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<InstructionKind> {
+        INSTRUCTION_KINDS
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+}
+
+pub struct Instruction {
+    pub kind: InstructionKind,
+    pub mnemonic: String,
+}
+
+pub fn decode(opcode: u16) -> Instruction {
+    let nnn = opcode & 0x0FFF;
+    let nn = opcode & 0x00FF;
+    let n = opcode & 0x000F;
+    let x = (opcode & 0x0F00) >> 8;
+    let y = (opcode & 0x00F0) >> 4;
+
+    let (kind, mnemonic) = match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00E0 => (InstructionKind::Display, "CLS".to_string()),
+            0x00EE => (InstructionKind::Flow, "RET".to_string()),
+            _ => (InstructionKind::Flow, format!("SYS {:#05X}", nnn)),
+        },
+        0x1000 => (InstructionKind::Flow, format!("JP {:#05X}", nnn)),
+        0x2000 => (InstructionKind::Flow, format!("CALL {:#05X}", nnn)),
+        0x3000 => (InstructionKind::Skip, format!("SE V{:X}, {:#04X}", x, nn)),
+        0x4000 => (InstructionKind::Skip, format!("SNE V{:X}, {:#04X}", x, nn)),
+        // Like the VIP and the processor, the low nibble of 5XYN and 9XYN is ignored.
+        0x5000 => (InstructionKind::Skip, format!("SE V{:X}, V{:X}", x, y)),
+        0x6000 => (
+            InstructionKind::Arithmetic,
+            format!("LD V{:X}, {:#04X}", x, nn),
+        ),
+        0x7000 => (
+            InstructionKind::Arithmetic,
+            format!("ADD V{:X}, {:#04X}", x, nn),
+        ),
+        0x8000 => {
+            let operation = match n {
+                0x0 => Some("LD"),
+                0x1 => Some("OR"),
+                0x2 => Some("AND"),
+                0x3 => Some("XOR"),
+                0x4 => Some("ADD"),
+                0x5 => Some("SUB"),
+                0x6 => Some("SHR"),
+                0x7 => Some("SUBN"),
+                0xE => Some("SHL"),
+                _ => None,
+            };
+
+            match operation {
+                Some(operation) => (
+                    InstructionKind::Arithmetic,
+                    format!("{} V{:X}, V{:X}", operation, x, y),
+                ),
+                None => unknown(opcode),
+            }
+        }
+        0x9000 => (InstructionKind::Skip, format!("SNE V{:X}, V{:X}", x, y)),
+        0xA000 => (InstructionKind::Memory, format!("LD I, {:#05X}", nnn)),
+        0xB000 => (InstructionKind::Flow, format!("JP V0, {:#05X}", nnn)),
+        0xC000 => (
+            InstructionKind::Arithmetic,
+            format!("RND V{:X}, {:#04X}", x, nn),
+        ),
+        0xD000 => (
+            InstructionKind::Display,
+            format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        ),
+        0xE000 => match nn {
+            0x9E => (InstructionKind::Input, format!("SKP V{:X}", x)),
+            0xA1 => (InstructionKind::Input, format!("SKNP V{:X}", x)),
+            _ => unknown(opcode),
+        },
+        0xF000 => match nn {
+            0x07 => (InstructionKind::Timer, format!("LD V{:X}, DT", x)),
+            0x0A => (InstructionKind::Input, format!("LD V{:X}, K", x)),
+            0x15 => (InstructionKind::Timer, format!("LD DT, V{:X}", x)),
+            0x18 => (InstructionKind::Timer, format!("LD ST, V{:X}", x)),
+            0x1E => (InstructionKind::Memory, format!("ADD I, V{:X}", x)),
+            0x29 => (InstructionKind::Memory, format!("LD F, V{:X}", x)),
+            0x30 => (InstructionKind::Memory, format!("LD HF, V{:X}", x)),
+            0x33 => (InstructionKind::Memory, format!("LD B, V{:X}", x)),
+            0x55 => (InstructionKind::Memory, format!("LD [I], V{:X}", x)),
+            0x65 => (InstructionKind::Memory, format!("LD V{:X}, [I]", x)),
+            _ => unknown(opcode),
+        },
+        _ => unknown(opcode),
+    };
+
+    Instruction { kind, mnemonic }
+}
+
+// Data or an instruction mch8 does not know, shown as a raw word.
+fn unknown(opcode: u16) -> (InstructionKind, String) {
+    (InstructionKind::Unknown, format!("DW {:#06X}", opcode))
+}
+
+pub fn disassemble(opcode: u16) -> String {
+    decode(opcode).mnemonic
+}
+
+#[cfg(test)]
+mod tests;
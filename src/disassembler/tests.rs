@@ -0,0 +1,95 @@
+use super::*;
+use InstructionKind::*;
+
+// An opcode, its mnemonic and the kind it is filtered under.
+struct DisassemblyCase {
+    opcode: u16,
+    mnemonic: &'static str,
+    kind: InstructionKind,
+}
+
+const fn case(opcode: u16, mnemonic: &'static str, kind: InstructionKind) -> DisassemblyCase {
+    DisassemblyCase {
+        opcode,
+        mnemonic,
+        kind,
+    }
+}
+
+const DISASSEMBLY_CASES: &[DisassemblyCase] = &[
+    case(0x00E0, "CLS", Display),
+    case(0x00EE, "RET", Flow),
+    case(0x0123, "SYS 0x123", Flow),
+    case(0x1ABC, "JP 0xABC", Flow),
+    case(0x2204, "CALL 0x204", Flow),
+    case(0x3A42, "SE VA, 0x42", Skip),
+    case(0x4B07, "SNE VB, 0x07", Skip),
+    case(0x5120, "SE V1, V2", Skip),
+    case(0x5121, "SE V1, V2", Skip),
+    case(0x6FFF, "LD VF, 0xFF", Arithmetic),
+    case(0x7001, "ADD V0, 0x01", Arithmetic),
+    case(0x8120, "LD V1, V2", Arithmetic),
+    case(0x8121, "OR V1, V2", Arithmetic),
+    case(0x8122, "AND V1, V2", Arithmetic),
+    case(0x8123, "XOR V1, V2", Arithmetic),
+    case(0x8124, "ADD V1, V2", Arithmetic),
+    case(0x8125, "SUB V1, V2", Arithmetic),
+    case(0x8126, "SHR V1, V2", Arithmetic),
+    case(0x8127, "SUBN V1, V2", Arithmetic),
+    case(0x812E, "SHL V1, V2", Arithmetic),
+    case(0x8128, "DW 0x8128", Unknown),
+    case(0x812F, "DW 0x812F", Unknown),
+    case(0x9AB0, "SNE VA, VB", Skip),
+    case(0x9ABF, "SNE VA, VB", Skip),
+    case(0xA2F0, "LD I, 0x2F0", Memory),
+    case(0xB300, "JP V0, 0x300", Flow),
+    case(0xC30F, "RND V3, 0x0F", Arithmetic),
+    case(0xD125, "DRW V1, V2, 5", Display),
+    case(0xD120, "DRW V1, V2, 0", Display),
+    case(0xE59E, "SKP V5", Input),
+    case(0xE5A1, "SKNP V5", Input),
+    case(0xE5A2, "DW 0xE5A2", Unknown),
+    case(0xF107, "LD V1, DT", Timer),
+    case(0xF10A, "LD V1, K", Input),
+    case(0xF115, "LD DT, V1", Timer),
+    case(0xF118, "LD ST, V1", Timer),
+    case(0xF11E, "ADD I, V1", Memory),
+    case(0xF129, "LD F, V1", Memory),
+    case(0xF130, "LD HF, V1", Memory),
+    case(0xF133, "LD B, V1", Memory),
+    case(0xF155, "LD [I], V1", Memory),
+    case(0xF165, "LD V1, [I]", Memory),
+    case(0xF1FF, "DW 0xF1FF", Unknown),
+];
+
+#[test]
+fn disassembly() {
+    for case in DISASSEMBLY_CASES {
+        let instruction = decode(case.opcode);
+        assert_eq!(instruction.mnemonic, case.mnemonic, "{:04X}", case.opcode);
+        assert_eq!(instruction.kind, case.kind, "{}", case.mnemonic);
+        assert_eq!(disassemble(case.opcode), case.mnemonic);
+    }
+}
+
+#[test]
+fn every_opcode_decodes() {
+    for opcode in 0..=u16::MAX {
+        let instruction = decode(opcode);
+        assert_eq!(
+            instruction.kind == Unknown,
+            instruction.mnemonic == format!("DW {:#06X}", opcode),
+            "{:04X}",
+            opcode
+        );
+    }
+}
+
+#[test]
+fn instruction_kind_names() {
+    for kind in INSTRUCTION_KINDS {
+        assert_eq!(InstructionKind::from_name(kind.name()), Some(kind));
+    }
+    assert_eq!(InstructionKind::from_name("Display"), Some(Display));
+    assert_eq!(InstructionKind::from_name("jump"), None);
+}
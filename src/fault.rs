@@ -0,0 +1,46 @@
+use std::fmt;
+
+// Errors of the emulated program, not of mch8 : the machine stops on the
+// faulting instruction until it is reset or a state is loaded.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Fault {
+    StackOverflow { address: u16, depth: u16 }, // CALL with `depth` return addresses already stacked.
+    StackUnderflow { address: u16 },            // RET with an empty stack.
+    UnknownOpcode { address: u16, opcode: u16 }, // Including the 0NNN machine code routines.
+    MemoryOutOfRange { address: u16, target: u16 }, // Access to `target`, past the 4 KiB.
+}
+
+impl Fault {
+    // Where the faulting instruction is.
+    pub fn address(&self) -> u16 {
+        match self {
+            Fault::StackOverflow { address, .. }
+            | Fault::StackUnderflow { address }
+            | Fault::UnknownOpcode { address, .. }
+            | Fault::MemoryOutOfRange { address, .. } => *address,
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::StackOverflow { address, depth } => write!(
+                f,
+                "Stack overflow at {:#05X}, more than {} nested calls",
+                address, depth
+            ),
+            Fault::StackUnderflow { address } => {
+                write!(f, "Stack underflow at {:#05X}, RET without CALL", address)
+            }
+            Fault::UnknownOpcode { address, opcode } => {
+                write!(f, "Unknown opcode {:04X} at {:#05X}", opcode, address)
+            }
+            Fault::MemoryOutOfRange { address, target } => write!(
+                f,
+                "Memory access to {:#06X} out of range at {:#05X}",
+                target, address
+            ),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::virtual_machine::VirtualMachine;
+
+pub const DEFAULT_FLICKER_DECAY: u8 = 160;
+
+// CHIP-8 sprites are erased by drawing them a second time (XOR), so a moving
+// sprite is switched off for part of the frames and flickers. These modes hide it
+// by looking at the screen over several frames instead of a single one.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum FlickerMode {
+    #[default]
+    Off,
+    Blend,       // Average of the last two frames.
+    OrLastTwo,   // A pixel is lit if it was lit in either of the last two frames.
+    Persistence, // Lit pixels fade out slowly, like a phosphor screen.
+}
+
+impl FlickerMode {
+    pub fn from_name(name: &str) -> Option<FlickerMode> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Some(FlickerMode::Off),
+            "blend" => Some(FlickerMode::Blend),
+            "or" | "or-last-two" => Some(FlickerMode::OrLastTwo),
+            "persistence" | "phosphor" => Some(FlickerMode::Persistence),
+            _ => None,
+        }
+    }
+}
+
+pub struct FlickerFilter {
+    mode: FlickerMode,
+    decay: u8, // Share of the intensity kept from one frame to the next (x / 256).
+    width: u8,
+    height: u8,
+    previous_frame: Vec<bool>,
+    intensities: Vec<u8>,
+}
+
+impl FlickerFilter {
+    pub fn new(mode: FlickerMode, decay: u8) -> FlickerFilter {
+        FlickerFilter {
+            mode,
+            decay,
+            width: 0,
+            height: 0,
+            previous_frame: Vec::new(),
+            intensities: Vec::new(),
+        }
+    }
+
+    // Anything but Off depends on past frames, so the screen has to be sampled
+    // (and repainted) every frame, not only when the ROM draws.
+    pub fn needs_every_frame(&self) -> bool {
+        self.mode != FlickerMode::Off
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    // Samples the screen, to be called once per emulated frame so the modes
+    // keep their timing with fast-forward or slow motion. The history is
+    // dropped when the resolution changes.
+    pub fn update(&mut self, virtual_machine: &VirtualMachine) {
+        if virtual_machine.screen_width() != self.width
+            || virtual_machine.screen_height() != self.height
+        {
+            self.width = virtual_machine.screen_width();
+            self.height = virtual_machine.screen_height();
+
+            let total_pixels = self.width as usize * self.height as usize;
+            self.previous_frame = vec![false; total_pixels];
+            self.intensities = vec![0; total_pixels];
+        }
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y as usize * self.width as usize + x as usize;
+                let is_on = virtual_machine.is_pixel_switched_on(x, y);
+                let was_on = self.previous_frame[index];
+
+                self.intensities[index] = match self.mode {
+                    FlickerMode::Off => is_on as u8 * 255,
+                    FlickerMode::Blend => match (is_on, was_on) {
+                        (true, true) => 255,
+                        (false, false) => 0,
+                        _ => 128,
+                    },
+                    FlickerMode::OrLastTwo => (is_on || was_on) as u8 * 255,
+                    FlickerMode::Persistence => {
+                        if is_on {
+                            255
+                        } else {
+                            ((self.intensities[index] as u16 * self.decay as u16) >> 8) as u8
+                        }
+                    }
+                };
+
+                self.previous_frame[index] = is_on;
+            }
+        }
+    }
+
+    // Before drawing : without any history, Off shows the screen as it is now,
+    // the other modes show what the last emulated frame left.
+    pub fn refresh(&mut self, virtual_machine: &VirtualMachine) {
+        if self.mode == FlickerMode::Off
+            || virtual_machine.screen_width() != self.width
+            || virtual_machine.screen_height() != self.height
+        {
+            self.update(virtual_machine);
+        }
+    }
+
+    // From 0 (off) to 255 (fully lit).
+    pub fn intensity(&self, x: u8, y: u8) -> u8 {
+        self.intensities[y as usize * self.width as usize + x as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,98 @@
+use super::*;
+
+// LD I, sprite ; then DRW V0, V1, 1 twice and JP back : the top left pixel is
+// switched on and off on alternate instructions.
+const PROGRAM: [u8; 11] = [
+    0xA2, 0x0A, 0xD0, 0x11, 0xD0, 0x11, 0x12, 0x02, 0x00, 0x00, 0x80,
+];
+
+// The top left pixel as the filter shows it, after each frame of a single
+// instruction.
+fn intensities(mode: FlickerMode, decay: u8) -> Vec<u8> {
+    let mut virtual_machine = VirtualMachine::new();
+    virtual_machine.load_rom_bytes(&PROGRAM).unwrap();
+    let mut filter = FlickerFilter::new(mode, decay);
+
+    (0..7)
+        .map(|_| {
+            virtual_machine.run_frame(1);
+            filter.update(&virtual_machine);
+            assert_eq!(filter.intensity(1, 0), 0);
+            filter.intensity(0, 0)
+        })
+        .collect()
+}
+
+#[test]
+fn off_shows_the_last_frame() {
+    assert_eq!(
+        intensities(FlickerMode::Off, DEFAULT_FLICKER_DECAY),
+        [0, 255, 0, 0, 255, 0, 0]
+    );
+}
+
+#[test]
+fn blend_averages_the_last_two_frames() {
+    assert_eq!(
+        intensities(FlickerMode::Blend, DEFAULT_FLICKER_DECAY),
+        [0, 128, 128, 0, 128, 128, 0]
+    );
+}
+
+#[test]
+fn or_last_two_keeps_pixels_lit_for_a_frame() {
+    assert_eq!(
+        intensities(FlickerMode::OrLastTwo, DEFAULT_FLICKER_DECAY),
+        [0, 255, 255, 0, 255, 255, 0]
+    );
+}
+
+#[test]
+fn persistence_fades_pixels_out() {
+    assert_eq!(
+        intensities(FlickerMode::Persistence, 128),
+        [0, 255, 127, 63, 255, 127, 63]
+    );
+    assert_eq!(
+        intensities(FlickerMode::Persistence, 0),
+        [0, 255, 0, 0, 255, 0, 0]
+    );
+}
+
+#[test]
+fn refresh_only_samples_without_history() {
+    let mut virtual_machine = VirtualMachine::new();
+    virtual_machine.load_rom_bytes(&PROGRAM).unwrap();
+    virtual_machine.run_frame(2);
+
+    // A new filter is sized by its first refresh, whatever the mode.
+    let mut off = FlickerFilter::new(FlickerMode::Off, DEFAULT_FLICKER_DECAY);
+    let mut blend = FlickerFilter::new(FlickerMode::Blend, DEFAULT_FLICKER_DECAY);
+    off.refresh(&virtual_machine);
+    blend.refresh(&virtual_machine);
+    assert_eq!((blend.width(), blend.height()), (64, 32));
+    assert_eq!(off.intensity(0, 0), 255);
+    assert_eq!(blend.intensity(0, 0), 128);
+
+    // Drawing again between two emulated frames does not move the history.
+    virtual_machine.run_frame(1);
+    off.refresh(&virtual_machine);
+    blend.refresh(&virtual_machine);
+    blend.refresh(&virtual_machine);
+    assert_eq!(off.intensity(0, 0), 0);
+    assert_eq!(blend.intensity(0, 0), 128);
+    assert!(!off.needs_every_frame());
+    assert!(blend.needs_every_frame());
+}
+
+#[test]
+fn mode_names() {
+    assert_eq!(FlickerMode::from_name("Blend"), Some(FlickerMode::Blend));
+    assert_eq!(FlickerMode::from_name("or"), Some(FlickerMode::OrLastTwo));
+    assert_eq!(
+        FlickerMode::from_name("phosphor"),
+        Some(FlickerMode::Persistence)
+    );
+    assert_eq!(FlickerMode::from_name("off"), Some(FlickerMode::Off));
+    assert_eq!(FlickerMode::from_name("smooth"), None);
+}
@@ -0,0 +1,184 @@
+use crate::globals::*;
+use std::fs;
+
+pub const SMALL_FONT_BYTES: u16 = 80; // 16 characters of 4x5 pixels.
+pub const BIG_FONT_BYTES: u16 = 160; // 16 characters of 8x10 pixels.
+pub const FONT_BYTES: u16 = SMALL_FONT_BYTES + BIG_FONT_BYTES;
+// The big font follows the small one, both staying in the interpreter area.
+pub const MAX_FONT_ADDRESS: u16 = PROCESSOR_INTERNAL_PROGRAM_COUNTER_START - FONT_BYTES;
+
+const NAMED_FONTS: [(&str, [u8; SMALL_FONT_BYTES as usize]); 4] = [
+    (
+        "schip",
+        [
+            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+            0x20, 0x60, 0x20, 0x20, 0x70, // 1
+            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+        ],
+    ),
+    (
+        "vip",
+        [
+            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+            0x60, 0x20, 0x20, 0x20, 0x70, // 1
+            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+            0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+            0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+            0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+            0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+        ],
+    ),
+    (
+        "dream6800",
+        [
+            0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+            0x40, 0x40, 0x40, 0x40, 0x40, // 1
+            0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+            0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+            0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+            0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+            0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+            0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+            0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+            0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+            0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+            0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+            0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+            0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+            0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+            0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+        ],
+    ),
+    (
+        "eti660",
+        [
+            0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+            0x20, 0x20, 0x20, 0x20, 0x20, // 1
+            0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+            0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+            0xA0, 0xA0, 0xE0, 0x20, 0x20, // 4
+            0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+            0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+            0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+            0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+            0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+            0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+            0x80, 0x80, 0xE0, 0xA0, 0xE0, // B
+            0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+            0x20, 0x20, 0xE0, 0xA0, 0xE0, // D
+            0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+            0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+        ],
+    ),
+];
+
+// The SCHIP digits, with the letters XO-CHIP added.
+const BIG_FONT: [u8; BIG_FONT_BYTES as usize] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+// The hexadecimal digits FX29 and FX30 point I to, written in the interpreter
+// area when the ROM is loaded.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Fontset {
+    small: [u8; SMALL_FONT_BYTES as usize],
+    big: [u8; BIG_FONT_BYTES as usize],
+}
+
+impl Default for Fontset {
+    fn default() -> Self {
+        Self::by_name("schip").unwrap()
+    }
+}
+
+impl Fontset {
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        NAMED_FONTS.iter().map(|(name, _)| *name)
+    }
+
+    // The built-in fonts all come with the same big font.
+    pub fn by_name(name: &str) -> Option<Fontset> {
+        NAMED_FONTS
+            .iter()
+            .find(|(font_name, _)| font_name.eq_ignore_ascii_case(name))
+            .map(|(_, small)| Fontset {
+                small: *small,
+                big: BIG_FONT,
+            })
+    }
+
+    // The 80 bytes of a small font, optionally followed by the 160 of a big one.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Fontset, String> {
+        let (small, big) = match bytes.len() as u16 {
+            SMALL_FONT_BYTES => (bytes, &BIG_FONT[..]),
+            FONT_BYTES => bytes.split_at(SMALL_FONT_BYTES as usize),
+            _ => {
+                return Err(format!(
+                    "[Error] A font holds {} bytes, or {} with the big font !",
+                    SMALL_FONT_BYTES, FONT_BYTES
+                ))
+            }
+        };
+
+        Ok(Fontset {
+            small: small.try_into().unwrap(),
+            big: big.try_into().unwrap(),
+        })
+    }
+
+    // A built-in font's name, or the path of a font file.
+    pub fn from_name_or_file(name: &str) -> Result<Fontset, String> {
+        if let Some(fontset) = Fontset::by_name(name) {
+            return Ok(fontset);
+        }
+
+        let bytes = fs::read(name)
+            .map_err(|_| format!("[Error] Unknown font or unreadable font file '{}' !", name))?;
+        Fontset::from_bytes(&bytes)
+    }
+
+    // The small font then the big one, as they are laid out in memory.
+    pub fn bytes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.small.iter().chain(self.big.iter()).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,122 @@
+use super::*;
+use crate::virtual_machine::VirtualMachine;
+
+// Every byte differs, to tell where each one ended up.
+fn numbered_bytes(count: u16) -> Vec<u8> {
+    (0..count).map(|byte| byte as u8).collect()
+}
+
+#[test]
+fn from_bytes_with_and_without_big_font() {
+    let small_only = Fontset::from_bytes(&numbered_bytes(SMALL_FONT_BYTES)).unwrap();
+    let bytes: Vec<u8> = small_only.bytes().collect();
+    assert_eq!(
+        bytes[..SMALL_FONT_BYTES as usize],
+        numbered_bytes(SMALL_FONT_BYTES)
+    );
+    assert_eq!(bytes[SMALL_FONT_BYTES as usize..], BIG_FONT);
+
+    let both = Fontset::from_bytes(&numbered_bytes(FONT_BYTES)).unwrap();
+    assert_eq!(
+        both.bytes().collect::<Vec<u8>>(),
+        numbered_bytes(FONT_BYTES)
+    );
+}
+
+#[test]
+fn from_bytes_with_wrong_size() {
+    let expected = Err("[Error] A font holds 80 bytes, or 240 with the big font !".to_string());
+    for size in [0, 79, 81, 160, 239, 241] {
+        assert_eq!(Fontset::from_bytes(&numbered_bytes(size)), expected);
+    }
+}
+
+#[test]
+fn from_name_or_file() {
+    assert_eq!(
+        Fontset::from_name_or_file("VIP"),
+        Ok(Fontset::by_name("vip").unwrap())
+    );
+
+    let font_path = std::env::temp_dir().join(format!("mch8-test-{}.font", std::process::id()));
+    fs::write(&font_path, numbered_bytes(SMALL_FONT_BYTES)).unwrap();
+    let fontset = Fontset::from_name_or_file(font_path.to_str().unwrap());
+    fs::remove_file(&font_path).unwrap();
+    assert_eq!(
+        fontset,
+        Fontset::from_bytes(&numbered_bytes(SMALL_FONT_BYTES))
+    );
+
+    assert_eq!(
+        Fontset::from_name_or_file("no-such-font"),
+        Err("[Error] Unknown font or unreadable font file 'no-such-font' !".to_string())
+    );
+}
+
+#[test]
+fn font_address_moves_font_and_character_lookups() {
+    let mut virtual_machine = VirtualMachine::new();
+    virtual_machine.set_fontset(Fontset::from_bytes(&numbered_bytes(FONT_BYTES)).unwrap());
+    virtual_machine.set_font_address(0x50).unwrap();
+    // V0 = 0x0A, I = small A, V1 = 0x03, I = big 3.
+    virtual_machine
+        .load_rom_bytes(&[0x60, 0x0A, 0xF0, 0x29, 0x61, 0x03, 0xF1, 0x30])
+        .unwrap();
+
+    for (offset, byte) in numbered_bytes(FONT_BYTES).iter().enumerate() {
+        assert_eq!(virtual_machine.memory().read(0x50 + offset as u16), *byte);
+    }
+
+    virtual_machine.execute_processor_instruction();
+    virtual_machine.execute_processor_instruction();
+    assert_eq!(
+        virtual_machine.virtual_processor().index_register(),
+        0x50 + 0x0A * 5
+    );
+
+    virtual_machine.execute_processor_instruction();
+    virtual_machine.execute_processor_instruction();
+    assert_eq!(
+        virtual_machine.virtual_processor().index_register(),
+        0x50 + SMALL_FONT_BYTES + 3 * 10
+    );
+}
+
+#[test]
+fn font_address_must_leave_room_for_the_font() {
+    let mut virtual_machine = VirtualMachine::new();
+    assert_eq!(virtual_machine.set_font_address(MAX_FONT_ADDRESS), Ok(()));
+    assert_eq!(
+        virtual_machine.set_font_address(MAX_FONT_ADDRESS + 1),
+        Err("[Error] The font does not fit below 0x200 at this address !")
+    );
+    virtual_machine.reset();
+    assert_eq!(
+        virtual_machine.virtual_processor().font_address(),
+        MAX_FONT_ADDRESS
+    );
+}
+
+#[test]
+fn font_address_applies_on_next_reset() {
+    let mut virtual_machine = VirtualMachine::new();
+    // V0 = 0x01, I = small 1.
+    virtual_machine
+        .load_rom_bytes(&[0x60, 0x01, 0xF0, 0x29])
+        .unwrap();
+    virtual_machine.set_font_address(0x50).unwrap();
+
+    // The font is still at 0, where I has to point until the reset.
+    virtual_machine.execute_processor_instruction();
+    virtual_machine.execute_processor_instruction();
+    let index_register = virtual_machine.virtual_processor().index_register();
+    assert_eq!(index_register, 5);
+    assert_eq!(virtual_machine.memory().read(index_register), 0x20);
+
+    virtual_machine.reset();
+    virtual_machine.execute_processor_instruction();
+    virtual_machine.execute_processor_instruction();
+    let index_register = virtual_machine.virtual_processor().index_register();
+    assert_eq!(index_register, 0x55);
+    assert_eq!(virtual_machine.memory().read(index_register), 0x20);
+}
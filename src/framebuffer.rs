@@ -0,0 +1,132 @@
+use crate::flicker_filter::FlickerFilter;
+use crate::palette::{Palette, PixelStyle, Rgb};
+
+const BYTES_PER_PIXEL: usize = 4;
+
+// RGBA image (one byte per channel, row by row) the screen is rendered into
+// before being handed to the frontend in one go.
+#[derive(Clone)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![0xFF; width as usize * height as usize * BYTES_PER_PIXEL],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pitch(&self) -> usize {
+        self.width as usize * BYTES_PER_PIXEL
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Rgb {
+        let index = (y as usize * self.width as usize + x as usize) * BYTES_PER_PIXEL;
+
+        Rgb {
+            r: self.pixels[index],
+            g: self.pixels[index + 1],
+            b: self.pixels[index + 2],
+        }
+    }
+
+    // Out of bounds pixels are ignored so callers can draw partly offscreen.
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Rgb) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+
+        let index = (y as usize * self.width as usize + x as usize) * BYTES_PER_PIXEL;
+        self.pixels[index] = color.r;
+        self.pixels[index + 1] = color.g;
+        self.pixels[index + 2] = color.b;
+        self.pixels[index + 3] = 0xFF;
+    }
+
+    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Rgb) {
+        for pixel_y in y..(y + height).min(self.height) {
+            for pixel_x in x..(x + width).min(self.width) {
+                self.set_pixel(pixel_x, pixel_y, color);
+            }
+        }
+    }
+
+    pub fn clear(&mut self, color: Rgb) {
+        self.fill_rect(0, 0, self.width, self.height, color);
+    }
+
+    // Draws the CHIP-8 screen as last sampled by the flicker filter, each pixel
+    // becoming a `scale` x `scale` block.
+    pub fn draw_screen(
+        &mut self,
+        flicker_filter: &FlickerFilter,
+        palette: &Palette,
+        pixel_style: PixelStyle,
+        scale: u32,
+    ) {
+        let (gap_color, gap) = match pixel_style {
+            PixelStyle::Solid => (palette.background(), 0),
+            PixelStyle::Grid => (palette.grid(), 1),
+            PixelStyle::Gap => (palette.background(), 2),
+        };
+        let gap = gap.min(scale - 1);
+
+        self.clear(gap_color);
+
+        for y in 0..flicker_filter.height() {
+            for x in 0..flicker_filter.width() {
+                let color = palette
+                    .background()
+                    .blend(palette.foreground(), flicker_filter.intensity(x, y));
+
+                self.fill_rect(
+                    x as u32 * scale,
+                    y as u32 * scale,
+                    scale - gap,
+                    scale - gap,
+                    color,
+                );
+            }
+        }
+    }
+
+    // Copies `source` resized to `width` x `height` at (x, y), nearest neighbour.
+    pub fn draw_scaled(&mut self, source: &Framebuffer, x: u32, y: u32, width: u32, height: u32) {
+        if source.width == 0 || source.height == 0 {
+            return;
+        }
+
+        for target_y in 0..height {
+            let source_y = target_y * source.height / height;
+            for target_x in 0..width {
+                let source_x = target_x * source.width / width;
+                self.set_pixel(
+                    x + target_x,
+                    y + target_y,
+                    source.get_pixel(source_x, source_y),
+                );
+            }
+        }
+    }
+}
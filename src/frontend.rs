@@ -0,0 +1,744 @@
+use crate::cheats::{Cheat, Cheats};
+use crate::config::Config;
+use crate::coverage::Coverage;
+use crate::fault::Fault;
+use crate::gdb_stub::GdbStub;
+use crate::globals::*;
+use crate::profiler::Profiler;
+use crate::quirks::Quirks;
+use crate::virtual_machine::VirtualMachine;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+// Keyboard layout shared by every frontend, the left side of a QWERTY keyboard
+// mapped onto the COSMAC VIP hexadecimal keypad.
+pub const KEYPAD_LAYOUT: [(char, u8); 16] = [
+    ('1', 0x1),
+    ('2', 0x2),
+    ('3', 0x3),
+    ('4', 0xC),
+    ('q', 0x4),
+    ('w', 0x5),
+    ('e', 0x6),
+    ('r', 0xD),
+    ('a', 0x7),
+    ('s', 0x8),
+    ('d', 0x9),
+    ('f', 0xE),
+    ('z', 0xA),
+    ('x', 0x0),
+    ('c', 0xB),
+    ('v', 0xF),
+];
+
+pub fn keypad_key_from_char(c: char) -> Option<u8> {
+    let c = c.to_ascii_lowercase();
+    KEYPAD_LAYOUT
+        .iter()
+        .find(|(layout_char, _)| *layout_char == c)
+        .map(|(_, key)| *key)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Hotkey {
+    TogglePause,
+    Pause,
+    Resume,
+    FrameAdvance,    // Runs a single frame while paused.
+    StepInstruction, // Runs a single instruction while paused.
+    SetFastForward(bool),
+    SlowDown,
+    SpeedUp,
+    Reset,
+}
+
+// How fast the emulated 60 Hz frames go compared to real time. Instructions,
+// timers and sound all follow, as they are all driven by the frames.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Speed {
+    Scaled(f32),
+    Uncapped, // As many frames as the host can run.
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed::Scaled(1.0)
+    }
+}
+
+const SPEED_PRESETS: [Speed; 6] = [
+    Speed::Scaled(0.25),
+    Speed::Scaled(0.5),
+    Speed::Scaled(1.0),
+    Speed::Scaled(2.0),
+    Speed::Scaled(4.0),
+    Speed::Uncapped,
+];
+
+// Share of a host frame spent running frames when uncapped, the rest is left
+// for input and drawing.
+const UNCAPPED_FRAME_BUDGET_MICROS: u64 = FRAME_DURATION_MICROS * 3 / 4;
+
+impl Speed {
+    // "uncapped", or a multiplier such as "2", "0.5" or "4x".
+    pub fn from_name(name: &str) -> Option<Speed> {
+        let name = name.to_ascii_lowercase();
+        if name == "uncapped" {
+            return Some(Speed::Uncapped);
+        }
+
+        name.trim_end_matches('x')
+            .parse::<f32>()
+            .ok()
+            .filter(|multiplier| *multiplier > 0.0 && multiplier.is_finite())
+            .map(Speed::Scaled)
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Speed::Scaled(multiplier) => format!("x{}", multiplier),
+            Speed::Uncapped => "uncapped".to_string(),
+        }
+    }
+
+    // The preset after (or before) this speed, staying on the last one.
+    fn next_preset(self, is_faster: bool) -> Speed {
+        let rank = |speed: &Speed| match speed {
+            Speed::Scaled(multiplier) => *multiplier,
+            Speed::Uncapped => f32::INFINITY,
+        };
+
+        let current_rank = rank(&self);
+        let preset = if is_faster {
+            SPEED_PRESETS
+                .iter()
+                .find(|preset| rank(preset) > current_rank)
+        } else {
+            SPEED_PRESETS
+                .iter()
+                .rev()
+                .find(|preset| rank(preset) < current_rank)
+        };
+
+        preset.copied().unwrap_or(self)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum FrontendEvent {
+    Quit,
+    KeyDown(u8),
+    KeyUp(u8),
+    Hotkey(Hotkey),
+    SaveState(u8), // Slot number.
+    LoadState(u8),
+    SetInstructionsPerFrame(u16),
+    SetQuirks(Quirks),
+    LoadRom(String), // Path of the ROM to switch to.
+    SetBreakpoint(u16, bool),
+    ToggleCheat(usize), // Index in the machine's cheats.
+    AddCheat(Cheat),
+}
+
+// What a user interface has to provide to be driven by the RunLoop.
+pub trait Frontend {
+    fn present_frame(&mut self, virtual_machine: &VirtualMachine);
+    fn poll_input(&mut self) -> Vec<FrontendEvent>;
+    fn set_audio(&mut self, is_active: bool);
+
+    fn should_quit(&self) -> bool {
+        false
+    }
+
+    // Frontends filtering the screen over time want every frame, not only the
+    // ones where the ROM has drawn something.
+    fn needs_continuous_repaint(&self) -> bool {
+        false
+    }
+
+    // Called at the end of every emulated frame, however many of them run
+    // between two presented ones (fast-forward, slow motion...).
+    fn frame_finished(&mut self, _virtual_machine: &VirtualMachine) {}
+
+    // Called with the instructions per frame the RunLoop runs, on the first
+    // step and after every change.
+    fn instructions_per_frame_changed(&mut self, _instructions_per_frame: u16) {}
+
+    // Feedback for the user (state saved, speed changed...), ignored by
+    // frontends without any place to show it.
+    fn show_message(&mut self, _message: &str) {}
+
+    // Called once the ROM asked for with FrontendEvent::LoadRom is loaded, the
+    // machine is reset afterwards so the settings applied here are used.
+    fn rom_changed(&mut self, _virtual_machine: &mut VirtualMachine, _rom_path: &str) {}
+
+    // Where save state slots are kept is up to the frontend.
+    fn write_state(&mut self, _slot: u8, _state: &[u8]) -> Result<(), String> {
+        Err("[Error] Save states are not supported by this frontend !".to_string())
+    }
+
+    fn read_state(&mut self, _slot: u8) -> Result<Vec<u8>, String> {
+        Err("[Error] Save states are not supported by this frontend !".to_string())
+    }
+
+    // Same for the cheats of each ROM, frontends without any storage keep them
+    // for the session only.
+    fn read_cheats(&mut self, _rom: &[u8]) -> Result<Cheats, String> {
+        Ok(Cheats::new())
+    }
+
+    fn write_cheats(&mut self, _rom: &[u8], _cheats: &Cheats) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// Timing, pause and hotkey handling shared by all frontends.
+pub struct RunLoop {
+    instructions_per_frame: u16,
+    is_paused: bool,
+    is_audio_active: bool,
+    speed: Speed,
+    fast_forward_speed: Speed,
+    is_fast_forwarding: bool,
+    frame_credit: f32, // Frames owed at the current speed, slow motion runs one every few steps.
+    frames_to_advance: u32,
+    instructions_to_step: u32,
+    breakpoints: HashSet<u16>,
+    is_leaving_breakpoint: bool, // The instruction a breakpoint stopped on runs when resuming.
+    hit_breakpoint: Option<u16>, // Not reported to the frontend yet.
+    reported_fault: Option<Fault>,
+    reported_instructions_per_frame: Option<u16>,
+    gdb_stub: Option<GdbStub>,
+}
+
+impl Default for RunLoop {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunLoop {
+    pub fn new() -> RunLoop {
+        RunLoop {
+            instructions_per_frame: INSTRUCTIONS_PER_FRAME,
+            is_paused: false,
+            is_audio_active: false,
+            speed: Speed::default(),
+            fast_forward_speed: Speed::Uncapped,
+            is_fast_forwarding: false,
+            frame_credit: 0.0,
+            frames_to_advance: 0,
+            instructions_to_step: 0,
+            breakpoints: HashSet::new(),
+            is_leaving_breakpoint: false,
+            hit_breakpoint: None,
+            reported_fault: None,
+            reported_instructions_per_frame: None,
+            gdb_stub: None,
+        }
+    }
+
+    pub fn set_instructions_per_frame(&mut self, instructions_per_frame: u16) {
+        self.instructions_per_frame = instructions_per_frame;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    pub fn set_paused(&mut self, is_paused: bool) {
+        self.is_paused = is_paused;
+    }
+
+    // Runs one more frame on the next step, only while paused.
+    pub fn advance_frame(&mut self) {
+        if self.is_paused {
+            self.frames_to_advance += 1;
+        }
+    }
+
+    // Runs one more instruction on the next step, only while paused.
+    pub fn step_instruction(&mut self) {
+        if self.is_paused {
+            self.instructions_to_step += 1;
+        }
+    }
+
+    // The machine pauses before running an instruction at this address.
+    pub fn set_breakpoint(&mut self, address: u16, is_set: bool) {
+        if is_set {
+            self.breakpoints.insert(address);
+        } else {
+            self.breakpoints.remove(&address);
+        }
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.speed = speed;
+        self.frame_credit = 0.0;
+    }
+
+    // Speed used while fast-forward is held, uncapped by default.
+    pub fn set_fast_forward_speed(&mut self, speed: Speed) {
+        self.fast_forward_speed = speed;
+    }
+
+    pub fn set_fast_forward(&mut self, is_fast_forwarding: bool) {
+        self.is_fast_forwarding = is_fast_forwarding;
+        self.frame_credit = 0.0;
+    }
+
+    // While a debugger is attached it decides when the machine runs, on top
+    // of the pause.
+    pub fn set_gdb_stub(&mut self, gdb_stub: Option<GdbStub>) {
+        self.gdb_stub = gdb_stub;
+    }
+
+    fn is_halted_by_debugger(&self) -> bool {
+        self.gdb_stub
+            .as_ref()
+            .is_some_and(|gdb_stub| gdb_stub.is_halted())
+    }
+
+    // Returns false once the debugger or the memory protection stopped the
+    // machine.
+    fn run_frame(
+        &mut self,
+        virtual_machine: &mut VirtualMachine,
+        frontend: &mut impl Frontend,
+    ) -> bool {
+        let is_frame_done = self.run_frame_instructions(virtual_machine);
+        if is_frame_done {
+            frontend.frame_finished(virtual_machine);
+        }
+        is_frame_done
+    }
+
+    fn run_frame_instructions(&mut self, virtual_machine: &mut VirtualMachine) -> bool {
+        match self.gdb_stub.as_mut() {
+            Some(gdb_stub) if self.breakpoints.is_empty() => {
+                gdb_stub.run_frame(virtual_machine, self.instructions_per_frame);
+                !gdb_stub.is_halted()
+            }
+            None if self.breakpoints.is_empty() => {
+                virtual_machine.run_frame(self.instructions_per_frame);
+                if virtual_machine.memory_break().is_some() {
+                    self.is_paused = true;
+                    return false;
+                }
+                true
+            }
+            // The breakpoints set from the frontend also hold while a debugger
+            // is attached, the debugger then keeps its own.
+            _ => loop {
+                let program_counter = virtual_machine.virtual_processor().program_counter();
+                if !self.is_leaving_breakpoint && self.breakpoints.contains(&program_counter) {
+                    self.is_paused = true;
+                    self.is_leaving_breakpoint = true;
+                    self.hit_breakpoint = Some(program_counter);
+                    return false;
+                }
+                self.is_leaving_breakpoint = false;
+
+                let is_frame_done = match self.gdb_stub.as_mut() {
+                    Some(gdb_stub) => {
+                        let is_frame_done =
+                            gdb_stub.step_instruction(virtual_machine, self.instructions_per_frame);
+                        if gdb_stub.is_halted() {
+                            return false;
+                        }
+                        is_frame_done
+                    }
+                    None => {
+                        let is_frame_done =
+                            virtual_machine.step_instruction(self.instructions_per_frame);
+                        if virtual_machine.memory_break().is_some() {
+                            self.is_paused = true;
+                            return false;
+                        }
+                        is_frame_done
+                    }
+                };
+                if is_frame_done {
+                    return true;
+                }
+            },
+        }
+    }
+
+    fn current_speed(&self) -> Speed {
+        if self.is_fast_forwarding {
+            self.fast_forward_speed
+        } else {
+            self.speed
+        }
+    }
+
+    fn run_frames(&mut self, virtual_machine: &mut VirtualMachine, frontend: &mut impl Frontend) {
+        if self.is_halted_by_debugger() {
+            self.frames_to_advance = 0;
+            self.instructions_to_step = 0;
+            return;
+        }
+
+        if self.is_paused {
+            for _ in 0..self.frames_to_advance {
+                if !self.run_frame(virtual_machine, frontend) {
+                    break;
+                }
+            }
+            for _ in 0..self.instructions_to_step {
+                if virtual_machine.step_instruction(self.instructions_per_frame) {
+                    frontend.frame_finished(virtual_machine);
+                }
+                self.is_leaving_breakpoint = false;
+            }
+            self.frames_to_advance = 0;
+            self.instructions_to_step = 0;
+            return;
+        }
+
+        match self.current_speed() {
+            Speed::Scaled(multiplier) => {
+                self.frame_credit += multiplier;
+                while self.frame_credit >= 1.0 {
+                    self.frame_credit -= 1.0;
+                    if !self.run_frame(virtual_machine, frontend) {
+                        self.frame_credit = 0.0;
+                        break;
+                    }
+                }
+            }
+            Speed::Uncapped => {
+                let start = Instant::now();
+                let budget = Duration::from_micros(UNCAPPED_FRAME_BUDGET_MICROS);
+                while start.elapsed() < budget {
+                    if !self.run_frame(virtual_machine, frontend) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn change_speed(&mut self, is_faster: bool, frontend: &mut impl Frontend) {
+        let speed = self.speed.next_preset(is_faster);
+        self.set_speed(speed);
+        frontend.show_message(&format!("Speed : {}", speed.name()));
+    }
+
+    // The cheats kept by the frontend for the ROM the machine runs.
+    pub fn load_cheats(
+        &mut self,
+        virtual_machine: &mut VirtualMachine,
+        frontend: &mut impl Frontend,
+    ) {
+        match frontend.read_cheats(virtual_machine.rom()) {
+            Ok(cheats) => virtual_machine.set_cheats(cheats),
+            Err(err_message) => frontend.show_message(&err_message),
+        }
+    }
+
+    fn save_cheats(virtual_machine: &VirtualMachine, frontend: &mut impl Frontend) {
+        if let Err(err_message) =
+            frontend.write_cheats(virtual_machine.rom(), virtual_machine.cheats())
+        {
+            frontend.show_message(&err_message);
+        }
+    }
+
+    // Runs a single 60 Hz frame without waiting, for hosts that have their own
+    // clock (requestAnimationFrame...). Returns false once the frontend wants to quit.
+    pub fn step(
+        &mut self,
+        virtual_machine: &mut VirtualMachine,
+        frontend: &mut impl Frontend,
+    ) -> bool {
+        for event in frontend.poll_input() {
+            match event {
+                FrontendEvent::Quit => return false,
+                FrontendEvent::KeyDown(key) => virtual_machine.set_key(key, true),
+                FrontendEvent::KeyUp(key) => virtual_machine.set_key(key, false),
+                FrontendEvent::Hotkey(Hotkey::TogglePause) => self.is_paused = !self.is_paused,
+                FrontendEvent::Hotkey(Hotkey::Pause) => self.is_paused = true,
+                FrontendEvent::Hotkey(Hotkey::Resume) => self.is_paused = false,
+                FrontendEvent::Hotkey(Hotkey::FrameAdvance) => self.advance_frame(),
+                FrontendEvent::Hotkey(Hotkey::StepInstruction) => self.step_instruction(),
+                FrontendEvent::Hotkey(Hotkey::SetFastForward(is_fast_forwarding)) => {
+                    self.set_fast_forward(is_fast_forwarding)
+                }
+                FrontendEvent::Hotkey(Hotkey::SlowDown) => self.change_speed(false, frontend),
+                FrontendEvent::Hotkey(Hotkey::SpeedUp) => self.change_speed(true, frontend),
+                FrontendEvent::Hotkey(Hotkey::Reset) => virtual_machine.reset(),
+                FrontendEvent::SaveState(slot) => {
+                    match frontend.write_state(slot, &virtual_machine.save_state()) {
+                        Ok(()) => frontend.show_message(&format!("State saved to slot {}", slot)),
+                        Err(err_message) => frontend.show_message(&err_message),
+                    }
+                }
+                FrontendEvent::LoadState(slot) => {
+                    let result = frontend.read_state(slot).and_then(|state| {
+                        virtual_machine
+                            .load_state(&state)
+                            .map_err(|err_message| err_message.to_string())
+                    });
+                    match result {
+                        Ok(()) => {
+                            frontend.show_message(&format!("State loaded from slot {}", slot))
+                        }
+                        Err(err_message) => frontend.show_message(&err_message),
+                    }
+                }
+                FrontendEvent::SetInstructionsPerFrame(instructions_per_frame) => {
+                    self.instructions_per_frame = instructions_per_frame;
+                    frontend.show_message(&format!(
+                        "Cycles : {} instructions per frame",
+                        instructions_per_frame
+                    ));
+                }
+                FrontendEvent::SetQuirks(quirks) => virtual_machine.set_quirks(quirks),
+                FrontendEvent::LoadRom(rom_path) => match virtual_machine.load_rom(&rom_path) {
+                    Ok(()) => {
+                        frontend.rom_changed(virtual_machine, &rom_path);
+                        virtual_machine.reset();
+                        self.load_cheats(virtual_machine, frontend);
+                    }
+                    Err(err_message) => frontend.show_message(err_message),
+                },
+                FrontendEvent::SetBreakpoint(address, is_set) => {
+                    self.set_breakpoint(address, is_set)
+                }
+                FrontendEvent::ToggleCheat(index) => {
+                    if let Some(cheat) = virtual_machine.toggle_cheat(index) {
+                        let state = if cheat.is_enabled { "on" } else { "off" };
+                        frontend.show_message(&format!("Cheat {} : {}", cheat.name, state));
+                        Self::save_cheats(virtual_machine, frontend);
+                    }
+                }
+                FrontendEvent::AddCheat(cheat) => {
+                    frontend.show_message(&format!("Cheat added : {}", cheat.name));
+                    virtual_machine.add_cheat(cheat);
+                    Self::save_cheats(virtual_machine, frontend);
+                }
+            }
+        }
+
+        if frontend.should_quit() {
+            return false;
+        }
+
+        if let Some(gdb_stub) = self.gdb_stub.as_mut() {
+            gdb_stub.poll(virtual_machine, self.instructions_per_frame);
+        }
+
+        self.run_frames(virtual_machine, frontend);
+        if let Some(address) = self.hit_breakpoint.take() {
+            frontend.show_message(&format!("Breakpoint at {:#05X}", address));
+        }
+        if let Some(write) = virtual_machine.take_memory_break() {
+            self.is_paused = true;
+            frontend.show_message(&write.to_string());
+        }
+
+        // The machine stays stopped on a fault, it is only reported once.
+        let fault = virtual_machine.fault();
+        if fault != self.reported_fault {
+            if let Some(fault) = fault {
+                frontend.show_message(&fault.to_string());
+            }
+            self.reported_fault = fault;
+        }
+
+        if self.reported_instructions_per_frame != Some(self.instructions_per_frame) {
+            frontend.instructions_per_frame_changed(self.instructions_per_frame);
+            self.reported_instructions_per_frame = Some(self.instructions_per_frame);
+        }
+
+        let is_audio_active =
+            !self.is_paused && !self.is_halted_by_debugger() && virtual_machine.is_sound_active();
+        if is_audio_active != self.is_audio_active {
+            frontend.set_audio(is_audio_active);
+            self.is_audio_active = is_audio_active;
+        }
+
+        if virtual_machine.screen_need_repaint() || frontend.needs_continuous_repaint() {
+            frontend.present_frame(virtual_machine);
+            virtual_machine.disable_repaint();
+        }
+
+        true
+    }
+
+    // Runs frames at 60 Hz until the frontend wants to quit.
+    pub fn run(&mut self, virtual_machine: &mut VirtualMachine, frontend: &mut impl Frontend) {
+        let frame_duration = Duration::from_micros(FRAME_DURATION_MICROS);
+        let mut next_frame = Instant::now();
+
+        while self.step(virtual_machine, frontend) {
+            next_frame += frame_duration;
+
+            let now = Instant::now();
+            if next_frame > now {
+                std::thread::sleep(next_frame - now);
+            } else {
+                next_frame = now; // Running late, do not try to catch up.
+            }
+        }
+    }
+}
+
+// The debugging tools asked for on the command line, whose reports are written
+// once the emulation is over.
+pub struct Tools {
+    profile_path: Option<String>,
+    profile_folded_path: Option<String>,
+    coverage_path: Option<String>,
+    coverage_html_path: Option<String>,
+}
+
+// Attaches the tracer, profiler and coverage to the machine and the GDB stub to
+// the run loop. The stub waits for GDB to connect before returning.
+pub fn setup_tools(
+    config: &Config,
+    virtual_machine: &mut VirtualMachine,
+    run_loop: &mut RunLoop,
+) -> Result<Tools, String> {
+    if config.is_profiling() {
+        virtual_machine.set_profiler(Some(Profiler::new()));
+    }
+    if config.is_recording_coverage() {
+        virtual_machine.set_coverage(Some(Coverage::new()));
+    }
+    virtual_machine.set_tracer(config.create_tracer()?);
+
+    if let Some(gdb_address) = &config.gdb_address {
+        run_loop.set_gdb_stub(Some(GdbStub::bind(gdb_address)?));
+    }
+
+    Ok(Tools {
+        profile_path: config.profile_path.clone(),
+        profile_folded_path: config.profile_folded_path.clone(),
+        coverage_path: config.coverage_path.clone(),
+        coverage_html_path: config.coverage_html_path.clone(),
+    })
+}
+
+impl Tools {
+    // Writes the reports, one failing does not prevent the others. Returns the
+    // errors met.
+    pub fn finish(&self, virtual_machine: &VirtualMachine) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if let Some(profiler) = virtual_machine.profiler() {
+            let result = profiler.save(
+                self.profile_path.as_deref(),
+                self.profile_folded_path.as_deref(),
+            );
+            errors.extend(result.err());
+        }
+
+        if let Some(coverage) = virtual_machine.coverage() {
+            let result = coverage.save(
+                virtual_machine.rom(),
+                self.coverage_path.as_deref(),
+                self.coverage_html_path.as_deref(),
+            );
+            errors.extend(result.err());
+        }
+
+        errors
+    }
+}
+
+// Frontend without any output, running a fixed number of frames. Handy to run
+// ROMs in tests or to generate screenshots.
+pub struct HeadlessFrontend {
+    frames_left: u32,
+    presented_frames: u32,
+    finished_frames: u32,
+    is_audio_active: bool,
+    instructions_per_frame: Option<u16>,
+    pending_events: Vec<FrontendEvent>,
+    messages: Vec<String>,
+}
+
+impl HeadlessFrontend {
+    pub fn new(frames: u32) -> HeadlessFrontend {
+        HeadlessFrontend {
+            frames_left: frames,
+            presented_frames: 0,
+            finished_frames: 0,
+            is_audio_active: false,
+            instructions_per_frame: None,
+            pending_events: Vec::new(),
+            messages: Vec::new(),
+        }
+    }
+
+    pub fn presented_frames(&self) -> u32 {
+        self.presented_frames
+    }
+
+    // Emulated frames, more or fewer than the presented ones away from the
+    // normal speed.
+    pub fn finished_frames(&self) -> u32 {
+        self.finished_frames
+    }
+
+    pub fn is_audio_active(&self) -> bool {
+        self.is_audio_active
+    }
+
+    // As last reported by the RunLoop.
+    pub fn instructions_per_frame(&self) -> Option<u16> {
+        self.instructions_per_frame
+    }
+
+    // Given to the RunLoop on the next step, as if the user had done it.
+    pub fn push_event(&mut self, event: FrontendEvent) {
+        self.pending_events.push(event);
+    }
+
+    // Every message shown so far, the oldest first.
+    pub fn messages(&self) -> &[String] {
+        &self.messages
+    }
+}
+
+impl Frontend for HeadlessFrontend {
+    fn present_frame(&mut self, _virtual_machine: &VirtualMachine) {
+        self.presented_frames += 1;
+    }
+
+    fn poll_input(&mut self) -> Vec<FrontendEvent> {
+        if self.frames_left == 0 {
+            return vec![FrontendEvent::Quit];
+        }
+
+        self.frames_left -= 1;
+        std::mem::take(&mut self.pending_events)
+    }
+
+    fn set_audio(&mut self, is_active: bool) {
+        self.is_audio_active = is_active;
+    }
+
+    fn frame_finished(&mut self, _virtual_machine: &VirtualMachine) {
+        self.finished_frames += 1;
+    }
+
+    fn instructions_per_frame_changed(&mut self, instructions_per_frame: u16) {
+        self.instructions_per_frame = Some(instructions_per_frame);
+    }
+
+    fn show_message(&mut self, message: &str) {
+        self.messages.push(message.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests;
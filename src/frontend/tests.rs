@@ -0,0 +1,251 @@
+use super::*;
+use std::fs;
+
+const INSTRUCTIONS: u16 = 10;
+
+// JP 0x200, so every frame runs the same number of instructions.
+const PROGRAM: [u8; 2] = [0x12, 0x00];
+
+fn machine() -> VirtualMachine {
+    let mut virtual_machine = VirtualMachine::new();
+    virtual_machine.load_rom_bytes(&PROGRAM).unwrap();
+    virtual_machine
+}
+
+fn run_loop() -> RunLoop {
+    let mut run_loop = RunLoop::new();
+    run_loop.set_instructions_per_frame(INSTRUCTIONS);
+    run_loop
+}
+
+// Frames the machine ran during a number of steps of the RunLoop.
+fn frames_run(
+    run_loop: &mut RunLoop,
+    virtual_machine: &mut VirtualMachine,
+    frontend: &mut HeadlessFrontend,
+    steps: u32,
+) -> u64 {
+    let start = virtual_machine.executed_instructions();
+    for _ in 0..steps {
+        assert!(run_loop.step(virtual_machine, frontend));
+    }
+
+    let instructions = virtual_machine.executed_instructions() - start;
+    assert_eq!(instructions % INSTRUCTIONS as u64, 0);
+    instructions / INSTRUCTIONS as u64
+}
+
+#[test]
+fn runs_a_frame_per_step_until_the_frontend_quits() {
+    let (mut run_loop, mut virtual_machine) = (run_loop(), machine());
+    let mut frontend = HeadlessFrontend::new(3);
+
+    run_loop.run(&mut virtual_machine, &mut frontend);
+    assert_eq!(
+        virtual_machine.executed_instructions(),
+        3 * INSTRUCTIONS as u64
+    );
+    assert!(!run_loop.step(&mut virtual_machine, &mut frontend));
+}
+
+#[test]
+fn pause_and_frame_advance() {
+    let (mut run_loop, mut virtual_machine) = (run_loop(), machine());
+    let mut frontend = HeadlessFrontend::new(100);
+
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::Pause));
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 5),
+        0
+    );
+    assert!(run_loop.is_paused());
+
+    // A single frame for each press, the machine stays paused.
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::FrameAdvance));
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 3),
+        1
+    );
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::FrameAdvance));
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::FrameAdvance));
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 3),
+        2
+    );
+
+    let start = virtual_machine.executed_instructions();
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::StepInstruction));
+    run_loop.step(&mut virtual_machine, &mut frontend);
+    assert_eq!(virtual_machine.executed_instructions(), start + 1);
+
+    // Resuming finishes the frame the instruction was part of, frame advance
+    // does nothing while running.
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::TogglePause));
+    run_loop.advance_frame();
+    run_loop.step(&mut virtual_machine, &mut frontend);
+    assert!(!run_loop.is_paused());
+    assert_eq!(
+        virtual_machine.executed_instructions(),
+        start + INSTRUCTIONS as u64
+    );
+}
+
+#[test]
+fn slow_motion_runs_a_frame_every_few_steps() {
+    let (mut run_loop, mut virtual_machine) = (run_loop(), machine());
+    let mut frontend = HeadlessFrontend::new(100);
+
+    run_loop.set_speed(Speed::Scaled(0.25));
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 3),
+        0
+    );
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 1),
+        1
+    );
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 8),
+        2
+    );
+
+    assert_eq!(frontend.finished_frames(), 3);
+
+    // Frames owed at the old speed are dropped.
+    run_loop.set_speed(Speed::Scaled(0.5));
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 1),
+        0
+    );
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 1),
+        1
+    );
+}
+
+#[test]
+fn speed_hotkeys_go_through_the_presets() {
+    let (mut run_loop, mut virtual_machine) = (run_loop(), machine());
+    let mut frontend = HeadlessFrontend::new(100);
+
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::SpeedUp));
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 2),
+        4
+    );
+    assert_eq!(run_loop.speed(), Speed::Scaled(2.0));
+
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::SlowDown));
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::SlowDown));
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 2),
+        1
+    );
+    assert_eq!(
+        frontend.messages(),
+        ["Speed : x2", "Speed : x1", "Speed : x0.5"]
+    );
+}
+
+#[test]
+fn fast_forward_while_held() {
+    let (mut run_loop, mut virtual_machine) = (run_loop(), machine());
+    let mut frontend = HeadlessFrontend::new(100);
+
+    run_loop.set_fast_forward_speed(Speed::Scaled(4.0));
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::SetFastForward(true)));
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 2),
+        8
+    );
+
+    assert_eq!(frontend.finished_frames(), 8);
+
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::SetFastForward(false)));
+    assert_eq!(
+        frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 2),
+        2
+    );
+
+    // Uncapped, as many frames as fit in the host frame.
+    run_loop.set_fast_forward_speed(Speed::Uncapped);
+    run_loop.set_fast_forward(true);
+    assert!(frames_run(&mut run_loop, &mut virtual_machine, &mut frontend, 1) > 1);
+}
+
+#[test]
+fn instructions_per_frame_are_reported_to_the_frontend() {
+    let (mut run_loop, mut virtual_machine) = (run_loop(), machine());
+    let mut frontend = HeadlessFrontend::new(100);
+    assert_eq!(frontend.instructions_per_frame(), None);
+
+    run_loop.step(&mut virtual_machine, &mut frontend);
+    assert_eq!(frontend.instructions_per_frame(), Some(INSTRUCTIONS));
+
+    frontend.push_event(FrontendEvent::SetInstructionsPerFrame(50));
+    run_loop.step(&mut virtual_machine, &mut frontend);
+    assert_eq!(frontend.instructions_per_frame(), Some(50));
+}
+
+#[test]
+fn speed_names() {
+    assert_eq!(Speed::from_name("2"), Some(Speed::Scaled(2.0)));
+    assert_eq!(Speed::from_name("0.5x"), Some(Speed::Scaled(0.5)));
+    assert_eq!(Speed::from_name("Uncapped"), Some(Speed::Uncapped));
+    assert_eq!(Speed::from_name("0"), None);
+    assert_eq!(Speed::from_name("fast"), None);
+    assert_eq!(Speed::Scaled(0.25).next_preset(false), Speed::Scaled(0.25));
+    assert_eq!(Speed::Scaled(3.0).next_preset(true), Speed::Scaled(4.0));
+    assert_eq!(Speed::Uncapped.next_preset(true), Speed::Uncapped);
+}
+
+#[test]
+fn tools_reports_are_written_on_finish() {
+    let directory = std::env::temp_dir();
+    let profile_path = directory.join(format!("mch8-test-{}.profile", std::process::id()));
+    let coverage_path = directory.join(format!("mch8-test-{}.coverage", std::process::id()));
+    let mut config = Config::new();
+    config
+        .set("profile", profile_path.to_str().unwrap())
+        .unwrap();
+    config
+        .set("coverage", coverage_path.to_str().unwrap())
+        .unwrap();
+
+    let mut virtual_machine = machine();
+    let mut run_loop = run_loop();
+    let tools = setup_tools(&config, &mut virtual_machine, &mut run_loop).unwrap();
+    assert!(virtual_machine.profiler().is_some());
+    assert!(virtual_machine.coverage().is_some());
+
+    frames_run(
+        &mut run_loop,
+        &mut virtual_machine,
+        &mut HeadlessFrontend::new(10),
+        2,
+    );
+    let errors = tools.finish(&virtual_machine);
+    let profile = fs::read_to_string(&profile_path);
+    let coverage = fs::read_to_string(&coverage_path);
+    let _ = fs::remove_file(&profile_path);
+    let _ = fs::remove_file(&coverage_path);
+
+    assert!(errors.is_empty());
+    assert!(!profile.unwrap().is_empty());
+    assert!(coverage.unwrap().contains("JP 0x200"));
+}
+
+#[test]
+fn tools_setup_and_report_errors() {
+    let mut config = Config::new();
+    config.set("gdb", "not an address").unwrap();
+    let mut virtual_machine = machine();
+    assert!(setup_tools(&config, &mut virtual_machine, &mut run_loop()).is_err());
+
+    let mut config = Config::new();
+    config
+        .set("profile", "/no/such/directory/profile.txt")
+        .unwrap();
+    let tools = setup_tools(&config, &mut virtual_machine, &mut run_loop()).unwrap();
+    assert_eq!(tools.finish(&virtual_machine).len(), 1);
+}
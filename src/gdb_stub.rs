@@ -0,0 +1,691 @@
+use crate::log_info;
+use crate::memory::TOTAL_MEMORY_BYTES;
+use crate::virtual_machine::VirtualMachine;
+use std::collections::HashSet;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::PathBuf;
+
+const UNIX_SOCKET_PREFIX: &str = "unix:";
+const MAX_PACKET_SIZE: usize = 0x1000;
+const INTERRUPT_BYTE: u8 = 0x03;
+const SIGNAL_INTERRUPT: u8 = 2;
+const SIGNAL_TRAP: u8 = 5;
+const SIGNAL_SEGMENTATION_FAULT: u8 = 11;
+
+// Register numbers as GDB sees them, the order of the `g` packet.
+const REGISTER_I: usize = 16;
+const REGISTER_PC: usize = 17;
+const REGISTER_SP: usize = 18;
+const REGISTER_DT: usize = 19;
+const REGISTER_ST: usize = 20;
+const REGISTERS_COUNT: usize = 21;
+
+fn register_size(register: usize) -> usize {
+    match register {
+        REGISTER_I | REGISTER_PC => 2,
+        _ => 1,
+    }
+}
+
+fn register_name(register: usize) -> String {
+    match register {
+        REGISTER_I => "i".to_string(),
+        REGISTER_PC => "pc".to_string(),
+        REGISTER_SP => "sp".to_string(),
+        REGISTER_DT => "dt".to_string(),
+        REGISTER_ST => "st".to_string(),
+        _ => format!("v{:x}", register),
+    }
+}
+
+// Description of the registers sent to GDB, which knows nothing of Chip-8.
+fn target_description() -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\"?>\n<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n\
+         <target version=\"1.0\">\n<feature name=\"org.mch8.chip8\">\n",
+    );
+
+    for register in 0..REGISTERS_COUNT {
+        let register_type = match register {
+            REGISTER_I => "data_ptr",
+            REGISTER_PC => "code_ptr",
+            _ => "uint8",
+        };
+        xml.push_str(&format!(
+            "<reg name=\"{}\" bitsize=\"{}\" regnum=\"{}\" type=\"{}\"/>\n",
+            register_name(register),
+            register_size(register) * 8,
+            register,
+            register_type
+        ));
+    }
+
+    xml.push_str("</feature>\n</target>\n");
+    xml
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_number(text: &str) -> Option<usize> {
+    usize::from_str_radix(text, 16).ok()
+}
+
+// "addr,length" as found in the m, M and Z packets.
+fn parse_address_length(text: &str) -> Option<(usize, usize)> {
+    let (address, length) = text.split_once(',')?;
+    Some((parse_number(address)?, parse_number(length)?))
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+}
+
+trait Connection: Read + Write {
+    fn set_nonblocking(&self, is_nonblocking: bool) -> io::Result<()>;
+}
+
+impl Connection for TcpStream {
+    fn set_nonblocking(&self, is_nonblocking: bool) -> io::Result<()> {
+        TcpStream::set_nonblocking(self, is_nonblocking)
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn set_nonblocking(&self, is_nonblocking: bool) -> io::Result<()> {
+        UnixStream::set_nonblocking(self, is_nonblocking)
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    fn bind(address: &str) -> io::Result<Listener> {
+        if let Some(path) = address.strip_prefix(UNIX_SOCKET_PREFIX) {
+            #[cfg(unix)]
+            {
+                let _ = std::fs::remove_file(path); // Left behind by a previous run.
+                let listener = UnixListener::bind(path)?;
+                listener.set_nonblocking(true)?;
+                return Ok(Listener::Unix(listener, PathBuf::from(path)));
+            }
+
+            #[cfg(not(unix))]
+            {
+                let _ = path;
+                return Err(io::Error::new(
+                    ErrorKind::Unsupported,
+                    "Unix sockets are not available on this platform",
+                ));
+            }
+        }
+
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Listener::Tcp(listener))
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn Connection>> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, _) = listener.accept()?;
+                let _ = stream.set_nodelay(true);
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => Ok(Box::new(listener.accept()?.0)),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Listener::Unix(_, path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+// Server side of the GDB remote serial protocol, letting GDB (or any RSP
+// client) drive the machine. The RunLoop keeps presenting frames and polling
+// input while the machine is halted.
+pub struct GdbStub {
+    listener: Listener,
+    connection: Option<Box<dyn Connection>>,
+    input: Vec<u8>,
+    last_packet: Vec<u8>, // Sent again when the client asks for it with a '-'.
+    is_ack_mode: bool,
+    breakpoints: HashSet<u16>,
+    is_halted: bool,
+    is_resuming: bool, // Do not stop on the breakpoint the machine was just continued from.
+}
+
+impl GdbStub {
+    // "127.0.0.1:1234", or "unix:/tmp/mch8.sock" for a Unix socket. The
+    // machine waits halted until a client connects and continues it.
+    pub fn bind(address: &str) -> Result<GdbStub, String> {
+        let listener = Listener::bind(address).map_err(|err| {
+            format!(
+                "[Error] Unable to listen for GDB on '{}' : {} !",
+                address, err
+            )
+        })?;
+        log_info!("Waiting for GDB on {}", address);
+
+        Ok(GdbStub {
+            listener,
+            connection: None,
+            input: Vec::new(),
+            last_packet: Vec::new(),
+            is_ack_mode: true,
+            breakpoints: HashSet::new(),
+            is_halted: true,
+            is_resuming: false,
+        })
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.is_halted
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    // Accepts a client and handles what it sent, without ever blocking.
+    pub fn poll(&mut self, virtual_machine: &mut VirtualMachine, instructions_per_frame: u16) {
+        if self.connection.is_none() {
+            self.accept_connection();
+        }
+
+        let Some(connection) = self.connection.as_mut() else {
+            return;
+        };
+
+        let mut buffer = [0; 1024];
+        let is_closed = loop {
+            match connection.read(&mut buffer) {
+                Ok(0) => break true,
+                Ok(length) => self.input.extend_from_slice(&buffer[..length]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break false,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => break true,
+            }
+        };
+
+        self.handle_input(virtual_machine, instructions_per_frame);
+        if is_closed {
+            self.disconnect();
+        }
+    }
+
+    fn accept_connection(&mut self) {
+        let Ok(connection) = self.listener.accept() else {
+            return;
+        };
+
+        if connection.set_nonblocking(true).is_ok() {
+            log_info!("GDB connected");
+            self.connection = Some(connection);
+            self.input.clear();
+            self.is_ack_mode = true;
+            self.is_halted = true;
+        }
+    }
+
+    // The ROM goes on running once the debugger is gone.
+    fn disconnect(&mut self) {
+        if self.connection.take().is_some() {
+            log_info!("GDB disconnected");
+        }
+
+        self.breakpoints.clear();
+        self.is_halted = false;
+    }
+
+    // Runs the rest of the current frame, stopping before any instruction on a
+    // breakpoint.
+    pub fn run_frame(&mut self, virtual_machine: &mut VirtualMachine, instructions_per_frame: u16) {
+        while !self.step_instruction(virtual_machine, instructions_per_frame) {}
+    }
+
+    // Runs one instruction unless a fault or a breakpoint stops the machine
+    // first. Returns true once the frame is done or the machine is halted.
+    pub fn step_instruction(
+        &mut self,
+        virtual_machine: &mut VirtualMachine,
+        instructions_per_frame: u16,
+    ) -> bool {
+        if self.is_halted {
+            return true;
+        }
+        if virtual_machine.fault().is_some() {
+            self.stop(SIGNAL_SEGMENTATION_FAULT);
+            return true;
+        }
+
+        let program_counter = virtual_machine.virtual_processor().program_counter();
+        if !self.is_resuming && self.breakpoints.contains(&program_counter) {
+            self.stop(SIGNAL_TRAP);
+            return true;
+        }
+        self.is_resuming = false;
+
+        let is_frame_done = virtual_machine.step_instruction(instructions_per_frame);
+        if let Some(write) = virtual_machine.take_memory_break() {
+            log_info!("{}", write);
+            self.stop(SIGNAL_TRAP);
+            return true;
+        }
+        is_frame_done
+    }
+
+    // A faulted machine cannot go any further, GDB shows it as a crash.
+    fn stop_signal(virtual_machine: &VirtualMachine) -> u8 {
+        if virtual_machine.fault().is_some() {
+            SIGNAL_SEGMENTATION_FAULT
+        } else {
+            SIGNAL_TRAP
+        }
+    }
+
+    fn stop(&mut self, signal: u8) {
+        self.is_halted = true;
+        self.send_packet(&format!("S{:02x}", signal));
+    }
+
+    fn handle_input(&mut self, virtual_machine: &mut VirtualMachine, instructions_per_frame: u16) {
+        loop {
+            let Some(start) = self.input.iter().position(|byte| *byte != b'+') else {
+                self.input.clear();
+                return;
+            };
+            self.input.drain(..start);
+
+            match self.input[0] {
+                INTERRUPT_BYTE => {
+                    self.input.remove(0);
+                    if !self.is_halted {
+                        self.stop(SIGNAL_INTERRUPT);
+                    }
+                }
+                b'-' => {
+                    self.input.remove(0);
+                    let last_packet = self.last_packet.clone();
+                    self.write(&last_packet);
+                }
+                b'$' => {
+                    let Some(end) = self.input.iter().position(|byte| *byte == b'#') else {
+                        return; // Wait for the rest of the packet.
+                    };
+                    if self.input.len() < end + 3 {
+                        return;
+                    }
+
+                    let data: Vec<u8> = self.input[1..end].to_vec();
+                    let expected_checksum = std::str::from_utf8(&self.input[end + 1..end + 3])
+                        .ok()
+                        .and_then(|text| u8::from_str_radix(text, 16).ok());
+                    self.input.drain(..end + 3);
+
+                    if self.is_ack_mode {
+                        if expected_checksum != Some(checksum(&data)) {
+                            self.write(b"-");
+                            continue;
+                        }
+                        self.write(b"+");
+                    }
+
+                    let packet = String::from_utf8_lossy(&data).into_owned();
+                    if let Some(response) =
+                        self.handle_packet(&packet, virtual_machine, instructions_per_frame)
+                    {
+                        self.send_packet(&response);
+                    }
+                }
+                _ => {
+                    self.input.remove(0); // Noise between packets.
+                }
+            }
+        }
+    }
+
+    // Returns the response to send, None when it comes later (continue).
+    fn handle_packet(
+        &mut self,
+        packet: &str,
+        virtual_machine: &mut VirtualMachine,
+        instructions_per_frame: u16,
+    ) -> Option<String> {
+        let command = packet.chars().next().unwrap_or(' ');
+        let arguments = packet.get(1..).unwrap_or("");
+
+        let response = match command {
+            '?' => format!("S{:02x}", Self::stop_signal(virtual_machine)),
+            'g' => to_hex(&read_registers(virtual_machine)),
+            'G' => match from_hex(arguments) {
+                Some(bytes) if write_registers(virtual_machine, &bytes) => "OK".to_string(),
+                _ => "E01".to_string(),
+            },
+            'p' => match parse_number(arguments).and_then(|register| {
+                read_registers_layout(virtual_machine)
+                    .into_iter()
+                    .nth(register)
+            }) {
+                Some(bytes) => to_hex(&bytes),
+                None => "E01".to_string(),
+            },
+            'P' => {
+                let result = arguments.split_once('=').and_then(|(register, value)| {
+                    let register = parse_number(register)?;
+                    let bytes = from_hex(value)?;
+                    write_register(virtual_machine, register, &bytes).then_some(())
+                });
+                match result {
+                    Some(()) => "OK".to_string(),
+                    None => "E01".to_string(),
+                }
+            }
+            'm' => match parse_address_length(arguments)
+                .and_then(|(address, length)| read_memory(virtual_machine, address, length))
+            {
+                Some(bytes) => to_hex(&bytes),
+                None => "E01".to_string(),
+            },
+            'M' => {
+                let result = arguments.split_once(':').and_then(|(range, data)| {
+                    let (address, length) = parse_address_length(range)?;
+                    let bytes = from_hex(data).filter(|bytes| bytes.len() == length)?;
+                    write_memory(virtual_machine, address, &bytes).then_some(())
+                });
+                match result {
+                    Some(()) => "OK".to_string(),
+                    None => "E01".to_string(),
+                }
+            }
+            'Z' | 'z' => self.handle_breakpoint(command == 'Z', arguments),
+            's' => {
+                self.step(virtual_machine, arguments, instructions_per_frame);
+                format!("S{:02x}", Self::stop_signal(virtual_machine))
+            }
+            'c' => {
+                self.resume(virtual_machine, arguments);
+                return None;
+            }
+            'v' => return self.handle_v_packet(packet, virtual_machine, instructions_per_frame),
+            'q' | 'Q' => self.handle_query(packet),
+            'H' | 'T' => "OK".to_string(), // A single thread, always alive.
+            'D' => {
+                self.breakpoints.clear();
+                self.is_halted = false;
+                "OK".to_string()
+            }
+            'k' => {
+                self.disconnect();
+                return None;
+            }
+            _ => String::new(), // Empty means unsupported.
+        };
+
+        Some(response)
+    }
+
+    // Software and hardware breakpoints are the same thing here, watchpoints
+    // are not supported.
+    fn handle_breakpoint(&mut self, is_insert: bool, arguments: &str) -> String {
+        let mut fields = arguments.split(',');
+        let kind = fields.next();
+        let address = fields.next().and_then(parse_number);
+
+        match (kind, address) {
+            (Some("0") | Some("1"), Some(address)) if address < TOTAL_MEMORY_BYTES as usize => {
+                if is_insert {
+                    self.breakpoints.insert(address as u16);
+                } else {
+                    self.breakpoints.remove(&(address as u16));
+                }
+                "OK".to_string()
+            }
+            (Some("0") | Some("1"), _) => "E01".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    // `s` and `c` may give the address to go on from.
+    fn jump_to(virtual_machine: &mut VirtualMachine, address: &str) {
+        if let Some(address) = parse_number(address) {
+            virtual_machine
+                .virtual_processor_mut()
+                .set_program_counter(address as u16);
+        }
+    }
+
+    fn step(
+        &mut self,
+        virtual_machine: &mut VirtualMachine,
+        address: &str,
+        instructions_per_frame: u16,
+    ) {
+        Self::jump_to(virtual_machine, address);
+        virtual_machine.step_instruction(instructions_per_frame);
+        self.is_halted = true;
+    }
+
+    fn resume(&mut self, virtual_machine: &mut VirtualMachine, address: &str) {
+        Self::jump_to(virtual_machine, address);
+        self.is_halted = false;
+        self.is_resuming = true;
+    }
+
+    fn handle_v_packet(
+        &mut self,
+        packet: &str,
+        virtual_machine: &mut VirtualMachine,
+        instructions_per_frame: u16,
+    ) -> Option<String> {
+        if packet == "vCont?" {
+            return Some("vCont;c;C;s;S".to_string());
+        }
+
+        let Some(actions) = packet.strip_prefix("vCont;") else {
+            return Some(String::new());
+        };
+
+        // With a single thread, only the first action matters.
+        match actions.chars().next() {
+            Some('s') | Some('S') => {
+                self.step(virtual_machine, "", instructions_per_frame);
+                Some(format!("S{:02x}", Self::stop_signal(virtual_machine)))
+            }
+            Some('c') | Some('C') => {
+                self.resume(virtual_machine, "");
+                None
+            }
+            _ => Some("E01".to_string()),
+        }
+    }
+
+    fn handle_query(&mut self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            return format!(
+                "PacketSize={:x};qXfer:features:read+;QStartNoAckMode+",
+                MAX_PACKET_SIZE
+            );
+        }
+
+        if packet == "QStartNoAckMode" {
+            self.is_ack_mode = false;
+            return "OK".to_string();
+        }
+
+        if let Some(range) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let Some((offset, length)) = parse_address_length(range) else {
+                return "E01".to_string();
+            };
+
+            let description = target_description();
+            let start = offset.min(description.len());
+            let end = (start + length).min(description.len());
+            let marker = if end == description.len() { 'l' } else { 'm' };
+            return format!("{}{}", marker, &description[start..end]);
+        }
+
+        match packet {
+            "qAttached" => "1".to_string(),
+            "qC" => "QC1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn send_packet(&mut self, data: &str) {
+        let packet = format!("${}#{:02x}", data, checksum(data.as_bytes()));
+        self.last_packet = packet.clone().into_bytes();
+        self.write(packet.as_bytes());
+    }
+
+    // Packets are small, a client too slow to take them is dropped.
+    fn write(&mut self, bytes: &[u8]) {
+        let Some(connection) = self.connection.as_mut() else {
+            return;
+        };
+
+        let mut written = 0;
+        while written < bytes.len() {
+            match connection.write(&bytes[written..]) {
+                Ok(0) => break,
+                Ok(length) => written += length,
+                Err(err)
+                    if err.kind() == ErrorKind::WouldBlock
+                        || err.kind() == ErrorKind::Interrupted =>
+                {
+                    std::thread::yield_now()
+                }
+                Err(_) => break,
+            }
+        }
+
+        if written < bytes.len() {
+            self.disconnect();
+        }
+    }
+}
+
+// One entry per register, each in target (little endian) order.
+fn read_registers_layout(virtual_machine: &VirtualMachine) -> Vec<Vec<u8>> {
+    let processor = virtual_machine.virtual_processor();
+
+    let mut registers: Vec<Vec<u8>> = processor
+        .registers()
+        .iter()
+        .map(|register| vec![*register])
+        .collect();
+    registers.push(processor.index_register().to_le_bytes().to_vec());
+    registers.push(processor.program_counter().to_le_bytes().to_vec());
+    registers.push(vec![processor.stack_pointer()]);
+    registers.push(vec![processor.delay_timer()]);
+    registers.push(vec![processor.sound_timer()]);
+    registers
+}
+
+fn read_registers(virtual_machine: &VirtualMachine) -> Vec<u8> {
+    read_registers_layout(virtual_machine).concat()
+}
+
+// The stack pointer is read only, the stack itself is not addressable.
+fn write_register(virtual_machine: &mut VirtualMachine, register: usize, bytes: &[u8]) -> bool {
+    if register >= REGISTERS_COUNT || bytes.len() != register_size(register) {
+        return false;
+    }
+
+    let processor = virtual_machine.virtual_processor_mut();
+    match register {
+        REGISTER_I => processor.set_index_register(u16::from_le_bytes([bytes[0], bytes[1]])),
+        REGISTER_PC => processor.set_program_counter(u16::from_le_bytes([bytes[0], bytes[1]])),
+        REGISTER_SP => return bytes[0] == processor.stack_pointer(),
+        REGISTER_DT => processor.set_delay_timer(bytes[0]),
+        REGISTER_ST => processor.set_sound_timer(bytes[0]),
+        _ => processor.set_register(register, bytes[0]),
+    }
+
+    true
+}
+
+fn write_registers(virtual_machine: &mut VirtualMachine, bytes: &[u8]) -> bool {
+    let total_size: usize = (0..REGISTERS_COUNT).map(register_size).sum();
+    if bytes.len() != total_size {
+        return false;
+    }
+
+    let mut offset = 0;
+    for register in 0..REGISTERS_COUNT {
+        let size = register_size(register);
+        // GDB writes back the stack pointer it read, anything else is refused.
+        if register != REGISTER_SP
+            && !write_register(virtual_machine, register, &bytes[offset..offset + size])
+        {
+            return false;
+        }
+        offset += size;
+    }
+
+    true
+}
+
+fn is_memory_range(address: usize, length: usize) -> bool {
+    address
+        .checked_add(length)
+        .is_some_and(|end| end <= TOTAL_MEMORY_BYTES as usize)
+}
+
+fn read_memory(virtual_machine: &VirtualMachine, address: usize, length: usize) -> Option<Vec<u8>> {
+    if !is_memory_range(address, length) {
+        return None;
+    }
+
+    let memory = virtual_machine.memory();
+    Some(
+        (address..address + length)
+            .map(|address| memory.read(address as u16))
+            .collect(),
+    )
+}
+
+fn write_memory(virtual_machine: &mut VirtualMachine, address: usize, bytes: &[u8]) -> bool {
+    if !is_memory_range(address, bytes.len()) {
+        return false;
+    }
+
+    let memory = virtual_machine.memory_mut();
+    for (offset, byte) in bytes.iter().enumerate() {
+        memory.write((address + offset) as u16, *byte);
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests;
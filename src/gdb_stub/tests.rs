@@ -0,0 +1,219 @@
+use super::*;
+use crate::frontend::{FrontendEvent, HeadlessFrontend, Hotkey, RunLoop};
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const INSTRUCTIONS_PER_FRAME: u16 = 10;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// LD VA, 0x42 ; ADD VA, 1 ; JP 0x202
+const PROGRAM: [u8; 6] = [0x6A, 0x42, 0x7A, 0x01, 0x12, 0x02];
+
+// A client driving the stub over TCP, the way GDB does.
+struct Session {
+    stub: GdbStub,
+    client: TcpStream,
+    virtual_machine: VirtualMachine,
+    received: Vec<u8>,
+}
+
+impl Session {
+    fn new() -> Session {
+        let stub = GdbStub::bind("127.0.0.1:0").unwrap();
+        let address: SocketAddr = match &stub.listener {
+            Listener::Tcp(listener) => listener.local_addr().unwrap(),
+            #[cfg(unix)]
+            Listener::Unix(..) => unreachable!(),
+        };
+        let client = TcpStream::connect(address).unwrap();
+        client.set_nonblocking(true).unwrap();
+
+        let mut virtual_machine = VirtualMachine::new();
+        virtual_machine.load_rom_bytes(&PROGRAM).unwrap();
+
+        let mut session = Session {
+            stub,
+            client,
+            virtual_machine,
+            received: Vec::new(),
+        };
+        session.wait_until(|session| session.stub.is_connected());
+        session
+    }
+
+    fn poll(&mut self) {
+        self.stub
+            .poll(&mut self.virtual_machine, INSTRUCTIONS_PER_FRAME);
+
+        let mut buffer = [0; 1024];
+        match self.client.read(&mut buffer) {
+            Ok(length) => self.received.extend_from_slice(&buffer[..length]),
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    fn wait_until(&mut self, condition: impl Fn(&Session) -> bool) {
+        let start = Instant::now();
+        while !condition(self) {
+            assert!(start.elapsed() < TIMEOUT, "no answer from the stub");
+            self.poll();
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn send_raw(&mut self, bytes: &[u8]) {
+        self.client.write_all(bytes).unwrap();
+    }
+
+    fn send(&mut self, packet: &str) {
+        let bytes = format!("${}#{:02x}", packet, checksum(packet.as_bytes()));
+        self.send_raw(bytes.as_bytes());
+    }
+
+    // Takes the '+' acknowledging the last packet sent.
+    fn expect_ack(&mut self) {
+        self.wait_until(|session| !session.received.is_empty());
+        assert_eq!(self.received.remove(0), b'+');
+    }
+
+    // Takes the next packet the stub sent, checking its checksum.
+    fn receive(&mut self) -> String {
+        self.wait_until(|session| {
+            session
+                .received
+                .iter()
+                .position(|byte| *byte == b'#')
+                .is_some_and(|end| session.received.len() >= end + 3)
+        });
+
+        assert_eq!(self.received[0], b'$');
+        let end = self.received.iter().position(|byte| *byte == b'#').unwrap();
+        let data = self.received[1..end].to_vec();
+        let sent_checksum = std::str::from_utf8(&self.received[end + 1..end + 3]).unwrap();
+        assert_eq!(
+            u8::from_str_radix(sent_checksum, 16).unwrap(),
+            checksum(&data)
+        );
+        self.received.drain(..end + 3);
+        self.send_raw(b"+");
+
+        String::from_utf8(data).unwrap()
+    }
+
+    fn request(&mut self, packet: &str) -> String {
+        self.send(packet);
+        self.expect_ack();
+        self.receive()
+    }
+
+    fn program_counter(&self) -> u16 {
+        self.virtual_machine.virtual_processor().program_counter()
+    }
+}
+
+#[test]
+fn halts_when_a_client_connects() {
+    let session = Session::new();
+    assert!(session.stub.is_halted());
+    assert_eq!(session.program_counter(), 0x200);
+}
+
+#[test]
+fn reads_registers_and_memory() {
+    let mut session = Session::new();
+    assert_eq!(session.request("?"), "S05");
+
+    // V0-VF, then I and PC in little endian, SP, DT and ST.
+    let registers = session.request("g");
+    assert_eq!(registers.len(), 2 * 23);
+    assert_eq!(&registers[32..36], "0000");
+    assert_eq!(&registers[36..40], "0002");
+
+    assert_eq!(session.request("m200,6"), "6a427a011202");
+    assert_eq!(session.request("M300,2:abcd"), "OK");
+    assert_eq!(session.request("m300,2"), "abcd");
+    assert_eq!(session.virtual_machine.memory().read(0x301), 0xCD);
+    assert_eq!(session.request("m1000,2"), "E01");
+}
+
+#[test]
+fn steps_and_continues_to_a_breakpoint() {
+    let mut session = Session::new();
+    assert_eq!(session.request("Z0,204,2"), "OK");
+
+    assert_eq!(session.request("s"), "S05");
+    assert_eq!(session.program_counter(), 0x202);
+    assert_eq!(
+        session.virtual_machine.virtual_processor().registers()[0xA],
+        0x42
+    );
+
+    // The stop reply only comes once the machine reached the breakpoint.
+    session.send("c");
+    session.expect_ack();
+    assert!(!session.stub.is_halted());
+    session
+        .stub
+        .run_frame(&mut session.virtual_machine, INSTRUCTIONS_PER_FRAME);
+    assert_eq!(session.receive(), "S05");
+    assert!(session.stub.is_halted());
+    assert_eq!(session.program_counter(), 0x204);
+
+    // Without the breakpoint, a whole frame runs.
+    assert_eq!(session.request("z0,204,2"), "OK");
+    session.send("c");
+    session.expect_ack();
+    session
+        .stub
+        .run_frame(&mut session.virtual_machine, INSTRUCTIONS_PER_FRAME);
+    assert!(!session.stub.is_halted());
+    assert!(session.received.is_empty());
+}
+
+#[test]
+fn frontend_breakpoints_hold_while_attached() {
+    let mut session = Session::new();
+    assert_eq!(session.request("Z0,204,2"), "OK");
+    session.send("c");
+    session.expect_ack();
+
+    let Session {
+        stub,
+        client: _client,
+        mut virtual_machine,
+        ..
+    } = session;
+    let mut run_loop = RunLoop::new();
+    run_loop.set_instructions_per_frame(INSTRUCTIONS_PER_FRAME);
+    run_loop.set_gdb_stub(Some(stub));
+    run_loop.set_breakpoint(0x202, true);
+    let mut frontend = HeadlessFrontend::new(100);
+
+    // The frontend breakpoint pauses the run loop, the debugger keeps waiting.
+    assert!(run_loop.step(&mut virtual_machine, &mut frontend));
+    assert!(run_loop.is_paused());
+    assert_eq!(virtual_machine.virtual_processor().program_counter(), 0x202);
+    assert_eq!(frontend.messages(), ["Breakpoint at 0x202"]);
+
+    // Resuming goes on to the breakpoint of the debugger.
+    frontend.push_event(FrontendEvent::Hotkey(Hotkey::Resume));
+    assert!(run_loop.step(&mut virtual_machine, &mut frontend));
+    assert!(run_loop.step(&mut virtual_machine, &mut frontend));
+    assert_eq!(virtual_machine.virtual_processor().program_counter(), 0x204);
+}
+
+#[test]
+fn bad_checksums_are_refused() {
+    let mut session = Session::new();
+    session.send_raw(b"$?#00");
+    session.wait_until(|session| !session.received.is_empty());
+    assert_eq!(session.received, b"-");
+    session.received.clear();
+
+    // A '-' from the client asks for the last packet again.
+    assert_eq!(session.request("?"), "S05");
+    session.send_raw(b"-");
+    assert_eq!(session.receive(), "S05");
+}
@@ -3,6 +3,7 @@ pub const CHIP8_SCREEN_HEIGHT: u8 = 32;
 pub const SCALE_FACTOR: u8 = 10;
 pub const WINDOW_WIDTH: u32 = CHIP8_SCREEN_WIDTH as u32 * SCALE_FACTOR as u32;
 pub const WINDOW_HEIGHT: u32 = CHIP8_SCREEN_HEIGHT as u32 * SCALE_FACTOR as u32;
-pub const CPU_CLOCK_DELAY: u16 = 1000; // 1000 microseconds, 1ms.
-pub const TIMER_DIVISION_CLOCK: u8 = 9;
 pub const PROCESSOR_INTERNAL_PROGRAM_COUNTER_START: u16 = 0x200; // Processor's PC starts 0x200(512).
+pub const FRAME_DURATION_MICROS: u64 = 16_667; // Timers tick at 60 Hz.
+pub const INSTRUCTIONS_PER_FRAME: u16 = 16; // ~1000 instructions per second at 60 frames per second.
+pub const DEFAULT_RANDOM_SEED: u64 = 0x4D43_4838; // "MCH8".
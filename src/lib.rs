@@ -1,6 +1,40 @@
+#[cfg(feature = "sdl")]
 pub mod audio_system;
+pub mod bitmap_font;
+pub mod cheats;
+pub mod config;
+pub mod coverage;
+pub mod crt_filter;
+pub mod debug_view;
+pub mod disassembler;
+pub mod fault;
+pub mod flicker_filter;
+pub mod fontset;
+pub mod framebuffer;
+pub mod frontend;
+pub mod gdb_stub;
 pub mod globals;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+pub mod logger;
 pub mod memory;
+pub mod observer;
+pub mod osd;
+pub mod palette;
+pub mod pause_menu;
+pub mod profiler;
+pub mod quirks;
 pub mod render_table;
+pub mod rom_browser;
+pub mod rom_database;
+pub mod save_state;
+#[cfg(feature = "sdl")]
+pub mod sdl_frontend;
+pub mod tracer;
+#[cfg(feature = "tui")]
+pub mod tui_frontend;
+pub mod viewport;
 pub mod virtual_machine;
 pub mod virtual_processor;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
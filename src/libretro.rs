@@ -0,0 +1,587 @@
+// libretro core entry points, so mch8 can be loaded by RetroArch and other
+// libretro frontends. Only the parts of the API mch8 needs are declared here.
+use crate::flicker_filter::{FlickerFilter, FlickerMode, DEFAULT_FLICKER_DECAY};
+use crate::frontend::KEYPAD_LAYOUT;
+use crate::globals::*;
+use crate::palette::Palette;
+use crate::quirks::Quirks;
+use crate::virtual_machine::VirtualMachine;
+use std::ffi::{c_char, c_uint, c_void, CStr};
+use std::sync::Mutex;
+
+const RETRO_API_VERSION: c_uint = 1;
+const RETRO_REGION_NTSC: c_uint = 0;
+
+const RETRO_DEVICE_JOYPAD: c_uint = 1;
+const RETRO_DEVICE_KEYBOARD: c_uint = 3;
+
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS: c_uint = 11;
+const RETRO_ENVIRONMENT_GET_VARIABLE: c_uint = 15;
+const RETRO_ENVIRONMENT_SET_VARIABLES: c_uint = 16;
+const RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE: c_uint = 17;
+const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+
+const FRAMES_PER_SECOND: f64 = 60.0;
+const AUDIO_SAMPLE_RATE: u32 = 44100;
+const AUDIO_FRAMES_PER_VIDEO_FRAME: usize = AUDIO_SAMPLE_RATE as usize / 60;
+const BUZZER_FREQUENCY: u32 = 440;
+const BUZZER_AMPLITUDE: i16 = 0x1000;
+
+// Every save state is padded to this size since frontends expect it to be constant
+// (rewind, netplay). The header (5 bytes), the processor (24 bytes and 2 per
+// return address), the RAM (4096) and the screen (2048) leave room for 1009
+// return addresses, deeper stacks cannot be saved.
+const SAVE_STATE_SIZE: usize = 0x2000;
+
+// RetroPad button ids in libretro order (B, Y, SELECT, START, UP, DOWN, LEFT, RIGHT,
+// A, X, L, R, L2, R2, L3, R3) mapped to the keypad. The d-pad uses 5/8/7/9 and the
+// face buttons 6/4 as most games do.
+const JOYPAD_LAYOUT: [u8; 16] = [
+    0x4, 0x1, 0xA, 0xB, 0x5, 0x8, 0x7, 0x9, 0x6, 0x2, 0x3, 0xC, 0xD, 0xE, 0x0, 0xF,
+];
+const JOYPAD_DESCRIPTIONS: [&CStr; 16] = [
+    c"Key 4", c"Key 1", c"Key A", c"Key B", c"Key 5", c"Key 8", c"Key 7", c"Key 9", c"Key 6",
+    c"Key 2", c"Key 3", c"Key C", c"Key D", c"Key E", c"Key 0", c"Key F",
+];
+
+const OPTION_CLOCK_SPEED: &CStr = c"mch8_clock_speed";
+const OPTION_PALETTE: &CStr = c"mch8_palette";
+const OPTION_FLICKER: &CStr = c"mch8_flicker";
+const OPTION_SHIFT_QUIRK: &CStr = c"mch8_quirk_shift";
+const OPTION_LOAD_STORE_QUIRK: &CStr = c"mch8_quirk_load_store";
+const OPTION_JUMP_QUIRK: &CStr = c"mch8_quirk_jump";
+const OPTION_VF_RESET_QUIRK: &CStr = c"mch8_quirk_vf_reset";
+
+#[repr(C)]
+pub struct RetroSystemInfo {
+    library_name: *const c_char,
+    library_version: *const c_char,
+    valid_extensions: *const c_char,
+    need_fullpath: bool,
+    block_extract: bool,
+}
+
+#[repr(C)]
+pub struct RetroGameGeometry {
+    base_width: c_uint,
+    base_height: c_uint,
+    max_width: c_uint,
+    max_height: c_uint,
+    aspect_ratio: f32,
+}
+
+#[repr(C)]
+pub struct RetroSystemTiming {
+    fps: f64,
+    sample_rate: f64,
+}
+
+#[repr(C)]
+pub struct RetroSystemAvInfo {
+    geometry: RetroGameGeometry,
+    timing: RetroSystemTiming,
+}
+
+#[repr(C)]
+pub struct RetroGameInfo {
+    path: *const c_char,
+    data: *const c_void,
+    size: usize,
+    meta: *const c_char,
+}
+
+#[repr(C)]
+struct RetroVariable {
+    key: *const c_char,
+    value: *const c_char,
+}
+
+#[repr(C)]
+struct RetroInputDescriptor {
+    port: c_uint,
+    device: c_uint,
+    index: c_uint,
+    id: c_uint,
+    description: *const c_char,
+}
+
+pub type RetroEnvironmentFn = unsafe extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+pub type RetroVideoRefreshFn =
+    unsafe extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+pub type RetroAudioSampleFn = unsafe extern "C" fn(left: i16, right: i16);
+pub type RetroAudioSampleBatchFn = unsafe extern "C" fn(data: *const i16, frames: usize) -> usize;
+pub type RetroInputPollFn = unsafe extern "C" fn();
+pub type RetroInputStateFn =
+    unsafe extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+struct Callbacks {
+    environment: Option<RetroEnvironmentFn>,
+    video_refresh: Option<RetroVideoRefreshFn>,
+    audio_sample_batch: Option<RetroAudioSampleBatchFn>,
+    input_poll: Option<RetroInputPollFn>,
+    input_state: Option<RetroInputStateFn>,
+}
+
+struct Core {
+    virtual_machine: VirtualMachine,
+    instructions_per_frame: u16,
+    palette: Palette,
+    flicker_filter: FlickerFilter,
+    framebuffer: [u32; CHIP8_SCREEN_WIDTH as usize * CHIP8_SCREEN_HEIGHT as usize],
+    audio_buffer: [i16; AUDIO_FRAMES_PER_VIDEO_FRAME * 2],
+    audio_phase: u32,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn environment(cmd: c_uint, data: *mut c_void) -> bool {
+    let environment = CALLBACKS.lock().unwrap().environment;
+
+    match environment {
+        Some(environment) => unsafe { environment(cmd, data) },
+        None => false,
+    }
+}
+
+fn get_variable(key: &CStr) -> Option<String> {
+    let mut variable = RetroVariable {
+        key: key.as_ptr(),
+        value: std::ptr::null(),
+    };
+
+    let is_set = environment(
+        RETRO_ENVIRONMENT_GET_VARIABLE,
+        &mut variable as *mut RetroVariable as *mut c_void,
+    );
+    if !is_set || variable.value.is_null() {
+        return None;
+    }
+
+    let value = unsafe { CStr::from_ptr(variable.value) };
+    Some(value.to_string_lossy().into_owned())
+}
+
+fn is_variable_enabled(key: &CStr) -> bool {
+    get_variable(key).is_some_and(|value| value == "enabled")
+}
+
+impl Core {
+    fn new() -> Core {
+        Core {
+            virtual_machine: VirtualMachine::new(),
+            instructions_per_frame: INSTRUCTIONS_PER_FRAME,
+            palette: Palette::default(),
+            flicker_filter: FlickerFilter::new(FlickerMode::Off, DEFAULT_FLICKER_DECAY),
+            framebuffer: [0; CHIP8_SCREEN_WIDTH as usize * CHIP8_SCREEN_HEIGHT as usize],
+            audio_buffer: [0; AUDIO_FRAMES_PER_VIDEO_FRAME * 2],
+            audio_phase: 0,
+        }
+    }
+
+    fn apply_options(&mut self) {
+        if let Some(clock_speed) = get_variable(OPTION_CLOCK_SPEED) {
+            if let Ok(instructions_per_frame) = clock_speed.parse() {
+                self.instructions_per_frame = instructions_per_frame;
+            }
+        }
+
+        if let Some(palette_name) = get_variable(OPTION_PALETTE) {
+            if let Some(palette) = Palette::by_name(&palette_name) {
+                self.palette = palette;
+            }
+        }
+
+        if let Some(flicker_mode) =
+            get_variable(OPTION_FLICKER).and_then(|name| FlickerMode::from_name(&name))
+        {
+            self.flicker_filter = FlickerFilter::new(flicker_mode, DEFAULT_FLICKER_DECAY);
+        }
+
+        self.virtual_machine.set_quirks(Quirks {
+            shift_uses_vy: is_variable_enabled(OPTION_SHIFT_QUIRK),
+            load_store_increments_index: is_variable_enabled(OPTION_LOAD_STORE_QUIRK),
+            jump_uses_vx: is_variable_enabled(OPTION_JUMP_QUIRK),
+            logic_resets_vf: is_variable_enabled(OPTION_VF_RESET_QUIRK),
+        });
+    }
+
+    fn poll_keys(&mut self, input_state: RetroInputStateFn) {
+        let mut keys = [false; 16];
+
+        for (button, key) in JOYPAD_LAYOUT.iter().enumerate() {
+            if unsafe { input_state(0, RETRO_DEVICE_JOYPAD, 0, button as c_uint) } != 0 {
+                keys[*key as usize] = true;
+            }
+        }
+        // libretro keycodes match lowercase ASCII for letters and digits.
+        for (c, key) in KEYPAD_LAYOUT.iter() {
+            if unsafe { input_state(0, RETRO_DEVICE_KEYBOARD, 0, *c as c_uint) } != 0 {
+                keys[*key as usize] = true;
+            }
+        }
+
+        for (key, is_down) in keys.iter().enumerate() {
+            self.virtual_machine.set_key(key as u8, *is_down);
+        }
+    }
+
+    fn render(&mut self) {
+        self.flicker_filter.update(&self.virtual_machine);
+
+        for y in 0..CHIP8_SCREEN_HEIGHT {
+            for x in 0..CHIP8_SCREEN_WIDTH {
+                let color = self.palette.background().blend(
+                    self.palette.foreground(),
+                    self.flicker_filter.intensity(x, y),
+                );
+                self.framebuffer[y as usize * CHIP8_SCREEN_WIDTH as usize + x as usize] =
+                    color.to_u32();
+            }
+        }
+    }
+
+    // Square wave while the sound timer is running, silence otherwise.
+    fn fill_audio_buffer(&mut self) {
+        let is_sound_active = self.virtual_machine.is_sound_active();
+        let half_period = AUDIO_SAMPLE_RATE / BUZZER_FREQUENCY / 2;
+
+        for frame in self.audio_buffer.chunks_exact_mut(2) {
+            let sample = if !is_sound_active {
+                0
+            } else if (self.audio_phase / half_period).is_multiple_of(2) {
+                BUZZER_AMPLITUDE
+            } else {
+                -BUZZER_AMPLITUDE
+            };
+
+            frame[0] = sample;
+            frame[1] = sample;
+            self.audio_phase = self.audio_phase.wrapping_add(1);
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+/// # Safety
+///
+/// `environment` must be a valid libretro environment callback.
+#[no_mangle]
+pub unsafe extern "C" fn retro_set_environment(environment: RetroEnvironmentFn) {
+    CALLBACKS.lock().unwrap().environment = Some(environment);
+
+    let variables = [
+        RetroVariable {
+            key: OPTION_CLOCK_SPEED.as_ptr(),
+            value: c"Instructions per frame; 16|8|10|12|20|30|50|100|200|500".as_ptr(),
+        },
+        RetroVariable {
+            key: OPTION_PALETTE.as_ptr(),
+            value:
+                c"Palette; classic|amber|green-phosphor|lcd|gameboy|high-contrast|octo|cga|silicon8"
+                    .as_ptr(),
+        },
+        RetroVariable {
+            key: OPTION_FLICKER.as_ptr(),
+            value: c"Flicker reduction; off|blend|or-last-two|persistence".as_ptr(),
+        },
+        RetroVariable {
+            key: OPTION_SHIFT_QUIRK.as_ptr(),
+            value: c"Quirk : shifts use VY; disabled|enabled".as_ptr(),
+        },
+        RetroVariable {
+            key: OPTION_LOAD_STORE_QUIRK.as_ptr(),
+            value: c"Quirk : FX55/FX65 increment I; disabled|enabled".as_ptr(),
+        },
+        RetroVariable {
+            key: OPTION_JUMP_QUIRK.as_ptr(),
+            value: c"Quirk : BNNN jumps with VX; disabled|enabled".as_ptr(),
+        },
+        RetroVariable {
+            key: OPTION_VF_RESET_QUIRK.as_ptr(),
+            value: c"Quirk : logic operations reset VF; disabled|enabled".as_ptr(),
+        },
+        RetroVariable {
+            key: std::ptr::null(),
+            value: std::ptr::null(),
+        },
+    ];
+
+    environment(
+        RETRO_ENVIRONMENT_SET_VARIABLES,
+        variables.as_ptr() as *mut c_void,
+    );
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(video_refresh: RetroVideoRefreshFn) {
+    CALLBACKS.lock().unwrap().video_refresh = Some(video_refresh);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_audio_sample: RetroAudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(audio_sample_batch: RetroAudioSampleBatchFn) {
+    CALLBACKS.lock().unwrap().audio_sample_batch = Some(audio_sample_batch);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(input_poll: RetroInputPollFn) {
+    CALLBACKS.lock().unwrap().input_poll = Some(input_poll);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(input_state: RetroInputStateFn) {
+    CALLBACKS.lock().unwrap().input_state = Some(input_state);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {
+    *CORE.lock().unwrap() = Some(Core::new());
+}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap() = None;
+}
+
+/// # Safety
+///
+/// `info` must point to a writable `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: c"mch8".as_ptr(),
+        library_version: c"0.1.0".as_ptr(),
+        valid_extensions: c"ch8|rom".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+///
+/// `info` must point to a writable `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: CHIP8_SCREEN_WIDTH as c_uint,
+            base_height: CHIP8_SCREEN_HEIGHT as c_uint,
+            max_width: CHIP8_SCREEN_WIDTH as c_uint,
+            max_height: CHIP8_SCREEN_HEIGHT as c_uint,
+            aspect_ratio: CHIP8_SCREEN_WIDTH as f32 / CHIP8_SCREEN_HEIGHT as f32,
+        },
+        timing: RetroSystemTiming {
+            fps: FRAMES_PER_SECOND,
+            sample_rate: AUDIO_SAMPLE_RATE as f64,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+/// # Safety
+///
+/// `game` must be null or point to a valid `retro_game_info` whose `data` holds `size` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    if game.is_null() || (*game).data.is_null() {
+        return false;
+    }
+
+    let mut pixel_format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !environment(
+        RETRO_ENVIRONMENT_SET_PIXEL_FORMAT,
+        &mut pixel_format as *mut c_uint as *mut c_void,
+    ) {
+        return false;
+    }
+
+    let mut descriptors: Vec<RetroInputDescriptor> = JOYPAD_DESCRIPTIONS
+        .iter()
+        .enumerate()
+        .map(|(button, description)| RetroInputDescriptor {
+            port: 0,
+            device: RETRO_DEVICE_JOYPAD,
+            index: 0,
+            id: button as c_uint,
+            description: description.as_ptr(),
+        })
+        .collect();
+    descriptors.push(RetroInputDescriptor {
+        port: 0,
+        device: 0,
+        index: 0,
+        id: 0,
+        description: std::ptr::null(),
+    });
+    environment(
+        RETRO_ENVIRONMENT_SET_INPUT_DESCRIPTORS,
+        descriptors.as_mut_ptr() as *mut c_void,
+    );
+
+    let rom = std::slice::from_raw_parts((*game).data as *const u8, (*game).size);
+
+    let mut core = CORE.lock().unwrap();
+    let core = core.get_or_insert_with(Core::new);
+    core.apply_options();
+
+    core.virtual_machine.load_rom_bytes(rom).is_ok()
+}
+
+/// # Safety
+///
+/// Special game types are not supported, this never dereferences its arguments.
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    if let Some(core) = CORE.lock().unwrap().as_mut() {
+        core.virtual_machine = VirtualMachine::new();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = CORE.lock().unwrap().as_mut() {
+        core.virtual_machine.reset();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let (video_refresh, audio_sample_batch, input_poll, input_state) = {
+        let callbacks = CALLBACKS.lock().unwrap();
+        (
+            callbacks.video_refresh,
+            callbacks.audio_sample_batch,
+            callbacks.input_poll,
+            callbacks.input_state,
+        )
+    };
+
+    let mut options_updated = false;
+    environment(
+        RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+        &mut options_updated as *mut bool as *mut c_void,
+    );
+
+    let mut core = CORE.lock().unwrap();
+    let Some(core) = core.as_mut() else {
+        return;
+    };
+
+    if options_updated {
+        core.apply_options();
+    }
+
+    if let (Some(input_poll), Some(input_state)) = (input_poll, input_state) {
+        unsafe { input_poll() };
+        core.poll_keys(input_state);
+    }
+
+    core.virtual_machine.run_frame(core.instructions_per_frame);
+
+    core.render();
+    if let Some(video_refresh) = video_refresh {
+        unsafe {
+            video_refresh(
+                core.framebuffer.as_ptr() as *const c_void,
+                CHIP8_SCREEN_WIDTH as c_uint,
+                CHIP8_SCREEN_HEIGHT as c_uint,
+                CHIP8_SCREEN_WIDTH as usize * std::mem::size_of::<u32>(),
+            )
+        };
+    }
+
+    core.fill_audio_buffer();
+    if let Some(audio_sample_batch) = audio_sample_batch {
+        unsafe { audio_sample_batch(core.audio_buffer.as_ptr(), AUDIO_FRAMES_PER_VIDEO_FRAME) };
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    SAVE_STATE_SIZE
+}
+
+/// # Safety
+///
+/// `data` must point to `size` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    let core = CORE.lock().unwrap();
+    let Some(core) = core.as_ref() else {
+        return false;
+    };
+
+    let state = core.virtual_machine.save_state();
+    if state.len() > size {
+        return false;
+    }
+
+    let output = std::slice::from_raw_parts_mut(data as *mut u8, size);
+    output[..state.len()].copy_from_slice(&state);
+    output[state.len()..].fill(0);
+
+    true
+}
+
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    let mut core = CORE.lock().unwrap();
+    let Some(core) = core.as_mut() else {
+        return false;
+    };
+
+    let state = std::slice::from_raw_parts(data as *const u8, size);
+    core.virtual_machine.load_state(state).is_ok()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+/// # Safety
+///
+/// Cheats are not supported, this never dereferences `code`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// Messages above the current level are dropped. Everything goes to stderr so
+// stdout stays free for the programs piping mch8's output.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warning,
+    #[default]
+    Info,
+    Debug,
+}
+
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+
+impl LogLevel {
+    pub fn from_name(name: &str) -> Option<LogLevel> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Some(LogLevel::Off),
+            "error" => Some(LogLevel::Error),
+            "warning" | "warn" => Some(LogLevel::Warning),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+
+    fn tag(self) -> &'static str {
+        match self {
+            LogLevel::Off => "",
+            LogLevel::Error => "[Error]",
+            LogLevel::Warning => "[Warning]",
+            LogLevel::Info => "[Info]",
+            LogLevel::Debug => "[Debug]",
+        }
+    }
+}
+
+pub fn set_level(level: LogLevel) {
+    LOG_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> LogLevel {
+    match LOG_LEVEL.load(Ordering::Relaxed) {
+        0 => LogLevel::Off,
+        1 => LogLevel::Error,
+        2 => LogLevel::Warning,
+        3 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    }
+}
+
+pub fn is_enabled(level: LogLevel) -> bool {
+    level != LogLevel::Off && level as u8 <= LOG_LEVEL.load(Ordering::Relaxed)
+}
+
+// Use the log_error!, log_warning!, log_info! and log_debug! macros instead.
+pub fn log(level: LogLevel, args: fmt::Arguments) {
+    if is_enabled(level) {
+        eprintln!("{} {}", level.tag(), args);
+    }
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => {
+        $crate::logger::log($crate::logger::LogLevel::Error, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_warning {
+    ($($arg:tt)*) => {
+        $crate::logger::log($crate::logger::LogLevel::Warning, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => {
+        $crate::logger::log($crate::logger::LogLevel::Info, format_args!($($arg)*))
+    };
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => {
+        $crate::logger::log($crate::logger::LogLevel::Debug, format_args!($($arg)*))
+    };
+}
@@ -1,7 +1,100 @@
-const TOTAL_MEMORY_BYTES: u16 = 0x1000; // 4096 bytes of RAM.
+use crate::globals::*;
+use crate::log_warning;
+use crate::save_state::{StateReader, StateWriter};
+use std::fmt;
+
+pub const TOTAL_MEMORY_BYTES: u16 = 0x1000; // 4096 bytes of RAM.
+
+// What happens when the ROM reads or writes past the 4 KiB, usually through
+// an I pointing past the end.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum MemoryPolicy {
+    #[default]
+    Wrap, // Back to 0x000, as many interpreters do.
+    Fault, // The machine stops on the instruction.
+    Warn,  // Reads give 0 and writes are dropped, with a warning the first time.
+}
+
+impl MemoryPolicy {
+    pub fn from_name(name: &str) -> Option<MemoryPolicy> {
+        match name.to_ascii_lowercase().as_str() {
+            "wrap" => Some(MemoryPolicy::Wrap),
+            "fault" => Some(MemoryPolicy::Fault),
+            "warn" => Some(MemoryPolicy::Warn),
+            _ => None,
+        }
+    }
+}
+
+// Whether the ROM's writes into the interpreter area or into its own code
+// are reported, each address once. They are bugs as often as tricks.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum MemoryProtection {
+    #[default]
+    Off,
+    Warn,  // A warning in the log.
+    Break, // The machine pauses as on a breakpoint.
+}
+
+impl MemoryProtection {
+    pub fn from_name(name: &str) -> Option<MemoryProtection> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Some(MemoryProtection::Off),
+            "warn" => Some(MemoryProtection::Warn),
+            "break" => Some(MemoryProtection::Break),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MemoryRegion {
+    Interpreter, // 0x000-0x1FF, the font lives there.
+    Program,     // Where the ROM was loaded.
+    Free,        // The RAM after the ROM.
+}
+
+impl MemoryRegion {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MemoryRegion::Interpreter => "interpreter",
+            MemoryRegion::Program => "program",
+            MemoryRegion::Free => "free",
+        }
+    }
+}
+
+// A write by the ROM the memory protection reports.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FlaggedWrite {
+    pub program_counter: u16,
+    pub address: u16,
+    pub is_into_code: bool, // Otherwise into the interpreter area.
+}
+
+impl fmt::Display for FlaggedWrite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_into_code {
+            write!(
+                f,
+                "Self-modifying write to {:#05X} at {:#05X}",
+                self.address, self.program_counter
+            )
+        } else {
+            write!(
+                f,
+                "Write to the interpreter area at {:#05X} from {:#05X}",
+                self.address, self.program_counter
+            )
+        }
+    }
+}
 
 pub struct Memory {
     memory: [u8; TOTAL_MEMORY_BYTES as usize],
+    program_end: u16,
+    executed: [bool; TOTAL_MEMORY_BYTES as usize], // Bytes fetched as part of an instruction.
+    flagged: [bool; TOTAL_MEMORY_BYTES as usize],  // Already reported by the protection.
 }
 
 impl Default for Memory {
@@ -14,12 +107,66 @@ impl Memory {
     pub fn new() -> Memory {
         Memory {
             memory: [0x0; TOTAL_MEMORY_BYTES as usize],
+            program_end: PROCESSOR_INTERNAL_PROGRAM_COUNTER_START,
+            executed: [false; TOTAL_MEMORY_BYTES as usize],
+            flagged: [false; TOTAL_MEMORY_BYTES as usize],
+        }
+    }
+
+    pub fn set_program_length(&mut self, length: u16) {
+        self.program_end = PROCESSOR_INTERNAL_PROGRAM_COUNTER_START.saturating_add(length);
+    }
+
+    pub fn region(&self, address: u16) -> MemoryRegion {
+        if address < PROCESSOR_INTERNAL_PROGRAM_COUNTER_START {
+            MemoryRegion::Interpreter
+        } else if address < self.program_end {
+            MemoryRegion::Program
+        } else {
+            MemoryRegion::Free
         }
     }
 
+    // Both bytes of the instruction at `address`.
+    pub fn mark_executed(&mut self, address: u16) {
+        for offset in 0..2 {
+            self.executed[(address.wrapping_add(offset) % TOTAL_MEMORY_BYTES) as usize] = true;
+        }
+    }
+
+    pub fn is_executed(&self, address: u16) -> bool {
+        self.executed[(address % TOTAL_MEMORY_BYTES) as usize]
+    }
+
+    // Writes for the ROM itself, returning the write when the protection
+    // should report it : the first one into the interpreter area or into
+    // code already run at this address.
+    pub fn write_checked(
+        &mut self,
+        addr: u16,
+        data: u8,
+        program_counter: u16,
+    ) -> Option<FlaggedWrite> {
+        self.write(addr, data);
+
+        let index = (addr % TOTAL_MEMORY_BYTES) as usize;
+        let is_into_code = self.executed[index];
+        if self.flagged[index] || !(is_into_code || self.region(addr) == MemoryRegion::Interpreter)
+        {
+            return None;
+        }
+
+        self.flagged[index] = true;
+        Some(FlaggedWrite {
+            program_counter,
+            address: addr,
+            is_into_code,
+        })
+    }
+
     pub fn write(&mut self, addr: u16, data: u8) {
-        if addr > TOTAL_MEMORY_BYTES {
-            println!("[Warning] Memory WRITE command out of range !");
+        if addr >= TOTAL_MEMORY_BYTES {
+            log_warning!("Memory WRITE command out of range !");
             return;
         }
 
@@ -27,11 +174,27 @@ impl Memory {
     }
 
     pub fn read(&self, addr: u16) -> u8 {
-        if addr > TOTAL_MEMORY_BYTES {
-            println!("[Warning] Memory READ command out of range !");
+        if addr >= TOTAL_MEMORY_BYTES {
+            log_warning!("Memory READ command out of range !");
             return 0x0;
         }
 
         self.memory[addr as usize]
     }
+
+    // The whole RAM, to compare machines or dump it.
+    pub fn bytes(&self) -> &[u8] {
+        &self.memory
+    }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.memory);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), &'static str> {
+        self.memory
+            .copy_from_slice(reader.read_bytes(TOTAL_MEMORY_BYTES as usize)?);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,115 @@
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Timer {
+    Delay,
+    Sound,
+}
+
+// What the processor does as it runs the ROM. Addresses are the ones
+// accessed, after the memory policy wrapped them, and `program_counter` is
+// the instruction doing it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MachineEvent {
+    Fetch {
+        address: u16,
+        opcode: u16,
+    },
+    // By DXYN and FX65, fetches are not reported as reads.
+    MemoryRead {
+        program_counter: u16,
+        address: u16,
+        value: u8,
+    },
+    // By FX33 and FX55.
+    MemoryWrite {
+        program_counter: u16,
+        address: u16,
+        value: u8,
+    },
+    Draw {
+        program_counter: u16,
+        x: u8,
+        y: u8,
+        address: u16, // Where the sprite was read, I.
+        height: u8,
+        is_collision: bool,
+    },
+    Clear {
+        program_counter: u16,
+    },
+    // Each time FX0A runs without a key down, so once per instruction waited.
+    KeyWait {
+        program_counter: u16,
+        register: u8,
+    },
+    TimerWrite {
+        program_counter: u16,
+        timer: Timer,
+        value: u8,
+    },
+}
+
+// A tool watching the machine : debugger, coverage, cheats, achievements...
+// Any `FnMut(&MachineEvent)` closure is one.
+pub trait Observer {
+    fn on_event(&mut self, event: &MachineEvent);
+}
+
+impl<F: FnMut(&MachineEvent)> Observer for F {
+    fn on_event(&mut self, event: &MachineEvent) {
+        self(event)
+    }
+}
+
+// The tool keeps its own handle on the observer to read what it gathered.
+pub type SharedObserver = Arc<Mutex<dyn Observer + Send>>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ObserverId(u32);
+
+// The observers subscribed to a processor. Events are only built when there
+// is at least one, so the processor runs as fast without any.
+#[derive(Clone, Default)]
+pub struct Observers {
+    observers: Vec<(ObserverId, SharedObserver)>,
+    next_id: u32,
+}
+
+impl Observers {
+    pub fn new() -> Observers {
+        Observers::default()
+    }
+
+    pub fn subscribe(&mut self, observer: SharedObserver) -> ObserverId {
+        let id = ObserverId(self.next_id);
+        self.next_id += 1;
+        self.observers.push((id, observer));
+        id
+    }
+
+    // Returns false when the observer was not subscribed.
+    pub fn unsubscribe(&mut self, id: ObserverId) -> bool {
+        let count = self.observers.len();
+        self.observers.retain(|(observer_id, _)| *observer_id != id);
+        self.observers.len() != count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    #[inline]
+    pub fn notify(&self, event: impl FnOnce() -> MachineEvent) {
+        if self.observers.is_empty() {
+            return;
+        }
+
+        let event = event();
+        for (_, observer) in &self.observers {
+            // A tool that panicked while holding its lock still gets the events.
+            let mut observer = observer.lock().unwrap_or_else(|error| error.into_inner());
+            observer.on_event(&event);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::bitmap_font::{draw_text, text_height, text_width};
+use crate::framebuffer::Framebuffer;
+use crate::palette::Palette;
+use std::time::{Duration, Instant};
+
+const MESSAGE_DURATION_FRAMES: u32 = 120;
+const MAX_MESSAGES: usize = 4;
+const TEXT_SCALE: u32 = 2;
+const MARGIN: u32 = 8;
+const PADDING: u32 = 4;
+
+// On-screen display drawn over the game : short-lived messages, a prompt that
+// stays until it is cleared, and an optional frames / instructions per second counter.
+pub struct Osd {
+    messages: Vec<(String, u32)>, // Text and frames left before it disappears.
+    prompt: Option<String>,
+    is_counter_visible: bool,
+    counter_text: String,
+    counter_start: Instant,
+    counted_frames: u32,
+    counted_instructions_start: u64,
+}
+
+impl Default for Osd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Text on a box of the background colour so it stays readable over the game.
+fn draw_label(framebuffer: &mut Framebuffer, x: u32, y: u32, text: &str, palette: &Palette) {
+    framebuffer.fill_rect(
+        x.saturating_sub(PADDING),
+        y.saturating_sub(PADDING),
+        text_width(text, TEXT_SCALE) + PADDING * 2,
+        text_height(TEXT_SCALE) + PADDING * 2,
+        palette.background(),
+    );
+    draw_text(framebuffer, x, y, text, palette.foreground(), TEXT_SCALE);
+}
+
+impl Osd {
+    pub fn new() -> Osd {
+        Osd {
+            messages: Vec::new(),
+            prompt: None,
+            is_counter_visible: false,
+            counter_text: String::new(),
+            counter_start: Instant::now(),
+            counted_frames: 0,
+            counted_instructions_start: 0,
+        }
+    }
+
+    pub fn show_message(&mut self, message: &str) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+        self.messages
+            .push((message.to_string(), MESSAGE_DURATION_FRAMES));
+    }
+
+    pub fn set_prompt(&mut self, prompt: Option<String>) {
+        self.prompt = prompt;
+    }
+
+    pub fn is_counter_visible(&self) -> bool {
+        self.is_counter_visible
+    }
+
+    pub fn set_counter_visible(&mut self, is_visible: bool) {
+        self.is_counter_visible = is_visible;
+        self.counter_text.clear();
+        self.counted_frames = 0;
+        self.counter_start = Instant::now();
+    }
+
+    // While something is displayed, the screen has to be repainted every frame.
+    pub fn is_active(&self) -> bool {
+        !self.messages.is_empty() || self.prompt.is_some() || self.is_counter_visible
+    }
+
+    // To be called once per presented frame.
+    pub fn update(&mut self, executed_instructions: u64) {
+        for (_, frames_left) in self.messages.iter_mut() {
+            *frames_left -= 1;
+        }
+        self.messages.retain(|(_, frames_left)| *frames_left > 0);
+
+        if !self.is_counter_visible {
+            return;
+        }
+
+        if self.counted_frames == 0 {
+            self.counted_instructions_start = executed_instructions;
+        }
+        self.counted_frames += 1;
+
+        let elapsed = self.counter_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            let seconds = elapsed.as_secs_f64();
+            let instructions = executed_instructions - self.counted_instructions_start;
+            self.counter_text = format!(
+                "{:.0} FPS {:.0} IPS",
+                self.counted_frames as f64 / seconds,
+                instructions as f64 / seconds
+            );
+            self.counted_frames = 0;
+            self.counter_start = Instant::now();
+        }
+    }
+
+    pub fn draw(&self, framebuffer: &mut Framebuffer, palette: &Palette) {
+        let line_height = text_height(TEXT_SCALE) + PADDING * 3;
+
+        if self.is_counter_visible && !self.counter_text.is_empty() {
+            let x = framebuffer
+                .width()
+                .saturating_sub(text_width(&self.counter_text, TEXT_SCALE) + MARGIN);
+            draw_label(framebuffer, x, MARGIN, &self.counter_text, palette);
+        }
+
+        if let Some(prompt) = &self.prompt {
+            let x = framebuffer
+                .width()
+                .saturating_sub(text_width(prompt, TEXT_SCALE))
+                / 2;
+            let y = framebuffer.height().saturating_sub(text_height(TEXT_SCALE)) / 2;
+            draw_label(framebuffer, x, y, prompt, palette);
+        }
+
+        // Most recent message at the bottom.
+        let mut y = framebuffer
+            .height()
+            .saturating_sub(MARGIN + text_height(TEXT_SCALE));
+        for (message, _) in self.messages.iter().rev() {
+            draw_label(framebuffer, MARGIN, y, message, palette);
+            y = y.saturating_sub(line_height);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    pub const fn from_u32(rgb: u32) -> Rgb {
+        Rgb {
+            r: (rgb >> 16) as u8,
+            g: (rgb >> 8) as u8,
+            b: rgb as u8,
+        }
+    }
+
+    pub fn to_u32(self) -> u32 {
+        (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
+    }
+
+    // Accepts "#RRGGBB" or "RRGGBB", and the short "#RGB" form.
+    pub fn from_hex(hex: &str) -> Result<Rgb, String> {
+        let invalid_colour = || {
+            format!(
+                "[Error] Invalid colour '{}', expected #RRGGBB or #RGB !",
+                hex
+            )
+        };
+
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid_colour());
+        }
+
+        let rgb = u32::from_str_radix(digits, 16).map_err(|_| invalid_colour())?;
+        match digits.len() {
+            6 => Ok(Rgb::from_u32(rgb)),
+            3 => {
+                // Each digit is doubled, #F80 is #FF8800.
+                let digit = |shift: u32| ((rgb >> shift) & 0xF) as u8 * 0x11;
+                Ok(Rgb {
+                    r: digit(8),
+                    g: digit(4),
+                    b: digit(0),
+                })
+            }
+            _ => Err(invalid_colour()),
+        }
+    }
+
+    // Linear mix between two colours, `amount` going from 0 (self) to 255 (other).
+    pub fn blend(self, other: Rgb, amount: u8) -> Rgb {
+        let mix = |a: u8, b: u8| {
+            ((a as u16 * (255 - amount as u16) + b as u16 * amount as u16) / 255) as u8
+        };
+
+        Rgb {
+            r: mix(self.r, other.r),
+            g: mix(self.g, other.g),
+            b: mix(self.b, other.b),
+        }
+    }
+}
+
+// Four colours so XO-CHIP palettes can be described : background, first plane,
+// second plane and both planes. CHIP-8 ROMs only use the first two.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Palette {
+    pub colors: [Rgb; 4],
+}
+
+const NAMED_PALETTES: [(&str, [u32; 4]); 9] = [
+    ("classic", [0x000000, 0xFFFFFF, 0xAAAAAA, 0x555555]),
+    ("amber", [0x1A0F00, 0xFFB000, 0xB37B00, 0x664600]),
+    ("green-phosphor", [0x001A00, 0x33FF33, 0x22AA22, 0x116611]),
+    ("lcd", [0x8F9E76, 0x2B3326, 0x5D6A4E, 0x445040]),
+    ("gameboy", [0x9BBC0F, 0x0F380F, 0x8BAC0F, 0x306230]),
+    ("high-contrast", [0x000000, 0xFFFF00, 0x00FFFF, 0xFFFFFF]),
+    ("octo", [0x996600, 0xFFCC00, 0xFF6600, 0x662200]),
+    ("cga", [0x000000, 0x55FFFF, 0xFF55FF, 0xFFFFFF]),
+    ("silicon8", [0x000000, 0xFFFFFF, 0xFF0000, 0x0000FF]),
+];
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::by_name("classic").unwrap()
+    }
+}
+
+impl Palette {
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        NAMED_PALETTES.iter().map(|(name, _)| *name)
+    }
+
+    pub fn by_name(name: &str) -> Option<Palette> {
+        NAMED_PALETTES
+            .iter()
+            .find(|(palette_name, _)| palette_name.eq_ignore_ascii_case(name))
+            .map(|(_, colors)| Palette {
+                colors: colors.map(Rgb::from_u32),
+            })
+    }
+
+    pub fn background(&self) -> Rgb {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> Rgb {
+        self.colors[1]
+    }
+
+    // Colour of the lines drawn between pixels with PixelStyle::Grid.
+    pub fn grid(&self) -> Rgb {
+        self.background().blend(self.foreground(), 48)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum PixelStyle {
+    #[default]
+    Solid,
+    Grid, // Thin lines between every pixel, like an LCD.
+    Gap,  // Pixels are drawn smaller, leaving the background visible between them.
+}
+
+impl PixelStyle {
+    pub fn from_name(name: &str) -> Option<PixelStyle> {
+        match name.to_ascii_lowercase().as_str() {
+            "solid" => Some(PixelStyle::Solid),
+            "grid" => Some(PixelStyle::Grid),
+            "gap" => Some(PixelStyle::Gap),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
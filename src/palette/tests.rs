@@ -0,0 +1,56 @@
+use super::*;
+
+#[test]
+fn colours_from_hex() {
+    for (hex, expected) in [
+        ("#204020", 0x204020),
+        ("204020", 0x204020),
+        ("#ffb000", 0xFFB000),
+        ("#FFB000", 0xFFB000),
+        ("#F80", 0xFF8800),
+        ("f80", 0xFF8800),
+        ("#000", 0x000000),
+        ("#fff", 0xFFFFFF),
+    ] {
+        assert_eq!(Rgb::from_hex(hex), Ok(Rgb::from_u32(expected)), "{}", hex);
+    }
+}
+
+#[test]
+fn invalid_colours() {
+    for hex in [
+        "", "#", "#F8", "#F800", "#FF88000", "#GG0000", "#+12345", "# 12345", "##F80", "red",
+    ] {
+        assert_eq!(
+            Rgb::from_hex(hex),
+            Err(format!(
+                "[Error] Invalid colour '{}', expected #RRGGBB or #RGB !",
+                hex
+            ))
+        );
+    }
+}
+
+#[test]
+fn blend() {
+    let black = Rgb::from_u32(0x000000);
+    let white = Rgb::from_u32(0xFFFFFF);
+    assert_eq!(black.blend(white, 0), black);
+    assert_eq!(black.blend(white, 255), white);
+    assert_eq!(black.blend(white, 128), Rgb::from_u32(0x808080));
+    assert_eq!(Rgb::from_u32(0x123456).to_u32(), 0x123456);
+}
+
+#[test]
+fn palettes_by_name() {
+    assert_eq!(Palette::names().count(), NAMED_PALETTES.len());
+    for name in Palette::names() {
+        assert!(Palette::by_name(name).is_some(), "{}", name);
+    }
+
+    let gameboy = Palette::by_name("GameBoy").unwrap();
+    assert_eq!(gameboy.background(), Rgb::from_u32(0x9BBC0F));
+    assert_eq!(gameboy.foreground(), Rgb::from_u32(0x0F380F));
+    assert_eq!(Palette::default(), Palette::by_name("classic").unwrap());
+    assert_eq!(Palette::by_name("sepia"), None);
+}
@@ -0,0 +1,332 @@
+use crate::bitmap_font::{draw_text, text_height, text_width};
+use crate::cheats::Cheats;
+use crate::framebuffer::Framebuffer;
+use crate::globals::*;
+use crate::palette::Palette;
+use crate::quirks::Quirks;
+
+const TEXT_SCALE: u32 = 2;
+const LINE_SPACING: u32 = 4;
+const PADDING: u32 = 8;
+const SAVE_STATE_SLOTS: u8 = 9;
+const SPEEDS: [u16; 12] = [1, 2, 4, 8, 12, 16, 20, 30, 50, 100, 200, 500];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+}
+
+// What the frontend has to do after an input, the menu itself only keeps track
+// of the values it displays.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuAction {
+    Resume,
+    Reset,
+    SaveState(u8),
+    LoadState(u8),
+    LoadRom,
+    SetInstructionsPerFrame(u16),
+    SetPalette(Palette),
+    SetQuirks(Quirks),
+    ToggleCheat(usize),
+    RemapKeys,
+    Quit,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MenuItem {
+    Resume,
+    Reset,
+    SaveState,
+    LoadState,
+    LoadRom,
+    Speed,
+    Palette,
+    Quirk(usize),
+    Cheat,
+    RemapKeys,
+    Quit,
+}
+
+const MENU_ITEMS: [MenuItem; 14] = [
+    MenuItem::Resume,
+    MenuItem::Reset,
+    MenuItem::SaveState,
+    MenuItem::LoadState,
+    MenuItem::LoadRom,
+    MenuItem::Speed,
+    MenuItem::Palette,
+    MenuItem::Quirk(0),
+    MenuItem::Quirk(1),
+    MenuItem::Quirk(2),
+    MenuItem::Quirk(3),
+    MenuItem::Cheat,
+    MenuItem::RemapKeys,
+    MenuItem::Quit,
+];
+
+const QUIRK_NAMES: [&str; 4] = [
+    "Shift uses VY",
+    "Load/store moves I",
+    "Jump uses VX",
+    "Logic resets VF",
+];
+
+// The closest preset, the speed may have been set to another value.
+fn speed_index(instructions_per_frame: u16) -> usize {
+    (0..SPEEDS.len())
+        .min_by_key(|index| SPEEDS[*index].abs_diff(instructions_per_frame))
+        .unwrap_or(0)
+}
+
+fn quirk_mut(quirks: &mut Quirks, index: usize) -> &mut bool {
+    match index {
+        0 => &mut quirks.shift_uses_vy,
+        1 => &mut quirks.load_store_increments_index,
+        2 => &mut quirks.jump_uses_vx,
+        _ => &mut quirks.logic_resets_vf,
+    }
+}
+
+pub struct PauseMenu {
+    selected_item: usize,
+    save_state_slot: u8,
+    speed_index: usize,
+    palette_names: Vec<&'static str>,
+    palette_index: usize,
+    quirks: Quirks,
+    cheats: Vec<(String, bool)>, // Name and whether it is enabled.
+    cheat_index: usize,
+}
+
+impl Default for PauseMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PauseMenu {
+    pub fn new() -> PauseMenu {
+        PauseMenu {
+            selected_item: 0,
+            save_state_slot: 1,
+            speed_index: speed_index(INSTRUCTIONS_PER_FRAME),
+            palette_names: Palette::names().collect(),
+            palette_index: 0,
+            quirks: Quirks::default(),
+            cheats: Vec::new(),
+            cheat_index: 0,
+        }
+    }
+
+    // Shows the palette the frontend started with, when it is one of the named ones.
+    pub fn select_palette(&mut self, palette: &Palette) {
+        if let Some(index) = self
+            .palette_names
+            .iter()
+            .position(|name| Palette::by_name(name).as_ref() == Some(palette))
+        {
+            self.palette_index = index;
+        }
+    }
+
+    // Left and right go through the cheats of the ROM, select toggles the one shown.
+    pub fn set_cheats(&mut self, cheats: &Cheats) {
+        self.cheats = cheats
+            .iter()
+            .map(|cheat| (cheat.name.clone(), cheat.is_enabled))
+            .collect();
+        if self.cheat_index >= self.cheats.len() {
+            self.cheat_index = 0;
+        }
+    }
+
+    // The quirks and the speed shown are the ones of the machine, which may
+    // have been changed by the configuration of the ROM.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn set_instructions_per_frame(&mut self, instructions_per_frame: u16) {
+        self.speed_index = speed_index(instructions_per_frame);
+    }
+
+    pub fn save_state_slot(&self) -> u8 {
+        self.save_state_slot
+    }
+
+    // Goes back to the first item, to be called when the menu is opened.
+    pub fn open(&mut self) {
+        self.selected_item = 0;
+    }
+
+    pub fn handle_input(&mut self, input: MenuInput) -> Option<MenuAction> {
+        let item = MENU_ITEMS[self.selected_item];
+
+        match input {
+            MenuInput::Up => {
+                self.selected_item = (self.selected_item + MENU_ITEMS.len() - 1) % MENU_ITEMS.len();
+                None
+            }
+            MenuInput::Down => {
+                self.selected_item = (self.selected_item + 1) % MENU_ITEMS.len();
+                None
+            }
+            MenuInput::Left | MenuInput::Right => {
+                let is_right = input == MenuInput::Right;
+                self.change_value(item, is_right)
+            }
+            MenuInput::Select => match item {
+                MenuItem::Resume => Some(MenuAction::Resume),
+                MenuItem::Reset => Some(MenuAction::Reset),
+                MenuItem::SaveState => Some(MenuAction::SaveState(self.save_state_slot)),
+                MenuItem::LoadState => Some(MenuAction::LoadState(self.save_state_slot)),
+                MenuItem::LoadRom => Some(MenuAction::LoadRom),
+                MenuItem::Cheat if !self.cheats.is_empty() => {
+                    Some(MenuAction::ToggleCheat(self.cheat_index))
+                }
+                MenuItem::Cheat => None,
+                MenuItem::RemapKeys => Some(MenuAction::RemapKeys),
+                MenuItem::Quit => Some(MenuAction::Quit),
+                MenuItem::Speed | MenuItem::Palette | MenuItem::Quirk(_) => {
+                    self.change_value(item, true)
+                }
+            },
+        }
+    }
+
+    fn change_value(&mut self, item: MenuItem, is_increase: bool) -> Option<MenuAction> {
+        let step = |value: usize, count: usize| {
+            if is_increase {
+                (value + 1) % count
+            } else {
+                (value + count - 1) % count
+            }
+        };
+
+        match item {
+            MenuItem::SaveState | MenuItem::LoadState => {
+                self.save_state_slot =
+                    step(self.save_state_slot as usize - 1, SAVE_STATE_SLOTS as usize) as u8 + 1;
+                None
+            }
+            MenuItem::Speed => {
+                self.speed_index = step(self.speed_index, SPEEDS.len());
+                Some(MenuAction::SetInstructionsPerFrame(
+                    SPEEDS[self.speed_index],
+                ))
+            }
+            MenuItem::Palette => {
+                self.palette_index = step(self.palette_index, self.palette_names.len());
+                Palette::by_name(self.palette_names[self.palette_index]).map(MenuAction::SetPalette)
+            }
+            MenuItem::Quirk(index) => {
+                let quirk = quirk_mut(&mut self.quirks, index);
+                *quirk = !*quirk;
+                Some(MenuAction::SetQuirks(self.quirks))
+            }
+            MenuItem::Cheat if !self.cheats.is_empty() => {
+                self.cheat_index = step(self.cheat_index, self.cheats.len());
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn item_label(&self, item: MenuItem) -> String {
+        match item {
+            MenuItem::Resume => "Resume".to_string(),
+            MenuItem::Reset => "Reset".to_string(),
+            MenuItem::SaveState => format!("Save state < {} >", self.save_state_slot),
+            MenuItem::LoadState => format!("Load state < {} >", self.save_state_slot),
+            MenuItem::LoadRom => "Load ROM".to_string(),
+            MenuItem::Speed => format!("Cycles < {} per frame >", SPEEDS[self.speed_index]),
+            MenuItem::Palette => format!("Palette < {} >", self.palette_names[self.palette_index]),
+            MenuItem::Quirk(index) => {
+                let mut quirks = self.quirks;
+                let state = if *quirk_mut(&mut quirks, index) {
+                    "on"
+                } else {
+                    "off"
+                };
+                format!("{} < {} >", QUIRK_NAMES[index], state)
+            }
+            MenuItem::Cheat => match self.cheats.get(self.cheat_index) {
+                Some((name, is_enabled)) => format!(
+                    "Cheat < {} : {} >",
+                    name,
+                    if *is_enabled { "on" } else { "off" }
+                ),
+                None => "Cheat < none >".to_string(),
+            },
+            MenuItem::RemapKeys => "Remap keys".to_string(),
+            MenuItem::Quit => "Quit".to_string(),
+        }
+    }
+
+    // Drawn as a panel in the middle of the screen, the selected item being
+    // highlighted with the palette's colours swapped.
+    pub fn draw(&self, framebuffer: &mut Framebuffer, palette: &Palette) {
+        let title = "Paused";
+        let labels: Vec<String> = MENU_ITEMS
+            .iter()
+            .map(|item| self.item_label(*item))
+            .collect();
+
+        let line_height = text_height(TEXT_SCALE) + LINE_SPACING * 2;
+        let content_width = labels
+            .iter()
+            .map(|label| text_width(label, TEXT_SCALE))
+            .chain([text_width(title, TEXT_SCALE)])
+            .max()
+            .unwrap_or(0);
+        let panel_width = content_width + PADDING * 2;
+        let panel_height = line_height * (labels.len() as u32 + 1) + PADDING * 2;
+        let panel_x = framebuffer.width().saturating_sub(panel_width) / 2;
+        let panel_y = framebuffer.height().saturating_sub(panel_height) / 2;
+
+        framebuffer.fill_rect(
+            panel_x,
+            panel_y,
+            panel_width,
+            panel_height,
+            palette.background(),
+        );
+
+        let text_x = panel_x + PADDING;
+        let mut y = panel_y + PADDING + LINE_SPACING;
+        draw_text(
+            framebuffer,
+            text_x,
+            y,
+            title,
+            palette.foreground(),
+            TEXT_SCALE,
+        );
+
+        for (index, label) in labels.iter().enumerate() {
+            y += line_height;
+
+            let color = if index == self.selected_item {
+                framebuffer.fill_rect(
+                    panel_x,
+                    y - LINE_SPACING,
+                    panel_width,
+                    line_height,
+                    palette.foreground(),
+                );
+                palette.background()
+            } else {
+                palette.foreground()
+            };
+            draw_text(framebuffer, text_x, y, label, color, TEXT_SCALE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
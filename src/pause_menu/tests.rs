@@ -0,0 +1,161 @@
+use super::*;
+use crate::cheats::{Cheat, CheatKind};
+
+// Moves the selection down to an item of MENU_ITEMS.
+fn select(menu: &mut PauseMenu, item: MenuItem) {
+    menu.open();
+    let index = MENU_ITEMS.iter().position(|menu_item| *menu_item == item);
+    for _ in 0..index.unwrap() {
+        assert_eq!(menu.handle_input(MenuInput::Down), None);
+    }
+}
+
+#[test]
+fn selection_wraps_around() {
+    let mut menu = PauseMenu::new();
+    assert_eq!(
+        menu.handle_input(MenuInput::Select),
+        Some(MenuAction::Resume)
+    );
+
+    menu.handle_input(MenuInput::Up);
+    assert_eq!(menu.handle_input(MenuInput::Select), Some(MenuAction::Quit));
+    menu.handle_input(MenuInput::Down);
+    assert_eq!(
+        menu.handle_input(MenuInput::Select),
+        Some(MenuAction::Resume)
+    );
+
+    // Opening the menu again starts from the top.
+    menu.handle_input(MenuInput::Down);
+    menu.open();
+    assert_eq!(
+        menu.handle_input(MenuInput::Select),
+        Some(MenuAction::Resume)
+    );
+}
+
+#[test]
+fn save_state_slots_step_and_wrap() {
+    let mut menu = PauseMenu::new();
+    select(&mut menu, MenuItem::SaveState);
+    assert_eq!(menu.save_state_slot(), 1);
+
+    assert_eq!(menu.handle_input(MenuInput::Left), None);
+    assert_eq!(menu.save_state_slot(), SAVE_STATE_SLOTS);
+    assert_eq!(
+        menu.handle_input(MenuInput::Select),
+        Some(MenuAction::SaveState(SAVE_STATE_SLOTS))
+    );
+    menu.handle_input(MenuInput::Right);
+    menu.handle_input(MenuInput::Right);
+    assert_eq!(menu.save_state_slot(), 2);
+
+    // Saving and loading share the slot.
+    menu.handle_input(MenuInput::Down);
+    assert_eq!(
+        menu.handle_input(MenuInput::Select),
+        Some(MenuAction::LoadState(2))
+    );
+    assert_eq!(menu.item_label(MenuItem::LoadState), "Load state < 2 >");
+}
+
+#[test]
+fn quirks_toggle_one_at_a_time() {
+    let mut menu = PauseMenu::new();
+    select(&mut menu, MenuItem::Quirk(2));
+
+    let mut quirks = Quirks::default();
+    quirks.jump_uses_vx = !quirks.jump_uses_vx;
+    assert_eq!(
+        menu.handle_input(MenuInput::Select),
+        Some(MenuAction::SetQuirks(quirks))
+    );
+    let state = if quirks.jump_uses_vx { "on" } else { "off" };
+    assert_eq!(
+        menu.item_label(MenuItem::Quirk(2)),
+        format!("Jump uses VX < {} >", state)
+    );
+
+    assert_eq!(
+        menu.handle_input(MenuInput::Left),
+        Some(MenuAction::SetQuirks(Quirks::default()))
+    );
+}
+
+#[test]
+fn speed_goes_through_the_presets() {
+    let mut menu = PauseMenu::new();
+    select(&mut menu, MenuItem::Speed);
+
+    let start = menu.speed_index;
+    for offset in 1..=SPEEDS.len() {
+        assert_eq!(
+            menu.handle_input(MenuInput::Right),
+            Some(MenuAction::SetInstructionsPerFrame(
+                SPEEDS[(start + offset) % SPEEDS.len()]
+            ))
+        );
+    }
+    assert_eq!(menu.speed_index, start);
+}
+
+#[test]
+fn cheats_are_toggled_by_index() {
+    let mut menu = PauseMenu::new();
+    select(&mut menu, MenuItem::Cheat);
+    assert_eq!(menu.handle_input(MenuInput::Select), None);
+    assert_eq!(menu.item_label(MenuItem::Cheat), "Cheat < none >");
+
+    let mut cheats = Cheats::new();
+    cheats.add(Cheat::new(
+        "Lives",
+        CheatKind::Freeze {
+            address: 0x300,
+            value: 3,
+        },
+    ));
+    cheats.add(Cheat::new(
+        "Timer",
+        CheatKind::Freeze {
+            address: 0x301,
+            value: 0,
+        },
+    ));
+    menu.set_cheats(&cheats);
+
+    menu.handle_input(MenuInput::Left);
+    assert_eq!(menu.item_label(MenuItem::Cheat), "Cheat < Timer : on >");
+    assert_eq!(
+        menu.handle_input(MenuInput::Select),
+        Some(MenuAction::ToggleCheat(1))
+    );
+}
+
+#[test]
+fn shows_the_settings_of_the_machine() {
+    let mut menu = PauseMenu::new();
+    let mut quirks = Quirks::default();
+    quirks.shift_uses_vy = !quirks.shift_uses_vy;
+    quirks.logic_resets_vf = !quirks.logic_resets_vf;
+    menu.set_quirks(quirks);
+    menu.set_instructions_per_frame(SPEEDS[3]);
+    assert_eq!(
+        menu.item_label(MenuItem::Speed),
+        format!("Cycles < {} per frame >", SPEEDS[3])
+    );
+
+    // Toggling a quirk keeps the others as the machine has them.
+    select(&mut menu, MenuItem::Quirk(1));
+    quirks.load_store_increments_index = !quirks.load_store_increments_index;
+    assert_eq!(
+        menu.handle_input(MenuInput::Select),
+        Some(MenuAction::SetQuirks(quirks))
+    );
+
+    // Speeds between two presets show the closest one.
+    menu.set_instructions_per_frame(19);
+    assert_eq!(menu.item_label(MenuItem::Speed), "Cycles < 20 per frame >");
+    menu.set_instructions_per_frame(1000);
+    assert_eq!(menu.item_label(MenuItem::Speed), "Cycles < 500 per frame >");
+}
@@ -0,0 +1,266 @@
+use crate::disassembler::{decode, InstructionKind, INSTRUCTION_KINDS};
+use crate::memory::TOTAL_MEMORY_BYTES;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+
+const TOP_ENTRIES: usize = 15;
+const MAX_CALL_DEPTH: usize = 256;
+const ROOT_FRAME_NAME: &str = "main";
+
+// Counts where the cycles of a ROM go. Subroutines are named after their
+// address, the code outside any of them being "main".
+pub struct Profiler {
+    address_counts: Vec<u64>,
+    address_opcodes: Vec<u16>, // Last opcode seen at each address, for the report.
+    kind_counts: HashMap<InstructionKind, u64>,
+    call_stack: Vec<u16>,
+    ignored_calls: usize, // Calls deeper than MAX_CALL_DEPTH, still waiting for their RET.
+    stack_counts: HashMap<Vec<u16>, u64>,
+    instructions: u64,
+    frames: u64,
+    key_wait_frames: u64,
+    delay_wait_frames: u64,
+    // State of the frame being run.
+    is_waiting_for_key: bool,
+    delay_timer_polls: u32,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn frame_name(address: u16) -> String {
+    format!("sub_{:03X}", address)
+}
+
+fn percentage(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            address_counts: vec![0; TOTAL_MEMORY_BYTES as usize],
+            address_opcodes: vec![0; TOTAL_MEMORY_BYTES as usize],
+            kind_counts: HashMap::new(),
+            call_stack: Vec::new(),
+            ignored_calls: 0,
+            stack_counts: HashMap::new(),
+            instructions: 0,
+            frames: 0,
+            key_wait_frames: 0,
+            delay_wait_frames: 0,
+            is_waiting_for_key: false,
+            delay_timer_polls: 0,
+        }
+    }
+
+    // The machine was reset or a state loaded, the current calls are gone.
+    pub fn reset_call_stack(&mut self) {
+        self.call_stack.clear();
+        self.ignored_calls = 0;
+    }
+
+    // To be called before each instruction is executed.
+    pub fn record_instruction(&mut self, program_counter: u16, opcode: u16, delay_timer: u8) {
+        let address = program_counter as usize % TOTAL_MEMORY_BYTES as usize;
+        self.address_counts[address] += 1;
+        self.address_opcodes[address] = opcode;
+        *self.kind_counts.entry(decode(opcode).kind).or_insert(0) += 1;
+        self.instructions += 1;
+
+        match self.stack_counts.get_mut(self.call_stack.as_slice()) {
+            Some(count) => *count += 1,
+            None => {
+                self.stack_counts.insert(self.call_stack.clone(), 1);
+            }
+        }
+
+        // FX0A does not move the program counter until a key is pressed, so a
+        // frame ending on it was spent waiting.
+        self.is_waiting_for_key = opcode & 0xF0FF == 0xF00A;
+        if opcode & 0xF0FF == 0xF007 && delay_timer > 0 {
+            self.delay_timer_polls += 1;
+        }
+
+        match opcode {
+            0x00EE => {
+                if self.ignored_calls > 0 {
+                    self.ignored_calls -= 1;
+                } else {
+                    self.call_stack.pop();
+                }
+            }
+            _ if opcode & 0xF000 == 0x2000 => {
+                if self.call_stack.len() < MAX_CALL_DEPTH {
+                    self.call_stack.push(opcode & 0x0FFF);
+                } else {
+                    self.ignored_calls += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // To be called once every 60 Hz frame.
+    pub fn end_frame(&mut self) {
+        self.frames += 1;
+
+        // Reading a running delay timer more than once in a frame is a busy loop
+        // waiting for it to reach zero.
+        if self.is_waiting_for_key {
+            self.key_wait_frames += 1;
+        } else if self.delay_timer_polls > 1 {
+            self.delay_wait_frames += 1;
+        }
+
+        self.is_waiting_for_key = false;
+        self.delay_timer_polls = 0;
+    }
+
+    // Instructions spent in each subroutine itself, and including the ones it calls.
+    fn subroutine_counts(&self) -> Vec<(String, u64, u64)> {
+        let mut counts: HashMap<Option<u16>, (u64, u64)> = HashMap::new();
+
+        for (stack, count) in &self.stack_counts {
+            counts.entry(stack.last().copied()).or_default().0 += count;
+
+            let mut frames: Vec<Option<u16>> = stack.iter().map(|address| Some(*address)).collect();
+            frames.push(None);
+            frames.sort_unstable();
+            frames.dedup();
+            for frame in frames {
+                counts.entry(frame).or_default().1 += count;
+            }
+        }
+
+        let mut subroutines: Vec<(String, u64, u64)> = counts
+            .into_iter()
+            .map(|(frame, (self_count, total_count))| {
+                let name = frame.map_or(ROOT_FRAME_NAME.to_string(), frame_name);
+                (name, self_count, total_count)
+            })
+            .collect();
+        subroutines.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        subroutines
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        let total = self.instructions;
+
+        let _ = writeln!(
+            report,
+            "Profile : {} instructions over {} frames ({:.1} per frame)",
+            total,
+            self.frames,
+            total as f64 / self.frames.max(1) as f64
+        );
+        let _ = writeln!(
+            report,
+            "Frames waiting for a key (FX0A) : {} ({:.1}%)",
+            self.key_wait_frames,
+            percentage(self.key_wait_frames, self.frames)
+        );
+        let _ = writeln!(
+            report,
+            "Frames polling the delay timer : {} ({:.1}%)",
+            self.delay_wait_frames,
+            percentage(self.delay_wait_frames, self.frames)
+        );
+
+        let _ = writeln!(report, "\nInstruction kinds :");
+        for kind in INSTRUCTION_KINDS {
+            let count = self.kind_counts.get(&kind).copied().unwrap_or(0);
+            if count > 0 {
+                let _ = writeln!(
+                    report,
+                    "  {:<12} {:>12} {:>6.1}%",
+                    kind.name(),
+                    count,
+                    percentage(count, total)
+                );
+            }
+        }
+
+        let mut addresses: Vec<usize> = (0..self.address_counts.len())
+            .filter(|address| self.address_counts[*address] > 0)
+            .collect();
+        addresses.sort_by(|a, b| {
+            self.address_counts[*b]
+                .cmp(&self.address_counts[*a])
+                .then(a.cmp(b))
+        });
+
+        let _ = writeln!(report, "\nTop addresses :");
+        for address in addresses.into_iter().take(TOP_ENTRIES) {
+            let count = self.address_counts[address];
+            let _ = writeln!(
+                report,
+                "  {:#05X}  {:<18} {:>12} {:>6.1}%",
+                address,
+                decode(self.address_opcodes[address]).mnemonic,
+                count,
+                percentage(count, total)
+            );
+        }
+
+        let _ = writeln!(report, "\nTop subroutines (self / including calls) :");
+        for (name, self_count, total_count) in
+            self.subroutine_counts().into_iter().take(TOP_ENTRIES)
+        {
+            let _ = writeln!(
+                report,
+                "  {:<8} {:>12} {:>6.1}% {:>12} {:>6.1}%",
+                name,
+                self_count,
+                percentage(self_count, total),
+                total_count,
+                percentage(total_count, total)
+            );
+        }
+
+        report
+    }
+
+    // One "main;sub_2A4;sub_300 1234" line per call stack, the format read by
+    // flamegraph.pl and inferno.
+    pub fn folded_stacks(&self) -> String {
+        let mut lines: Vec<String> = self
+            .stack_counts
+            .iter()
+            .map(|(stack, count)| {
+                let mut frames = vec![ROOT_FRAME_NAME.to_string()];
+                frames.extend(stack.iter().map(|address| frame_name(*address)));
+                format!("{} {}", frames.join(";"), count)
+            })
+            .collect();
+        lines.sort();
+
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    pub fn save(&self, report_path: Option<&str>, folded_path: Option<&str>) -> Result<(), String> {
+        for (path, contents) in [
+            (report_path, self.report()),
+            (folded_path, self.folded_stacks()),
+        ] {
+            if let Some(path) = path {
+                fs::write(path, contents)
+                    .map_err(|_| format!("[Error] Unable to write the profile '{}' !", path))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
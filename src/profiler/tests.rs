@@ -0,0 +1,74 @@
+use super::*;
+use crate::virtual_machine::VirtualMachine;
+
+// main calls sub_206 twice, which calls sub_20C, then loops on itself.
+const PROGRAM: [u8; 16] = [
+    0x22, 0x06, // 0x200 CALL 0x206
+    0x22, 0x06, // 0x202 CALL 0x206
+    0x12, 0x04, // 0x204 JP 0x204
+    0x22, 0x0C, // 0x206 CALL 0x20C
+    0x00, 0xEE, // 0x208 RET
+    0x00, 0x00, // 0x20A
+    0x60, 0x01, // 0x20C LD V0, 0x01
+    0x00, 0xEE, // 0x20E RET
+];
+
+// The machine that ran the program, with its profiler.
+fn profile(program: &[u8], instructions_per_frame: u16, frames: u32) -> VirtualMachine {
+    let mut virtual_machine = VirtualMachine::new();
+    virtual_machine.load_rom_bytes(program).unwrap();
+    virtual_machine.set_profiler(Some(Profiler::new()));
+    for _ in 0..frames {
+        virtual_machine.run_frame(instructions_per_frame);
+    }
+
+    virtual_machine
+}
+
+#[test]
+fn folded_stacks_follow_calls_and_returns() {
+    // Both calls, then two turns of the final loop.
+    let virtual_machine = profile(&PROGRAM, 12, 1);
+    assert_eq!(
+        virtual_machine.profiler().unwrap().folded_stacks(),
+        "main 4\nmain;sub_206 4\nmain;sub_206;sub_20C 4\n"
+    );
+}
+
+#[test]
+fn report_attributes_instructions_to_subroutines() {
+    let report = profile(&PROGRAM, 12, 1).profiler().unwrap().report();
+
+    assert!(report.starts_with("Profile : 12 instructions over 1 frames (12.0 per frame)\n"));
+    for (name, self_count, total_count) in [("main", 4, 12), ("sub_206", 4, 8), ("sub_20C", 4, 4)] {
+        let line = format!(
+            "  {:<8} {:>12} {:>6.1}% {:>12} {:>6.1}%\n",
+            name,
+            self_count,
+            percentage(self_count, 12),
+            total_count,
+            percentage(total_count, 12)
+        );
+        assert!(report.contains(&line), "{}", line);
+    }
+    assert!(report.contains(&format!(
+        "  {:#05X}  {:<18} {:>12} {:>6.1}%\n",
+        0x204,
+        "JP 0x204",
+        2,
+        percentage(2, 12)
+    )));
+}
+
+#[test]
+fn frames_spent_waiting() {
+    // LD V0, K forever.
+    let report = profile(&[0xF0, 0x0A], 5, 3).profiler().unwrap().report();
+    assert!(report.contains("Frames waiting for a key (FX0A) : 3 (100.0%)"));
+
+    // LD V1, 0x3C ; LD DT, V1 ; then LD V0, DT and SE V0, 0 until it is 0.
+    let program = [0x61, 0x3C, 0xF1, 0x15, 0xF0, 0x07, 0x30, 0x00, 0x12, 0x04];
+    let report = profile(&program, 10, 2).profiler().unwrap().report();
+    assert!(report.contains("Frames polling the delay timer : 2 (100.0%)"));
+    assert!(report.contains("Frames waiting for a key (FX0A) : 0 (0.0%)"));
+}
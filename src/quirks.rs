@@ -0,0 +1,14 @@
+// Behaviours that differ between CHIP-8 interpreters. The defaults match what
+// mch8 has always done, which is close to the original COSMAC VIP interpreter
+// except for the shift and load/store instructions.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Quirks {
+    // 8XY6/8XYE shift VY into VX instead of shifting VX in place.
+    pub shift_uses_vy: bool,
+    // FX55/FX65 leave I pointing past the last register.
+    pub load_store_increments_index: bool,
+    // BNNN jumps to XNN + VX (SCHIP) instead of NNN + V0.
+    pub jump_uses_vx: bool,
+    // 8XY1/8XY2/8XY3 clear VF.
+    pub logic_resets_vf: bool,
+}
@@ -1,4 +1,6 @@
 use crate::globals::*;
+use crate::log_warning;
+use crate::save_state::{StateReader, StateWriter};
 
 #[derive(PartialEq)]
 pub enum PixelState {
@@ -25,9 +27,19 @@ impl RenderTable {
         }
     }
 
+    // Frontends go through these instead of the constants so they keep working
+    // once extended modes change the resolution.
+    pub fn width(&self) -> u8 {
+        CHIP8_SCREEN_WIDTH
+    }
+
+    pub fn height(&self) -> u8 {
+        CHIP8_SCREEN_HEIGHT
+    }
+
+    // Only a query, coordinates outside the screen read as switched off.
     pub fn is_pixel_switched_on(&self, x: u8, y: u8) -> bool {
-        if x > CHIP8_SCREEN_WIDTH || y > CHIP8_SCREEN_HEIGHT {
-            println!("[Warning] Invalid coordinates -> X : {} Y : {}", x, y);
+        if x >= CHIP8_SCREEN_WIDTH || y >= CHIP8_SCREEN_HEIGHT {
             return false;
         }
 
@@ -35,8 +47,8 @@ impl RenderTable {
     }
 
     pub fn change_pixel_state_to(&mut self, x: u8, y: u8, new_state: PixelState) {
-        if x > CHIP8_SCREEN_WIDTH || y > CHIP8_SCREEN_HEIGHT {
-            println!("[Warning] Invalid coordinates -> X : {} Y : {}", x, y);
+        if x >= CHIP8_SCREEN_WIDTH || y >= CHIP8_SCREEN_HEIGHT {
+            log_warning!("Invalid coordinates -> X : {} Y : {}", x, y);
             return;
         }
 
@@ -45,6 +57,11 @@ impl RenderTable {
         self.render_table[y as usize * CHIP8_SCREEN_WIDTH as usize + x as usize] = state;
     }
 
+    // Row after row, to compare screens without going through every coordinate.
+    pub fn pixels(&self) -> &[bool] {
+        &self.render_table
+    }
+
     pub fn clear(&mut self) {
         for y in 0..CHIP8_SCREEN_HEIGHT {
             for x in 0..CHIP8_SCREEN_WIDTH {
@@ -52,4 +69,21 @@ impl RenderTable {
             }
         }
     }
+
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        for pixel in &self.render_table {
+            writer.write_bool(*pixel);
+        }
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), &'static str> {
+        for pixel in self.render_table.iter_mut() {
+            *pixel = reader.read_bool()?;
+        }
+
+        Ok(())
+    }
 }
+
+#[cfg(test)]
+mod tests;
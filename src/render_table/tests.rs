@@ -0,0 +1,15 @@
+use super::*;
+
+#[test]
+fn pixels_outside_the_screen_are_off_and_ignored() {
+    let mut render_table = RenderTable::new();
+    render_table.change_pixel_state_to(63, 31, PixelState::SwitchedOn);
+    render_table.change_pixel_state_to(64, 0, PixelState::SwitchedOn);
+    render_table.change_pixel_state_to(0, 32, PixelState::SwitchedOn);
+
+    assert!(render_table.is_pixel_switched_on(63, 31));
+    assert!(!render_table.is_pixel_switched_on(64, 0));
+    assert!(!render_table.is_pixel_switched_on(0, 32));
+    assert!(!render_table.is_pixel_switched_on(255, 255));
+    assert!(!render_table.is_pixel_switched_on(0, 0));
+}
@@ -0,0 +1,397 @@
+use crate::bitmap_font::{draw_text, text_height};
+use crate::config::Config;
+use crate::framebuffer::Framebuffer;
+use crate::globals::*;
+use crate::logger::{self, LogLevel};
+use crate::palette::Palette;
+use crate::pause_menu::MenuInput;
+use crate::rom_database::{find_metadata, title_from_path};
+use crate::virtual_machine::VirtualMachine;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const ROM_EXTENSIONS: [&str; 4] = ["ch8", "c8", "rom", "xo8"];
+const BUNDLED_ROM_DIRECTORY: &str = "roms";
+const RECENT_ROMS_FILE_NAME: &str = "recent_roms.txt";
+const MAX_RECENT_ROMS: usize = 5;
+const THUMBNAIL_FRAMES: u32 = 180;
+const THUMBNAIL_SCALE: u32 = 3;
+const TEXT_SCALE: u32 = 2;
+const LINE_HEIGHT: u32 = 18;
+const MARGIN: u32 = 8;
+const LIST_TOP: u32 = 32;
+const LIST_CHARACTERS: usize = 28;
+const DETAILS_CHARACTERS: usize = 18;
+
+// Most recent first.
+pub fn load_recent_roms() -> Vec<PathBuf> {
+    let Some(path) = Config::user_dir().map(|dir| dir.join(RECENT_ROMS_FILE_NAME)) else {
+        return Vec::new();
+    };
+
+    fs::read_to_string(path)
+        .map(|text| {
+            text.lines()
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Failing to remember a ROM is not worth bothering the user.
+pub fn add_recent_rom(rom_path: &str) {
+    let Some(dir) = Config::user_dir() else {
+        return;
+    };
+    let rom_path = fs::canonicalize(rom_path).unwrap_or_else(|_| PathBuf::from(rom_path));
+
+    let mut recent_roms = load_recent_roms();
+    recent_roms.retain(|path| *path != rom_path);
+    recent_roms.insert(0, rom_path);
+    recent_roms.truncate(MAX_RECENT_ROMS);
+
+    let text: String = recent_roms
+        .iter()
+        .map(|path| format!("{}\n", path.to_string_lossy()))
+        .collect();
+    let _ = fs::create_dir_all(&dir).and_then(|_| fs::write(dir.join(RECENT_ROMS_FILE_NAME), text));
+}
+
+fn is_rom_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+            .is_some_and(|extension| ROM_EXTENSIONS.contains(&extension.as_str()))
+}
+
+fn scan_directory(directory: &Path) -> Vec<PathBuf> {
+    let Ok(dir_entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    dir_entries
+        .filter_map(|dir_entry| dir_entry.ok().map(|dir_entry| dir_entry.path()))
+        .filter(|path| is_rom_file(path))
+        .collect()
+}
+
+// The screen of a ROM after running it for a few seconds, most games show
+// their title screen by then.
+pub struct Thumbnail {
+    width: u8,
+    height: u8,
+    pixels: Vec<bool>,
+}
+
+impl Thumbnail {
+    // ROMs are run blindly and quietly, a faulting one keeps the screen it had
+    // when it stopped.
+    pub fn generate(rom: &[u8]) -> Option<Thumbnail> {
+        let log_level = logger::level();
+        logger::set_level(LogLevel::Off);
+        let virtual_machine = Thumbnail::run(rom);
+        logger::set_level(log_level);
+        let virtual_machine = virtual_machine?;
+
+        let width = virtual_machine.screen_width();
+        let height = virtual_machine.screen_height();
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| virtual_machine.is_pixel_switched_on(x, y))
+            .collect();
+
+        Some(Thumbnail {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn run(rom: &[u8]) -> Option<VirtualMachine> {
+        let mut virtual_machine = VirtualMachine::new();
+        virtual_machine.load_rom_bytes(rom).ok()?;
+
+        for _ in 0..THUMBNAIL_FRAMES {
+            virtual_machine.run_frame(INSTRUCTIONS_PER_FRAME);
+            if virtual_machine.fault().is_some() {
+                break;
+            }
+        }
+
+        Some(virtual_machine)
+    }
+
+    fn draw(&self, framebuffer: &mut Framebuffer, x: u32, y: u32, palette: &Palette) {
+        for (index, is_on) in self.pixels.iter().enumerate() {
+            let pixel_x = (index % self.width as usize) as u32;
+            let pixel_y = (index / self.width as usize) as u32;
+            let color = if *is_on {
+                palette.foreground()
+            } else {
+                palette.background()
+            };
+
+            framebuffer.fill_rect(
+                x + pixel_x * THUMBNAIL_SCALE,
+                y + pixel_y * THUMBNAIL_SCALE,
+                THUMBNAIL_SCALE,
+                THUMBNAIL_SCALE,
+                color,
+            );
+        }
+    }
+}
+
+pub struct RomEntry {
+    pub path: PathBuf,
+    pub title: String,
+    pub author: Option<&'static str>,
+    pub is_recent: bool,
+}
+
+impl RomEntry {
+    fn new(path: PathBuf, is_recent: bool) -> RomEntry {
+        let metadata = fs::read(&path).ok().and_then(|rom| find_metadata(&rom));
+
+        RomEntry {
+            title: metadata.map_or_else(
+                || title_from_path(&path),
+                |metadata| metadata.title.to_string(),
+            ),
+            author: metadata.map(|metadata| metadata.author),
+            path,
+            is_recent,
+        }
+    }
+}
+
+fn truncate(text: &str, max_characters: usize) -> String {
+    if text.chars().count() <= max_characters {
+        return text.to_string();
+    }
+
+    let mut truncated: String = text.chars().take(max_characters - 2).collect();
+    truncated.push_str("..");
+    truncated
+}
+
+fn visible_entries_in(height: u32) -> usize {
+    (height.saturating_sub(LIST_TOP + MARGIN) / LINE_HEIGHT).max(1) as usize
+}
+
+// Entries skipped by Left / Right, as many as fit in the default window.
+fn visible_entries() -> usize {
+    visible_entries_in(WINDOW_HEIGHT)
+}
+
+// Screen shown when no ROM is given : the recent ROMs, then the ones found in
+// the `rom_dir` directory and in the bundled `roms/` folder.
+pub struct RomBrowser {
+    entries: Vec<RomEntry>,
+    selected_entry: usize,
+    thumbnails: HashMap<PathBuf, Option<Thumbnail>>,
+}
+
+impl RomBrowser {
+    pub fn new(config: &Config) -> RomBrowser {
+        let mut directories: Vec<PathBuf> =
+            config.rom_directory.iter().map(PathBuf::from).collect();
+        directories.push(PathBuf::from(BUNDLED_ROM_DIRECTORY));
+
+        RomBrowser::with_roms(load_recent_roms(), directories)
+    }
+
+    // The recent ROMs first in their order, then the ones of the directories
+    // sorted by title, each ROM listed once.
+    fn with_roms(recent_roms: Vec<PathBuf>, directories: Vec<PathBuf>) -> RomBrowser {
+        let mut entries: Vec<RomEntry> = recent_roms
+            .into_iter()
+            .filter(|path| path.is_file())
+            .map(|path| RomEntry::new(path, true))
+            .collect();
+
+        let mut found_entries: Vec<RomEntry> = Vec::new();
+        for directory in directories {
+            for path in scan_directory(&directory) {
+                let canonical_path = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                let is_listed = entries.iter().chain(found_entries.iter()).any(|entry| {
+                    fs::canonicalize(&entry.path).unwrap_or_else(|_| entry.path.clone())
+                        == canonical_path
+                });
+
+                if !is_listed {
+                    found_entries.push(RomEntry::new(path, false));
+                }
+            }
+        }
+        found_entries.sort_by_key(|entry| entry.title.to_ascii_lowercase());
+        entries.extend(found_entries);
+
+        let mut rom_browser = RomBrowser {
+            entries,
+            selected_entry: 0,
+            thumbnails: HashMap::new(),
+        };
+        rom_browser.prepare_thumbnail();
+        rom_browser
+    }
+
+    pub fn entries(&self) -> &[RomEntry] {
+        &self.entries
+    }
+
+    // Thumbnails are only generated for the ROMs actually looked at.
+    fn prepare_thumbnail(&mut self) {
+        let Some(entry) = self.entries.get(self.selected_entry) else {
+            return;
+        };
+
+        if !self.thumbnails.contains_key(&entry.path) {
+            let thumbnail = fs::read(&entry.path)
+                .ok()
+                .and_then(|rom| Thumbnail::generate(&rom));
+            self.thumbnails.insert(entry.path.clone(), thumbnail);
+        }
+    }
+
+    // Returns the ROM to start once one is selected.
+    pub fn handle_input(&mut self, input: MenuInput) -> Option<PathBuf> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let last_entry = self.entries.len() - 1;
+        match input {
+            MenuInput::Up => {
+                self.selected_entry = self.selected_entry.checked_sub(1).unwrap_or(last_entry)
+            }
+            MenuInput::Down => {
+                self.selected_entry = if self.selected_entry == last_entry {
+                    0
+                } else {
+                    self.selected_entry + 1
+                }
+            }
+            MenuInput::Left => {
+                self.selected_entry = self.selected_entry.saturating_sub(visible_entries())
+            }
+            MenuInput::Right => {
+                self.selected_entry = (self.selected_entry + visible_entries()).min(last_entry)
+            }
+            MenuInput::Select => return Some(self.entries[self.selected_entry].path.clone()),
+        }
+
+        self.prepare_thumbnail();
+        None
+    }
+
+    pub fn draw(&self, framebuffer: &mut Framebuffer, palette: &Palette) {
+        framebuffer.clear(palette.background());
+        draw_text(
+            framebuffer,
+            MARGIN,
+            MARGIN,
+            "Select a ROM or drop one on the window",
+            palette.foreground(),
+            TEXT_SCALE,
+        );
+
+        if self.entries.is_empty() {
+            draw_text(
+                framebuffer,
+                MARGIN,
+                LIST_TOP,
+                "No ROM found in roms/ or rom_dir",
+                palette.foreground(),
+                TEXT_SCALE,
+            );
+            return;
+        }
+
+        let visible_entries = visible_entries_in(framebuffer.height());
+        let first_entry = (self.selected_entry + 1).saturating_sub(visible_entries);
+        let list_width = (LIST_CHARACTERS as u32 + 2) * 6 * TEXT_SCALE;
+
+        for (row, (index, entry)) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(first_entry)
+            .take(visible_entries)
+            .enumerate()
+        {
+            let y = LIST_TOP + row as u32 * LINE_HEIGHT;
+            let label = format!(
+                "{}{}",
+                if entry.is_recent { "* " } else { "  " },
+                truncate(&entry.title, LIST_CHARACTERS)
+            );
+
+            let color = if index == self.selected_entry {
+                framebuffer.fill_rect(
+                    MARGIN / 2,
+                    y - (LINE_HEIGHT - text_height(TEXT_SCALE)) / 2,
+                    list_width,
+                    LINE_HEIGHT,
+                    palette.foreground(),
+                );
+                palette.background()
+            } else {
+                palette.foreground()
+            };
+            draw_text(framebuffer, MARGIN, y, &label, color, TEXT_SCALE);
+        }
+
+        self.draw_details(framebuffer, list_width + MARGIN * 2, palette);
+    }
+
+    fn draw_details(&self, framebuffer: &mut Framebuffer, x: u32, palette: &Palette) {
+        let entry = &self.entries[self.selected_entry];
+        let mut y = LIST_TOP;
+
+        if let Some(Some(thumbnail)) = self.thumbnails.get(&entry.path) {
+            let width = thumbnail.width as u32 * THUMBNAIL_SCALE;
+            let height = thumbnail.height as u32 * THUMBNAIL_SCALE;
+
+            // One pixel frame so dark thumbnails stand out from the background.
+            framebuffer.fill_rect(x - 1, y - 1, width + 2, height + 2, palette.grid());
+            thumbnail.draw(framebuffer, x, y, palette);
+            y += height + MARGIN * 2;
+        }
+
+        let file_name = entry
+            .path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let details = [
+            Some(truncate(&entry.title, DETAILS_CHARACTERS)),
+            entry
+                .author
+                .map(|author| truncate(author, DETAILS_CHARACTERS)),
+            Some(truncate(&file_name, DETAILS_CHARACTERS)),
+        ];
+
+        for detail in details.iter().flatten() {
+            draw_text(framebuffer, x, y, detail, palette.foreground(), TEXT_SCALE);
+            y += LINE_HEIGHT;
+        }
+
+        if self.entries.iter().any(|entry| entry.is_recent) {
+            draw_text(
+                framebuffer,
+                x,
+                framebuffer.height() - MARGIN - text_height(TEXT_SCALE),
+                "* Recently played",
+                palette.foreground(),
+                TEXT_SCALE,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,114 @@
+use super::*;
+
+// Sets I to the font's 0, draws it in the corner then runs 0000, a machine code
+// routine the core faults on.
+const FAULTING_ROM: [u8; 6] = [0xF0, 0x29, 0xD0, 0x05, 0x00, 0x00];
+
+// A directory of its own per test, removed when dropped.
+struct RomDirectory {
+    path: PathBuf,
+}
+
+impl RomDirectory {
+    fn new(name: &str, files: &[&str]) -> RomDirectory {
+        let path = std::env::temp_dir().join(format!("mch8-test-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&path).unwrap();
+        for (index, file) in files.iter().enumerate() {
+            // JP to itself, with a different address so every ROM differs.
+            fs::write(path.join(file), [0x12, index as u8 * 2]).unwrap();
+        }
+        RomDirectory { path }
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for RomDirectory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn titles(rom_browser: &RomBrowser) -> Vec<(&str, bool)> {
+    rom_browser
+        .entries()
+        .iter()
+        .map(|entry| (entry.title.as_str(), entry.is_recent))
+        .collect()
+}
+
+#[test]
+fn lists_recent_roms_first_then_sorted_roms_once() {
+    let first = RomDirectory::new("list-first", &["zeta_game.ch8", "alpha.ROM", "notes.txt"]);
+    let second = RomDirectory::new("list-second", &["Beta-Run.c8", "gamma.xo8"]);
+    let recent_roms = vec![
+        second.file("gamma.xo8"),
+        first.path.join("missing.ch8"),
+        first.file("zeta_game.ch8"),
+    ];
+
+    let rom_browser = RomBrowser::with_roms(
+        recent_roms,
+        vec![first.path.clone(), second.path.clone(), first.path.clone()],
+    );
+
+    assert_eq!(
+        titles(&rom_browser),
+        [
+            ("Gamma", true),
+            ("Zeta Game", true),
+            ("Alpha", false),
+            ("Beta Run", false),
+        ]
+    );
+}
+
+#[test]
+fn navigation_wraps_and_pages() {
+    let directory = RomDirectory::new("navigation", &["a.ch8", "b.ch8", "c.ch8"]);
+    let mut rom_browser = RomBrowser::with_roms(Vec::new(), vec![directory.path.clone()]);
+    let selected = |rom_browser: &mut RomBrowser| rom_browser.handle_input(MenuInput::Select);
+
+    assert_eq!(selected(&mut rom_browser), Some(directory.file("a.ch8")));
+    assert_eq!(rom_browser.handle_input(MenuInput::Up), None);
+    assert_eq!(selected(&mut rom_browser), Some(directory.file("c.ch8")));
+    rom_browser.handle_input(MenuInput::Down);
+    assert_eq!(selected(&mut rom_browser), Some(directory.file("a.ch8")));
+
+    // Pages stop at both ends of the list.
+    rom_browser.handle_input(MenuInput::Right);
+    assert_eq!(selected(&mut rom_browser), Some(directory.file("c.ch8")));
+    rom_browser.handle_input(MenuInput::Left);
+    assert_eq!(selected(&mut rom_browser), Some(directory.file("a.ch8")));
+
+    // Only the ROMs looked at get a thumbnail.
+    assert_eq!(rom_browser.thumbnails.len(), 2);
+    assert!(rom_browser.thumbnails[&directory.file("c.ch8")].is_some());
+}
+
+#[test]
+fn empty_browser_ignores_inputs() {
+    let directory = RomDirectory::new("empty", &["readme.md"]);
+    let mut rom_browser = RomBrowser::with_roms(Vec::new(), vec![directory.path.clone()]);
+
+    assert!(rom_browser.entries().is_empty());
+    for input in [MenuInput::Down, MenuInput::Right, MenuInput::Select] {
+        assert_eq!(rom_browser.handle_input(input), None);
+    }
+}
+
+#[test]
+fn thumbnail_of_a_faulting_rom() {
+    let thumbnail = Thumbnail::generate(&FAULTING_ROM).unwrap();
+
+    assert_eq!((thumbnail.width, thumbnail.height), (64, 32));
+    let row = |y: usize| &thumbnail.pixels[y * 64..y * 64 + 5];
+    assert_eq!(row(0), [true, true, true, true, false]);
+    assert_eq!(row(1), [true, false, false, true, false]);
+    assert_eq!(thumbnail.pixels.iter().filter(|is_on| **is_on).count(), 14);
+
+    let too_big = vec![0; 4096];
+    assert!(Thumbnail::generate(&too_big).is_none());
+}
@@ -0,0 +1,54 @@
+use std::path::Path;
+
+pub struct RomMetadata {
+    pub hash: u64,
+    pub title: &'static str,
+    pub author: &'static str,
+}
+
+// ROMs known by the contents of the file, so a renamed copy is still recognised.
+const KNOWN_ROMS: [RomMetadata; 2] = [
+    RomMetadata {
+        hash: 0x624B_3EED_6431_3F42,
+        title: "Pong",
+        author: "Paul Vervalin",
+    },
+    RomMetadata {
+        hash: 0x04EB_2109_DC29_B1AB,
+        title: "Tetris",
+        author: "Fran Dachille",
+    },
+];
+
+// 64 bit FNV-1a, stable across builds and platforms unlike std's hasher.
+pub fn rom_hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+pub fn find_metadata(rom: &[u8]) -> Option<&'static RomMetadata> {
+    let hash = rom_hash(rom);
+    KNOWN_ROMS.iter().find(|metadata| metadata.hash == hash)
+}
+
+// Title of an unknown ROM, made from its file name : "space_invaders.ch8"
+// becomes "Space Invaders".
+pub fn title_from_path(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    stem.split(['_', '-', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
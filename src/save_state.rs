@@ -0,0 +1,98 @@
+// Minimal binary format used for save states : every component appends its
+// fields in a fixed order and reads them back in the same order.
+
+use std::path::PathBuf;
+
+const SAVE_STATE_MAGIC: &[u8; 4] = b"MCH8";
+const SAVE_STATE_VERSION: u8 = 1;
+
+// Save state slots are kept next to the ROM, e.g. `pong.rom.state1`.
+pub fn slot_path(rom_path: &str, slot: u8) -> PathBuf {
+    PathBuf::from(format!("{}.state{}", rom_path, slot))
+}
+
+pub struct StateWriter {
+    buffer: Vec<u8>,
+}
+
+impl Default for StateWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateWriter {
+    pub fn new() -> StateWriter {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(SAVE_STATE_MAGIC);
+        buffer.push(SAVE_STATE_VERSION);
+
+        StateWriter { buffer }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.buffer.push(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Result<StateReader<'a>, &'static str> {
+        let mut reader = StateReader { data };
+
+        if reader.read_bytes(SAVE_STATE_MAGIC.len())? != SAVE_STATE_MAGIC {
+            return Err("[Error] The given data is not a MCH8 save state !");
+        }
+        if reader.read_u8()? != SAVE_STATE_VERSION {
+            return Err("[Error] Unsupported save state version !");
+        }
+
+        Ok(reader)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, &'static str> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, &'static str> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        if self.data.len() < len {
+            return Err("[Error] The save state is truncated !");
+        }
+
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,123 @@
+use super::*;
+use crate::virtual_machine::VirtualMachine;
+
+// Header, processor with an empty stack, RAM and screen.
+const EMPTY_STACK_STATE_BYTES: usize = 5 + 24 + 4096 + 2048;
+
+// CALL 0x206 (CLS and JP 0x204 are never run), then LD VA, 0x42 ; LD DT, VA ;
+// LD F, VA ; DRW V0, V0, 5 so the stack, timers, I and screen are all saved.
+const PROGRAM: [u8; 14] = [
+    0x22, 0x06, 0x00, 0xE0, 0x12, 0x04, 0x6A, 0x42, 0xFA, 0x15, 0xFA, 0x29, 0xD0, 0x05,
+];
+
+#[test]
+fn fields_round_trip() {
+    let mut writer = StateWriter::new();
+    writer.write_u8(0xAB);
+    writer.write_bool(true);
+    writer.write_bool(false);
+    writer.write_u16(0x1234);
+    writer.write_bytes(&[1, 2, 3]);
+    let bytes = writer.into_bytes();
+    assert_eq!(&bytes[..5], b"MCH8\x01");
+    assert_eq!(bytes.len(), 5 + 1 + 2 + 2 + 3);
+
+    let mut reader = StateReader::new(&bytes).unwrap();
+    assert_eq!(reader.read_u8(), Ok(0xAB));
+    assert_eq!(reader.read_bool(), Ok(true));
+    assert_eq!(reader.read_bool(), Ok(false));
+    assert_eq!(reader.read_u16(), Ok(0x1234));
+    assert_eq!(reader.read_bytes(3), Ok(&[1, 2, 3][..]));
+    assert_eq!(
+        reader.read_u8(),
+        Err("[Error] The save state is truncated !")
+    );
+}
+
+#[test]
+fn bad_magic_and_version() {
+    assert_eq!(
+        StateReader::new(b"MCH9\x01").err(),
+        Some("[Error] The given data is not a MCH8 save state !")
+    );
+    assert_eq!(
+        StateReader::new(b"MCH8\x02").err(),
+        Some("[Error] Unsupported save state version !")
+    );
+    assert_eq!(
+        StateReader::new(b"MCH").err(),
+        Some("[Error] The save state is truncated !")
+    );
+    assert_eq!(
+        StateReader::new(b"MCH8").err(),
+        Some("[Error] The save state is truncated !")
+    );
+}
+
+#[test]
+fn machine_round_trip() {
+    let mut virtual_machine = VirtualMachine::new();
+    virtual_machine.load_rom_bytes(&PROGRAM).unwrap();
+    virtual_machine.run_frame(5);
+    let state = virtual_machine.save_state();
+    assert_eq!(state.len(), EMPTY_STACK_STATE_BYTES + 2);
+
+    let mut restored = VirtualMachine::new();
+    restored.load_rom_bytes(&PROGRAM).unwrap();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+
+    let (processor, restored_processor) = (
+        virtual_machine.virtual_processor(),
+        restored.virtual_processor(),
+    );
+    assert_eq!(restored_processor.program_counter(), 0x20E);
+    assert_eq!(restored_processor.stack(), processor.stack());
+    assert_eq!(restored_processor.registers(), processor.registers());
+    assert_eq!(
+        restored_processor.index_register(),
+        processor.index_register()
+    );
+    assert_eq!(restored_processor.delay_timer(), processor.delay_timer());
+    assert_eq!(restored.memory().bytes(), virtual_machine.memory().bytes());
+    assert!(restored.is_pixel_switched_on(1, 0));
+
+    // Both go on the same way.
+    virtual_machine.run_frame(10);
+    restored.run_frame(10);
+    assert_eq!(restored.save_state(), virtual_machine.save_state());
+}
+
+#[test]
+fn truncated_states_leave_the_machine_untouched() {
+    let mut virtual_machine = VirtualMachine::new();
+    virtual_machine.load_rom_bytes(&PROGRAM).unwrap();
+    virtual_machine.run_frame(5);
+    let state = virtual_machine.save_state();
+
+    let mut other = VirtualMachine::new();
+    other.load_rom_bytes(&PROGRAM).unwrap();
+    let before = other.save_state();
+    for length in [0, 5, 20, 5 + 24 + 2, state.len() - 2048, state.len() - 1] {
+        assert_eq!(
+            other.load_state(&state[..length]),
+            Err("[Error] The save state is truncated !"),
+            "{}",
+            length
+        );
+        assert_eq!(other.save_state(), before);
+    }
+
+    let mut bad_version = state.clone();
+    bad_version[4] = SAVE_STATE_VERSION + 1;
+    assert!(other.load_state(&bad_version).is_err());
+    assert_eq!(other.save_state(), before);
+}
+
+#[test]
+fn slot_paths() {
+    assert_eq!(
+        slot_path("roms/pong.rom", 3),
+        PathBuf::from("roms/pong.rom.state3")
+    );
+}
@@ -0,0 +1,680 @@
+extern crate sdl2;
+
+use crate::audio_system::AudioSystem;
+use crate::cheats::{Cheats, SearchComparison};
+use crate::config::Config;
+use crate::crt_filter::CrtFilter;
+use crate::debug_view::{DebugInput, DebugView, DEBUG_VIEW_HEIGHT, DEBUG_VIEW_WIDTH};
+use crate::flicker_filter::FlickerFilter;
+use crate::framebuffer::Framebuffer;
+use crate::frontend::{Frontend, FrontendEvent, Hotkey, KEYPAD_LAYOUT};
+use crate::globals::*;
+use crate::osd::Osd;
+use crate::palette::{Palette, PixelStyle};
+use crate::pause_menu::{MenuAction, MenuInput, PauseMenu};
+use crate::rom_browser::{add_recent_rom, RomBrowser};
+use crate::save_state;
+use crate::viewport::{ScalingMode, Viewport};
+use crate::virtual_machine::VirtualMachine;
+use crate::{log_info, log_warning};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mixer::{InitFlag, Sdl2MixerContext, AUDIO_S16LSB, DEFAULT_CHANNELS};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect;
+use sdl2::render::{Texture, WindowCanvas};
+use sdl2::video::FullscreenType;
+use sdl2::{AudioSubsystem, EventPump, Sdl};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+pub struct SdlFrontend {
+    canvas: WindowCanvas,
+    texture: Texture,
+    texture_size: (u32, u32),
+    framebuffer: Framebuffer,
+    crt_filter: CrtFilter,
+    filtered_framebuffer: Framebuffer,
+    event_pump: EventPump,
+    audio_system: AudioSystem,
+    palette: Palette,
+    pixel_style: PixelStyle,
+    flicker_filter: FlickerFilter,
+    scaling_mode: ScalingMode,
+    osd: Osd,
+    pause_menu: PauseMenu,
+    is_menu_open: bool,
+    key_bindings: Vec<(Keycode, u8)>,
+    remapped_key_index: Option<usize>, // Index in KEYPAD_LAYOUT of the key waiting for a binding.
+    config: Config,
+    base_config: Config, // Without any ROM section, each ROM starts again from it.
+    debug_view: DebugView,
+    is_debug_view_open: bool,
+    debug_framebuffer: Framebuffer,
+    _mixer_context: Sdl2MixerContext,
+    _audio_subsystem: AudioSubsystem,
+    _sdl_context: Sdl,
+}
+
+fn default_key_bindings() -> Vec<(Keycode, u8)> {
+    KEYPAD_LAYOUT
+        .iter()
+        .filter_map(|(c, key)| Keycode::from_name(&c.to_string()).map(|keycode| (keycode, *key)))
+        .collect()
+}
+
+fn debug_input_from_keycode(keycode: Keycode) -> Option<DebugInput> {
+    match keycode {
+        Keycode::Up => Some(DebugInput::CursorUp),
+        Keycode::Down => Some(DebugInput::CursorDown),
+        Keycode::Home => Some(DebugInput::FollowProgramCounter),
+        Keycode::F2 => Some(DebugInput::ToggleBreakpoint),
+        Keycode::PageUp => Some(DebugInput::MemoryPageUp),
+        Keycode::PageDown => Some(DebugInput::MemoryPageDown),
+        Keycode::F6 => Some(DebugInput::NewSearch),
+        Keycode::F7 => Some(DebugInput::Search(SearchComparison::Changed)),
+        Keycode::F8 => Some(DebugInput::Search(SearchComparison::Unchanged)),
+        Keycode::G => Some(DebugInput::Search(SearchComparison::Greater)),
+        Keycode::L => Some(DebugInput::Search(SearchComparison::Less)),
+        Keycode::Return => Some(DebugInput::Search(SearchComparison::Equal(0))),
+        Keycode::Minus => Some(DebugInput::SearchValueDown),
+        Keycode::Equals => Some(DebugInput::SearchValueUp),
+        Keycode::Insert => Some(DebugInput::FreezeCandidate),
+        _ => None,
+    }
+}
+
+fn menu_input_from_keycode(keycode: Keycode) -> Option<MenuInput> {
+    match keycode {
+        Keycode::Up => Some(MenuInput::Up),
+        Keycode::Down => Some(MenuInput::Down),
+        Keycode::Left => Some(MenuInput::Left),
+        Keycode::Right => Some(MenuInput::Right),
+        Keycode::Return | Keycode::KpEnter | Keycode::Space => Some(MenuInput::Select),
+        _ => None,
+    }
+}
+
+// The whole screen is uploaded at once into a single streaming texture. With
+// "unsafe_textures" it is freed along with the renderer, or by an explicit destroy().
+fn create_screen_texture(
+    canvas: &WindowCanvas,
+    width: u32,
+    height: u32,
+) -> Result<Texture, String> {
+    canvas
+        .texture_creator()
+        .create_texture_streaming(PixelFormatEnum::RGBA32, width, height)
+        .map_err(|e| e.to_string())
+}
+
+impl SdlFrontend {
+    pub fn new(config: &Config) -> Result<SdlFrontend, String> {
+        let sdl_context = sdl2::init()?;
+        let video_subsystem = sdl_context.video()?;
+        let audio_subsystem = sdl_context.audio()?;
+        let mixer_context = sdl2::mixer::init(InitFlag::all())?;
+        sdl2::mixer::open_audio(44100, AUDIO_S16LSB, DEFAULT_CHANNELS, 2048)?;
+
+        let mut audio_system = AudioSystem::new();
+        audio_system.init();
+
+        let mut window = video_subsystem
+            .window("MCH8 by Yann BOYER", WINDOW_WIDTH, WINDOW_HEIGHT)
+            .position_centered()
+            .resizable()
+            .build()
+            .map_err(|e| e.to_string())?;
+        window
+            .set_minimum_size(CHIP8_SCREEN_WIDTH as u32, CHIP8_SCREEN_HEIGHT as u32)
+            .map_err(|e| e.to_string())?;
+        if config.is_fullscreen {
+            window.set_fullscreen(FullscreenType::Desktop)?;
+        }
+
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+
+        let texture = create_screen_texture(&canvas, WINDOW_WIDTH, WINDOW_HEIGHT)?;
+
+        let event_pump = sdl_context.event_pump()?;
+
+        let palette = config.palette();
+        let mut pause_menu = PauseMenu::new();
+        pause_menu.select_palette(&palette);
+
+        let mut osd = Osd::new();
+        osd.set_counter_visible(config.is_fps_visible);
+
+        Ok(SdlFrontend {
+            canvas,
+            texture,
+            texture_size: (WINDOW_WIDTH, WINDOW_HEIGHT),
+            framebuffer: Framebuffer::new(WINDOW_WIDTH, WINDOW_HEIGHT),
+            crt_filter: CrtFilter::new(config.crt_settings),
+            filtered_framebuffer: Framebuffer::new(WINDOW_WIDTH, WINDOW_HEIGHT),
+            event_pump,
+            audio_system,
+            palette,
+            pixel_style: config.pixel_style,
+            flicker_filter: FlickerFilter::new(config.flicker_mode, config.flicker_decay),
+            scaling_mode: config.scaling_mode,
+            osd,
+            pause_menu,
+            is_menu_open: false,
+            key_bindings: default_key_bindings(),
+            remapped_key_index: None,
+            config: config.clone(),
+            base_config: config.base()?,
+            debug_view: DebugView::new(),
+            is_debug_view_open: false,
+            debug_framebuffer: Framebuffer::new(DEBUG_VIEW_WIDTH, DEBUG_VIEW_HEIGHT),
+            _mixer_context: mixer_context,
+            _audio_subsystem: audio_subsystem,
+            _sdl_context: sdl_context,
+        })
+    }
+
+    // Follows the resolution of the emulated screen.
+    fn resize_screen(&mut self, width: u32, height: u32) {
+        self.framebuffer = Framebuffer::new(width, height);
+        self.filtered_framebuffer = Framebuffer::new(width, height);
+    }
+
+    // The texture is shared by the screen and the debug view, each with its size.
+    fn resize_texture(&mut self, width: u32, height: u32) {
+        if self.texture_size == (width, height) {
+            return;
+        }
+
+        let texture = create_screen_texture(&self.canvas, width, height).unwrap();
+        let old_texture = std::mem::replace(&mut self.texture, texture);
+        unsafe { old_texture.destroy() };
+        self.texture_size = (width, height);
+    }
+
+    // Draws the last rendered frame, letterboxed to fit the window. Integer
+    // scaling works on CHIP-8 pixels, not on the pre-scaled framebuffer.
+    fn blit(&mut self) {
+        let (window_width, window_height) = self.canvas.output_size().unwrap();
+        let viewport = if self.is_debug_view_open {
+            Viewport::fit(
+                window_width,
+                window_height,
+                DEBUG_VIEW_WIDTH,
+                DEBUG_VIEW_HEIGHT,
+                ScalingMode::Fit,
+            )
+        } else {
+            Viewport::fit(
+                window_width,
+                window_height,
+                self.framebuffer.width() / SCALE_FACTOR as u32,
+                self.framebuffer.height() / SCALE_FACTOR as u32,
+                self.scaling_mode,
+            )
+        };
+
+        self.canvas
+            .set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
+        self.canvas.clear();
+        self.canvas
+            .copy(
+                &self.texture,
+                None,
+                Rect::new(viewport.x, viewport.y, viewport.width, viewport.height),
+            )
+            .unwrap();
+        self.canvas.present();
+    }
+
+    // Takes the settings of a newly selected ROM (its section of the config file).
+    pub fn apply_config(&mut self, config: &Config) {
+        self.palette = config.palette();
+        self.pause_menu.select_palette(&self.palette);
+        self.pixel_style = config.pixel_style;
+        self.flicker_filter = FlickerFilter::new(config.flicker_mode, config.flicker_decay);
+        self.crt_filter = CrtFilter::new(config.crt_settings);
+        self.scaling_mode = config.scaling_mode;
+        self.config = config.clone();
+    }
+
+    // Shows the ROM browser until a ROM is picked or dropped on the window. None
+    // when the user quits (or goes back to the pause menu) instead.
+    pub fn browse_roms(&mut self, rom_browser: &mut RomBrowser) -> Option<String> {
+        let frame_duration = Duration::from_micros(FRAME_DURATION_MICROS);
+
+        loop {
+            for event in self.event_pump.poll_iter().collect::<Vec<_>>() {
+                match event {
+                    Event::Quit { .. }
+                    | Event::KeyDown {
+                        keycode: Some(Keycode::Escape),
+                        ..
+                    } => return None,
+                    Event::DropFile { filename, .. } => return Some(filename),
+                    Event::KeyDown {
+                        keycode: Some(Keycode::F11),
+                        repeat: false,
+                        ..
+                    } => self.toggle_fullscreen(),
+                    Event::KeyDown {
+                        keycode: Some(keycode),
+                        ..
+                    } => {
+                        if let Some(rom_path) = menu_input_from_keycode(keycode)
+                            .and_then(|input| rom_browser.handle_input(input))
+                        {
+                            return Some(rom_path.to_string_lossy().into_owned());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            rom_browser.draw(&mut self.framebuffer, &self.palette);
+            self.resize_texture(self.framebuffer.width(), self.framebuffer.height());
+            self.texture
+                .update(None, self.framebuffer.pixels(), self.framebuffer.pitch())
+                .unwrap();
+            self.blit();
+
+            std::thread::sleep(frame_duration);
+        }
+    }
+
+    fn keypad_key_from_keycode(&self, keycode: Keycode) -> Option<u8> {
+        self.key_bindings
+            .iter()
+            .find(|(bound_keycode, _)| *bound_keycode == keycode)
+            .map(|(_, key)| *key)
+    }
+
+    fn set_menu_open(&mut self, is_open: bool, events: &mut Vec<FrontendEvent>) {
+        self.is_menu_open = is_open;
+        if is_open {
+            self.pause_menu.open();
+            events.push(FrontendEvent::Hotkey(Hotkey::Pause));
+        } else {
+            events.push(FrontendEvent::Hotkey(Hotkey::Resume));
+        }
+    }
+
+    fn handle_menu_action(&mut self, action: MenuAction, events: &mut Vec<FrontendEvent>) {
+        match action {
+            MenuAction::Resume => self.set_menu_open(false, events),
+            MenuAction::Reset => {
+                events.push(FrontendEvent::Hotkey(Hotkey::Reset));
+                self.set_menu_open(false, events);
+            }
+            MenuAction::SaveState(slot) => events.push(FrontendEvent::SaveState(slot)),
+            MenuAction::LoadState(slot) => {
+                events.push(FrontendEvent::LoadState(slot));
+                self.set_menu_open(false, events);
+            }
+            MenuAction::LoadRom => {
+                if let Some(rom_path) = self.browse_roms(&mut RomBrowser::new(&self.config)) {
+                    events.push(FrontendEvent::LoadRom(rom_path));
+                    self.set_menu_open(false, events);
+                }
+            }
+            MenuAction::SetInstructionsPerFrame(instructions_per_frame) => events.push(
+                FrontendEvent::SetInstructionsPerFrame(instructions_per_frame),
+            ),
+            MenuAction::SetPalette(palette) => self.palette = palette,
+            MenuAction::SetQuirks(quirks) => events.push(FrontendEvent::SetQuirks(quirks)),
+            MenuAction::ToggleCheat(index) => events.push(FrontendEvent::ToggleCheat(index)),
+            MenuAction::RemapKeys => self.remap_key(Some(0)),
+            MenuAction::Quit => {
+                log_info!("Exiting...");
+                events.push(FrontendEvent::Quit);
+            }
+        }
+    }
+
+    // Walks through the keypad, the next key pressed being bound to the key
+    // shown in the prompt.
+    fn remap_key(&mut self, index: Option<usize>) {
+        self.remapped_key_index = index.filter(|index| *index < KEYPAD_LAYOUT.len());
+
+        let prompt = self.remapped_key_index.map(|index| {
+            format!(
+                "Press the key for {:X} (Esc to stop)",
+                KEYPAD_LAYOUT[index].1
+            )
+        });
+        self.osd.set_prompt(prompt);
+    }
+
+    fn bind_key(&mut self, keycode: Keycode) {
+        let Some(index) = self.remapped_key_index else {
+            return;
+        };
+        let key = KEYPAD_LAYOUT[index].1;
+
+        self.key_bindings
+            .retain(|(bound_keycode, bound_key)| *bound_keycode != keycode && *bound_key != key);
+        self.key_bindings.push((keycode, key));
+
+        self.remap_key(Some(index + 1));
+        if self.remapped_key_index.is_none() {
+            self.osd.show_message("Keys remapped");
+        }
+    }
+
+    // The panels need room, a small window is grown to show them.
+    fn toggle_debug_view(&mut self) {
+        self.is_debug_view_open = !self.is_debug_view_open;
+        if !self.is_debug_view_open {
+            return;
+        }
+
+        let window = self.canvas.window_mut();
+        let (width, height) = window.size();
+        if window.fullscreen_state() == FullscreenType::Off
+            && (width < DEBUG_VIEW_WIDTH || height < DEBUG_VIEW_HEIGHT)
+        {
+            if let Err(err) = window.set_size(DEBUG_VIEW_WIDTH, DEBUG_VIEW_HEIGHT) {
+                log_warning!("Unable to resize the window for the debugger : {}", err);
+            }
+        }
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
+        let fullscreen_type = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+
+        if let Err(err_message) = window.set_fullscreen(fullscreen_type) {
+            log_warning!("Unable to toggle fullscreen : {}", err_message);
+        }
+    }
+}
+
+impl Frontend for SdlFrontend {
+    fn present_frame(&mut self, virtual_machine: &VirtualMachine) {
+        let width = virtual_machine.screen_width() as u32 * SCALE_FACTOR as u32;
+        let height = virtual_machine.screen_height() as u32 * SCALE_FACTOR as u32;
+        if width != self.framebuffer.width() || height != self.framebuffer.height() {
+            self.resize_screen(width, height);
+        }
+
+        if self.is_debug_view_open {
+            self.resize_texture(DEBUG_VIEW_WIDTH, DEBUG_VIEW_HEIGHT);
+        } else {
+            self.resize_texture(width, height);
+        }
+
+        self.flicker_filter.refresh(virtual_machine);
+        self.framebuffer.draw_screen(
+            &self.flicker_filter,
+            &self.palette,
+            self.pixel_style,
+            SCALE_FACTOR as u32,
+        );
+
+        let framebuffer = if self.crt_filter.is_enabled() {
+            self.crt_filter
+                .apply(&self.framebuffer, &mut self.filtered_framebuffer);
+            &mut self.filtered_framebuffer
+        } else {
+            &mut self.framebuffer
+        };
+
+        let framebuffer = if self.is_debug_view_open {
+            self.debug_view.update(virtual_machine);
+            self.debug_view.draw(
+                &mut self.debug_framebuffer,
+                framebuffer,
+                virtual_machine,
+                &self.palette,
+            );
+            &mut self.debug_framebuffer
+        } else {
+            framebuffer
+        };
+
+        // Drawn after the CRT filter so the text stays sharp.
+        self.osd.update(virtual_machine.executed_instructions());
+        if self.is_menu_open {
+            self.pause_menu.set_cheats(virtual_machine.cheats());
+            self.pause_menu
+                .set_quirks(virtual_machine.virtual_processor().quirks());
+            self.pause_menu.draw(framebuffer, &self.palette);
+        }
+        self.osd.draw(framebuffer, &self.palette);
+
+        self.texture
+            .update(None, framebuffer.pixels(), framebuffer.pitch())
+            .unwrap();
+        self.blit();
+    }
+
+    fn poll_input(&mut self) -> Vec<FrontendEvent> {
+        let mut events = Vec::new();
+        let mut needs_blit = false;
+
+        for event in self.event_pump.poll_iter().collect::<Vec<_>>() {
+            match event {
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => self.toggle_fullscreen(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::Return),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD) => self.toggle_fullscreen(),
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..) | WindowEvent::Exposed,
+                    ..
+                } => needs_blit = true,
+                Event::Quit { .. } => {
+                    log_info!("Exiting...");
+                    events.push(FrontendEvent::Quit);
+                }
+                Event::DropFile { filename, .. } => events.push(FrontendEvent::LoadRom(filename)),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } if self.remapped_key_index.is_some() => {
+                    if keycode == Keycode::Escape {
+                        self.remap_key(None);
+                    } else {
+                        self.bind_key(keycode);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    repeat: false,
+                    ..
+                } => {
+                    let is_open = !self.is_menu_open;
+                    self.set_menu_open(is_open, &mut events);
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } if self.is_menu_open => {
+                    if let Some(action) = menu_input_from_keycode(keycode)
+                        .and_then(|input| self.pause_menu.handle_input(input))
+                    {
+                        self.handle_menu_action(action, &mut events);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => self.toggle_debug_view(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F10),
+                    ..
+                } => events.push(FrontendEvent::Hotkey(Hotkey::StepInstruction)),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } if self.is_debug_view_open && debug_input_from_keycode(keycode).is_some() => {
+                    if let Some(event) = debug_input_from_keycode(keycode)
+                        .and_then(|input| self.debug_view.handle_input(input))
+                    {
+                        events.push(event);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    repeat: false,
+                    ..
+                } => {
+                    let is_visible = !self.osd.is_counter_visible();
+                    self.osd.set_counter_visible(is_visible);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    repeat: false,
+                    ..
+                } => events.push(FrontendEvent::SaveState(self.pause_menu.save_state_slot())),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => events.push(FrontendEvent::LoadState(self.pause_menu.save_state_slot())),
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    repeat: false,
+                    ..
+                } => events.push(FrontendEvent::Hotkey(Hotkey::TogglePause)),
+                Event::KeyDown {
+                    keycode: Some(Keycode::N),
+                    ..
+                } => events.push(FrontendEvent::Hotkey(Hotkey::FrameAdvance)),
+                Event::KeyDown {
+                    keycode: Some(Keycode::Tab),
+                    repeat: false,
+                    ..
+                } => events.push(FrontendEvent::Hotkey(Hotkey::SetFastForward(true))),
+                Event::KeyUp {
+                    keycode: Some(Keycode::Tab),
+                    ..
+                } => events.push(FrontendEvent::Hotkey(Hotkey::SetFastForward(false))),
+                Event::KeyDown {
+                    keycode: Some(Keycode::LeftBracket),
+                    ..
+                } => events.push(FrontendEvent::Hotkey(Hotkey::SlowDown)),
+                Event::KeyDown {
+                    keycode: Some(Keycode::RightBracket),
+                    ..
+                } => events.push(FrontendEvent::Hotkey(Hotkey::SpeedUp)),
+                Event::KeyDown {
+                    keycode: Some(Keycode::Backspace),
+                    repeat: false,
+                    ..
+                } => events.push(FrontendEvent::Hotkey(Hotkey::Reset)),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let Some(key) = self.keypad_key_from_keycode(keycode) {
+                        events.push(FrontendEvent::KeyDown(key));
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let Some(key) = self.keypad_key_from_keycode(keycode) {
+                        events.push(FrontendEvent::KeyUp(key));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // The ROM may not draw anything for a while, the window still has to
+        // follow resizes.
+        if needs_blit {
+            self.blit();
+        }
+
+        events
+    }
+
+    fn set_audio(&mut self, is_active: bool) {
+        if is_active {
+            self.audio_system.play_beep_sound();
+        }
+    }
+
+    fn frame_finished(&mut self, virtual_machine: &VirtualMachine) {
+        if self.flicker_filter.needs_every_frame() {
+            self.flicker_filter.update(virtual_machine);
+        }
+    }
+
+    fn needs_continuous_repaint(&self) -> bool {
+        self.flicker_filter.needs_every_frame()
+            || self.is_menu_open
+            || self.is_debug_view_open
+            || self.osd.is_active()
+    }
+
+    fn instructions_per_frame_changed(&mut self, instructions_per_frame: u16) {
+        self.pause_menu
+            .set_instructions_per_frame(instructions_per_frame);
+    }
+
+    fn show_message(&mut self, message: &str) {
+        self.osd.show_message(message);
+    }
+
+    fn rom_changed(&mut self, virtual_machine: &mut VirtualMachine, rom_path: &str) {
+        let mut config = self.base_config.clone();
+        if let Err(err_message) = config.select_rom(rom_path) {
+            self.osd.show_message(&err_message);
+        }
+        if let Err(err_message) = config.configure_machine(virtual_machine) {
+            self.osd.show_message(err_message);
+        }
+        self.apply_config(&config);
+        add_recent_rom(rom_path);
+
+        let file_name = Path::new(rom_path)
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.osd.show_message(&format!("Loaded {}", file_name));
+    }
+
+    fn write_state(&mut self, slot: u8, state: &[u8]) -> Result<(), String> {
+        let rom_path = self
+            .config
+            .rom_path
+            .as_ref()
+            .ok_or("[Error] No ROM loaded !")?;
+        fs::write(save_state::slot_path(rom_path, slot), state)
+            .map_err(|_| "[Error] Unable to write the save state !".to_string())
+    }
+
+    fn read_cheats(&mut self, rom: &[u8]) -> Result<Cheats, String> {
+        Cheats::load_for_rom(rom)
+    }
+
+    fn write_cheats(&mut self, rom: &[u8], cheats: &Cheats) -> Result<(), String> {
+        cheats.save_for_rom(rom)
+    }
+
+    fn read_state(&mut self, slot: u8) -> Result<Vec<u8>, String> {
+        let rom_path = self
+            .config
+            .rom_path
+            .as_ref()
+            .ok_or("[Error] No ROM loaded !")?;
+        fs::read(save_state::slot_path(rom_path, slot))
+            .map_err(|_| format!("[Error] Slot {} is empty !", slot))
+    }
+}
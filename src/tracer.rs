@@ -0,0 +1,191 @@
+use crate::disassembler::{decode, InstructionKind};
+use crate::log_error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+const BINARY_TRACE_MAGIC: &[u8; 4] = b"MCHT";
+const BINARY_TRACE_VERSION: u8 = 1;
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum TraceFormat {
+    #[default]
+    Text, // One `key=value` line per instruction.
+    Binary, // Fixed size records, see TraceRecord::write_binary.
+}
+
+impl TraceFormat {
+    pub fn from_name(name: &str) -> Option<TraceFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" => Some(TraceFormat::Text),
+            "binary" => Some(TraceFormat::Binary),
+            _ => None,
+        }
+    }
+}
+
+// Which instructions end up in the trace. Empty means no restriction.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct TraceFilter {
+    pub address_range: Option<(u16, u16)>, // Inclusive.
+    pub kinds: Vec<InstructionKind>,
+}
+
+// Hexadecimal, with or without 0x.
+pub fn parse_address(text: &str) -> Result<u16, String> {
+    let text = text.trim();
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+
+    u16::from_str_radix(digits, 16).map_err(|_| format!("[Error] Invalid address '{}' !", text))
+}
+
+impl TraceFilter {
+    // "0x200-0x2FF", addresses being hexadecimal.
+    pub fn set_address_range(&mut self, text: &str) -> Result<(), String> {
+        let (start, end) = text.split_once('-').ok_or_else(|| {
+            format!(
+                "[Error] Invalid address range '{}', expected START-END !",
+                text
+            )
+        })?;
+
+        self.address_range = Some((parse_address(start)?, parse_address(end)?));
+        Ok(())
+    }
+
+    // Comma separated kinds : "display,flow".
+    pub fn set_kinds(&mut self, text: &str) -> Result<(), String> {
+        self.kinds = text
+            .split(',')
+            .map(|name| {
+                InstructionKind::from_name(name.trim())
+                    .ok_or_else(|| format!("[Error] Unknown instruction kind '{}' !", name.trim()))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(())
+    }
+
+    pub fn accepts(&self, program_counter: u16, opcode: u16) -> bool {
+        if let Some((start, end)) = self.address_range {
+            if program_counter < start || program_counter > end {
+                return false;
+            }
+        }
+
+        self.kinds.is_empty() || self.kinds.contains(&decode(opcode).kind)
+    }
+}
+
+// State of the machine right before an instruction is executed.
+pub struct TraceRecord {
+    pub cycle: u64,
+    pub program_counter: u16,
+    pub opcode: u16,
+    pub registers: [u8; 16],
+    pub index_register: u16,
+    pub stack_pointer: u8,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+}
+
+impl TraceRecord {
+    fn write_text(&self, output: &mut impl Write) -> std::io::Result<()> {
+        let registers: Vec<String> = self
+            .registers
+            .iter()
+            .map(|register| format!("{:02X}", register))
+            .collect();
+
+        writeln!(
+            output,
+            "cycle={} pc={:#05X} opcode={:04X} asm=\"{}\" v={} i={:#05X} sp={} dt={} st={}",
+            self.cycle,
+            self.program_counter,
+            self.opcode,
+            decode(self.opcode).mnemonic,
+            registers.join(","),
+            self.index_register,
+            self.stack_pointer,
+            self.delay_timer,
+            self.sound_timer
+        )
+    }
+
+    // 33 bytes, little endian : cycle (8), PC (2), opcode (2), V0-VF (16),
+    // I (2), SP, DT and ST (1 each).
+    fn write_binary(&self, output: &mut impl Write) -> std::io::Result<()> {
+        output.write_all(&self.cycle.to_le_bytes())?;
+        output.write_all(&self.program_counter.to_le_bytes())?;
+        output.write_all(&self.opcode.to_le_bytes())?;
+        output.write_all(&self.registers)?;
+        output.write_all(&self.index_register.to_le_bytes())?;
+        output.write_all(&[self.stack_pointer, self.delay_timer, self.sound_timer])
+    }
+}
+
+// Writes every executed instruction accepted by the filter. Tracing stops at
+// the first write error instead of failing every instruction after it.
+pub struct Tracer {
+    output: Box<dyn Write + Send>,
+    format: TraceFormat,
+    filter: TraceFilter,
+    is_failed: bool,
+}
+
+impl Tracer {
+    pub fn new(
+        mut output: Box<dyn Write + Send>,
+        format: TraceFormat,
+        filter: TraceFilter,
+    ) -> Tracer {
+        let mut is_failed = false;
+        if format == TraceFormat::Binary {
+            is_failed = output
+                .write_all(BINARY_TRACE_MAGIC)
+                .and_then(|_| output.write_all(&[BINARY_TRACE_VERSION]))
+                .is_err();
+        }
+
+        Tracer {
+            output,
+            format,
+            filter,
+            is_failed,
+        }
+    }
+
+    pub fn create(path: &str, format: TraceFormat, filter: TraceFilter) -> Result<Tracer, String> {
+        let file = File::create(path)
+            .map_err(|_| format!("[Error] Unable to create the trace file '{}' !", path))?;
+
+        Ok(Tracer::new(Box::new(BufWriter::new(file)), format, filter))
+    }
+
+    pub fn accepts(&self, program_counter: u16, opcode: u16) -> bool {
+        !self.is_failed && self.filter.accepts(program_counter, opcode)
+    }
+
+    pub fn record(&mut self, record: &TraceRecord) {
+        let result = match self.format {
+            TraceFormat::Text => record.write_text(&mut self.output),
+            TraceFormat::Binary => record.write_binary(&mut self.output),
+        };
+
+        if let Err(err) = result {
+            log_error!("Unable to write the trace, tracing stopped : {}", err);
+            self.is_failed = true;
+        }
+    }
+}
+
+impl Drop for Tracer {
+    fn drop(&mut self) {
+        let _ = self.output.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,54 @@
+use super::*;
+
+#[test]
+fn addresses() {
+    for (text, expected) in [
+        ("200", 0x200),
+        ("0x200", 0x200),
+        ("0X2fF", 0x2FF),
+        (" 0xEA0 ", 0xEA0),
+        ("0", 0x0),
+        ("0xFFFF", 0xFFFF),
+    ] {
+        assert_eq!(parse_address(text), Ok(expected), "{}", text);
+    }
+
+    for text in ["", "0x", "0x10000", "-1", "0x2G0", "x200", "0x 200"] {
+        assert_eq!(
+            parse_address(text),
+            Err(format!("[Error] Invalid address '{}' !", text.trim())),
+            "{}",
+            text
+        );
+    }
+}
+
+#[test]
+fn filter_by_address_range() {
+    let mut filter = TraceFilter::default();
+    assert!(filter.accepts(0x000, 0x00E0));
+
+    filter.set_address_range("0x200-0x2FF").unwrap();
+    assert_eq!(filter.address_range, Some((0x200, 0x2FF)));
+    assert!(filter.accepts(0x200, 0x00E0));
+    assert!(filter.accepts(0x2FF, 0x00E0));
+    assert!(!filter.accepts(0x300, 0x00E0));
+    assert!(!filter.accepts(0x1FE, 0x00E0));
+
+    assert!(filter.set_address_range("0x200").is_err());
+    assert!(filter.set_address_range("0x200-end").is_err());
+}
+
+#[test]
+fn filter_by_kind() {
+    let mut filter = TraceFilter::default();
+    filter.set_kinds("display, flow").unwrap();
+    assert!(filter.accepts(0x200, 0xD125));
+    assert!(filter.accepts(0x200, 0x1200));
+    assert!(!filter.accepts(0x200, 0x6012));
+
+    assert_eq!(
+        filter.set_kinds("display,jump"),
+        Err("[Error] Unknown instruction kind 'jump' !".to_string())
+    );
+}
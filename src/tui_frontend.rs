@@ -0,0 +1,234 @@
+extern crate termion;
+
+use crate::cheats::Cheats;
+use crate::config::Config;
+use crate::flicker_filter::FlickerFilter;
+use crate::frontend::{keypad_key_from_char, Frontend, FrontendEvent, Hotkey};
+use crate::globals::*;
+use crate::log_error;
+use crate::palette::Palette;
+use crate::virtual_machine::VirtualMachine;
+use std::io::{self, Read, Stdout, Write};
+use std::time::{Duration, Instant};
+use termion::color::{Bg, Fg, Rgb};
+use termion::event::{Event, Key};
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::screen::{AlternateScreen, IntoAlternateScreen};
+use termion::AsyncReader;
+
+// Terminals only report key presses, so a key is considered released once no
+// press (or auto-repeat) has been seen for this long. Auto-repeat usually
+// only starts after 500 ms, a held key must survive that first gap.
+const KEY_RELEASE_DELAY: Duration = Duration::from_millis(600);
+const ESCAPE_KEY: u8 = 0x1B;
+
+// When each keypad key was last pressed, the caller giving the time.
+#[derive(Default)]
+struct HeldKeys {
+    last_seen: [Option<Instant>; 16],
+}
+
+impl HeldKeys {
+    fn press(&mut self, key: u8, now: Instant) {
+        self.last_seen[key as usize] = Some(now);
+    }
+
+    // The keys without any press for longer than KEY_RELEASE_DELAY, each one
+    // reported once.
+    fn release_expired(&mut self, now: Instant) -> Vec<u8> {
+        let mut released_keys = Vec::new();
+
+        for (key, last_seen) in self.last_seen.iter_mut().enumerate() {
+            if let Some(instant) = last_seen {
+                if now.duration_since(*instant) > KEY_RELEASE_DELAY {
+                    *last_seen = None;
+                    released_keys.push(key as u8);
+                }
+            }
+        }
+
+        released_keys
+    }
+}
+
+pub struct TuiFrontend {
+    stdout: AlternateScreen<RawTerminal<Stdout>>,
+    stdin: AsyncReader,
+    held_keys: HeldKeys,
+    palette: Palette,
+    flicker_filter: FlickerFilter,
+    is_silent: bool,
+    is_audio_active: bool,
+}
+
+// Two vertically stacked CHIP-8 pixels are drawn in a single terminal cell.
+fn half_block(top: bool, bottom: bool) -> char {
+    match (top, bottom) {
+        (true, true) => '█',
+        (true, false) => '▀',
+        (false, true) => '▄',
+        (false, false) => ' ',
+    }
+}
+
+// Arrows, function keys and Alt+key arrive as escape sequences, only an escape
+// byte ending the input is the Esc key itself.
+fn parse_keys(input: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut bytes = input.iter().map(|byte| Ok(*byte)).peekable();
+
+    while let Some(Ok(byte)) = bytes.next() {
+        if byte == ESCAPE_KEY && bytes.peek().is_none() {
+            keys.push(Key::Esc);
+        } else if let Ok(Event::Key(key)) = termion::event::parse_event(byte, &mut bytes) {
+            keys.push(key);
+        }
+    }
+
+    keys
+}
+
+impl TuiFrontend {
+    pub fn new(config: &Config) -> io::Result<TuiFrontend> {
+        let mut stdout = io::stdout().into_raw_mode()?.into_alternate_screen()?;
+
+        write!(stdout, "{}{}", termion::clear::All, termion::cursor::Hide)?;
+
+        Ok(TuiFrontend {
+            stdout,
+            stdin: termion::async_stdin(),
+            held_keys: HeldKeys::default(),
+            palette: config.palette(),
+            flicker_filter: FlickerFilter::new(config.flicker_mode, config.flicker_decay),
+            is_silent: config.is_silent,
+            is_audio_active: false,
+        })
+    }
+
+    fn write_output(&mut self, output: &str) {
+        let result = self
+            .stdout
+            .write_all(output.as_bytes())
+            .and_then(|_| self.stdout.flush());
+        if let Err(err) = result {
+            log_error!("Unable to write to the terminal : {}", err);
+        }
+    }
+
+    fn draw_status(&mut self) {
+        let status = if self.is_audio_active && self.is_silent {
+            "[BEEP] Esc to quit, P to pause, N to step, [ ] for speed, Backspace to reset"
+        } else {
+            "       Esc to quit, P to pause, N to step, [ ] for speed, Backspace to reset"
+        };
+
+        let output = format!(
+            "{}{}{}",
+            termion::cursor::Goto(1, CHIP8_SCREEN_HEIGHT as u16 / 2 + 1),
+            termion::clear::CurrentLine,
+            status
+        );
+        self.write_output(&output);
+    }
+}
+
+impl Drop for TuiFrontend {
+    fn drop(&mut self) {
+        let _ = write!(
+            self.stdout,
+            "{}{}",
+            termion::style::Reset,
+            termion::cursor::Show
+        );
+        let _ = self.stdout.flush();
+    }
+}
+
+impl Frontend for TuiFrontend {
+    fn present_frame(&mut self, virtual_machine: &VirtualMachine) {
+        self.flicker_filter.refresh(virtual_machine);
+
+        let foreground = self.palette.foreground();
+        let background = self.palette.background();
+        let mut frame = format!(
+            "{}{}",
+            Fg(Rgb(foreground.r, foreground.g, foreground.b)),
+            Bg(Rgb(background.r, background.g, background.b))
+        );
+
+        for row in 0..(self.flicker_filter.height() / 2) {
+            frame.push_str(&format!("{}", termion::cursor::Goto(1, row as u16 + 1)));
+            for x in 0..self.flicker_filter.width() {
+                // Cells can only be on or off, half lit pixels are rounded.
+                let top = self.flicker_filter.intensity(x, row * 2) >= 128;
+                let bottom = self.flicker_filter.intensity(x, row * 2 + 1) >= 128;
+                frame.push(half_block(top, bottom));
+            }
+        }
+
+        self.write_output(&frame);
+        self.draw_status();
+    }
+
+    fn poll_input(&mut self) -> Vec<FrontendEvent> {
+        let mut events = Vec::new();
+        let now = Instant::now();
+
+        let mut input_buffer = [0u8; 64];
+        let read_bytes = self.stdin.read(&mut input_buffer).unwrap_or(0);
+        for key in parse_keys(&input_buffer[..read_bytes]) {
+            match key {
+                Key::Esc | Key::Ctrl('c') => events.push(FrontendEvent::Quit),
+                Key::Backspace => events.push(FrontendEvent::Hotkey(Hotkey::Reset)),
+                Key::Char('p') | Key::Char('P') => {
+                    events.push(FrontendEvent::Hotkey(Hotkey::TogglePause))
+                }
+                Key::Char('n') | Key::Char('N') => {
+                    events.push(FrontendEvent::Hotkey(Hotkey::FrameAdvance))
+                }
+                Key::Char('[') => events.push(FrontendEvent::Hotkey(Hotkey::SlowDown)),
+                Key::Char(']') => events.push(FrontendEvent::Hotkey(Hotkey::SpeedUp)),
+                Key::Char(character) => {
+                    if let Some(key) = keypad_key_from_char(character) {
+                        self.held_keys.press(key, now);
+                        events.push(FrontendEvent::KeyDown(key));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for key in self.held_keys.release_expired(now) {
+            events.push(FrontendEvent::KeyUp(key));
+        }
+
+        events
+    }
+
+    fn set_audio(&mut self, is_active: bool) {
+        self.is_audio_active = is_active;
+
+        if is_active && !self.is_silent {
+            self.write_output("\x07");
+        }
+        self.draw_status();
+    }
+
+    fn frame_finished(&mut self, virtual_machine: &VirtualMachine) {
+        if self.flicker_filter.needs_every_frame() {
+            self.flicker_filter.update(virtual_machine);
+        }
+    }
+
+    fn needs_continuous_repaint(&self) -> bool {
+        self.flicker_filter.needs_every_frame()
+    }
+
+    // The cheats enabled in the file apply, they can only be toggled from mch8.
+    fn read_cheats(&mut self, rom: &[u8]) -> Result<Cheats, String> {
+        Cheats::load_for_rom(rom)
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,67 @@
+use super::*;
+
+#[test]
+fn lone_escape_is_the_escape_key() {
+    assert_eq!(parse_keys(b"\x1B"), vec![Key::Esc]);
+    assert_eq!(parse_keys(b"1\x1B"), vec![Key::Char('1'), Key::Esc]);
+}
+
+#[test]
+fn escape_sequences_are_not_the_escape_key() {
+    assert_eq!(parse_keys(b"\x1B[A"), vec![Key::Up]);
+    assert_eq!(parse_keys(b"\x1B[D"), vec![Key::Left]);
+    assert_eq!(parse_keys(b"\x1Bq"), vec![Key::Alt('q')]);
+    assert_eq!(parse_keys(b"\x1BOP"), vec![Key::F(1)]);
+    assert_eq!(
+        parse_keys(b"\x1B[B4\x1Bx"),
+        vec![Key::Down, Key::Char('4'), Key::Alt('x')]
+    );
+}
+
+#[test]
+fn control_keys_are_parsed() {
+    assert_eq!(
+        parse_keys(b"\x03\x7Fp]"),
+        vec![
+            Key::Ctrl('c'),
+            Key::Backspace,
+            Key::Char('p'),
+            Key::Char(']')
+        ]
+    );
+}
+
+#[test]
+fn held_key_survives_auto_repeat_gaps() {
+    let start = Instant::now();
+    let at = |milliseconds: u64| start + Duration::from_millis(milliseconds);
+    let mut held_keys = HeldKeys::default();
+
+    // The first repeat comes 500 ms after the press, then every 33 ms.
+    held_keys.press(0x5, at(0));
+    assert!(held_keys.release_expired(at(499)).is_empty());
+    held_keys.press(0x5, at(500));
+    assert!(held_keys.release_expired(at(520)).is_empty());
+    held_keys.press(0x5, at(533));
+    assert!(held_keys.release_expired(at(1100)).is_empty());
+
+    // Released once, the delay after the last repeat.
+    assert_eq!(held_keys.release_expired(at(1134)), [0x5]);
+    assert!(held_keys.release_expired(at(5000)).is_empty());
+}
+
+#[test]
+fn keys_are_released_independently() {
+    let start = Instant::now();
+    let at = |milliseconds: u64| start + Duration::from_millis(milliseconds);
+    let mut held_keys = HeldKeys::default();
+
+    held_keys.press(0x1, at(0));
+    held_keys.press(0xF, at(300));
+    assert_eq!(held_keys.release_expired(at(700)), [0x1]);
+    assert_eq!(held_keys.release_expired(at(901)), [0xF]);
+
+    held_keys.press(0x1, at(1000));
+    held_keys.press(0x2, at(1000));
+    assert_eq!(held_keys.release_expired(at(1601)), [0x1, 0x2]);
+}
@@ -0,0 +1,71 @@
+// Where the screen is drawn inside a window of any size.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum ScalingMode {
+    #[default]
+    Integer, // Largest whole multiple of the screen size, so every pixel has the same size.
+    Fit,     // As large as possible while keeping the aspect ratio.
+    Stretch, // Fills the whole window.
+}
+
+impl ScalingMode {
+    pub fn from_name(name: &str) -> Option<ScalingMode> {
+        match name.to_ascii_lowercase().as_str() {
+            "integer" => Some(ScalingMode::Integer),
+            "fit" => Some(ScalingMode::Fit),
+            "stretch" => Some(ScalingMode::Stretch),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Viewport {
+    // Centres a `content_width` x `content_height` image in the window, the rest
+    // of the window being left for black bars (letterboxing).
+    pub fn fit(
+        window_width: u32,
+        window_height: u32,
+        content_width: u32,
+        content_height: u32,
+        scaling_mode: ScalingMode,
+    ) -> Viewport {
+        let (width, height) = match scaling_mode {
+            ScalingMode::Integer => {
+                let scale = (window_width / content_width)
+                    .min(window_height / content_height)
+                    .max(1);
+                (content_width * scale, content_height * scale)
+            }
+            ScalingMode::Fit => {
+                // Compare window_width / window_height with content_width / content_height.
+                if window_width as u64 * content_height as u64
+                    > window_height as u64 * content_width as u64
+                {
+                    let width = window_height as u64 * content_width as u64 / content_height as u64;
+                    (width as u32, window_height)
+                } else {
+                    let height = window_width as u64 * content_height as u64 / content_width as u64;
+                    (window_width, height as u32)
+                }
+            }
+            ScalingMode::Stretch => (window_width, window_height),
+        };
+
+        Viewport {
+            x: (window_width as i32 - width as i32) / 2,
+            y: (window_height as i32 - height as i32) / 2,
+            width,
+            height,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
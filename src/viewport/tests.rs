@@ -0,0 +1,92 @@
+use super::*;
+
+// The CHIP-8 screen.
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 32;
+
+fn viewport(x: i32, y: i32, width: u32, height: u32) -> Viewport {
+    Viewport {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+#[test]
+fn integer_scaling() {
+    for (window_width, window_height, expected) in [
+        (640, 320, viewport(0, 0, 640, 320)),
+        // Not a multiple of the screen size, the bars take what is left.
+        (700, 400, viewport(30, 40, 640, 320)),
+        (1000, 320, viewport(180, 0, 640, 320)),
+        (127, 63, viewport(31, 15, 64, 32)),
+        // Smaller than the screen, which still gets drawn at its real size
+        // and overflows the window on every side.
+        (40, 20, viewport(-12, -6, 64, 32)),
+        (0, 0, viewport(-32, -16, 64, 32)),
+    ] {
+        assert_eq!(
+            Viewport::fit(
+                window_width,
+                window_height,
+                WIDTH,
+                HEIGHT,
+                ScalingMode::Integer
+            ),
+            expected,
+            "{}x{}",
+            window_width,
+            window_height
+        );
+    }
+}
+
+#[test]
+fn fit_scaling_keeps_the_aspect_ratio() {
+    for (window_width, window_height, expected) in [
+        (640, 320, viewport(0, 0, 640, 320)),
+        // Wider than the screen : bars left and right.
+        (1000, 300, viewport(200, 0, 600, 300)),
+        // Taller than the screen : bars at the top and bottom.
+        (300, 1000, viewport(0, 425, 300, 150)),
+        // Rounded down when the scale is not whole.
+        (100, 1000, viewport(0, 475, 100, 50)),
+        (101, 1000, viewport(0, 475, 101, 50)),
+        (40, 20, viewport(0, 0, 40, 20)),
+    ] {
+        assert_eq!(
+            Viewport::fit(window_width, window_height, WIDTH, HEIGHT, ScalingMode::Fit),
+            expected,
+            "{}x{}",
+            window_width,
+            window_height
+        );
+    }
+}
+
+#[test]
+fn stretch_scaling_fills_the_window() {
+    assert_eq!(
+        Viewport::fit(1000, 300, WIDTH, HEIGHT, ScalingMode::Stretch),
+        viewport(0, 0, 1000, 300)
+    );
+    assert_eq!(
+        Viewport::fit(10, 10, WIDTH, HEIGHT, ScalingMode::Stretch),
+        viewport(0, 0, 10, 10)
+    );
+}
+
+#[test]
+fn scaling_mode_names() {
+    assert_eq!(ScalingMode::from_name("Fit"), Some(ScalingMode::Fit));
+    assert_eq!(
+        ScalingMode::from_name("integer"),
+        Some(ScalingMode::Integer)
+    );
+    assert_eq!(
+        ScalingMode::from_name("stretch"),
+        Some(ScalingMode::Stretch)
+    );
+    assert_eq!(ScalingMode::from_name("zoom"), None);
+}
@@ -1,37 +1,37 @@
-use crate::audio_system::AudioSystem;
+use crate::cheats::{Cheat, Cheats};
+use crate::coverage::Coverage;
+use crate::fault::Fault;
+use crate::fontset::{Fontset, MAX_FONT_ADDRESS};
 use crate::globals::*;
-use crate::memory::Memory;
+use crate::log_info;
+use crate::log_warning;
+use crate::memory::{FlaggedWrite, Memory, MemoryPolicy, MemoryProtection};
+use crate::observer::{ObserverId, SharedObserver};
+use crate::profiler::Profiler;
+use crate::quirks::Quirks;
 use crate::render_table::RenderTable;
-use crate::virtual_processor::VirtualProcessor;
+use crate::save_state::{StateReader, StateWriter};
+use crate::tracer::{TraceRecord, Tracer};
+use crate::virtual_processor::{StackDepth, VirtualProcessor};
 use std::fs::File;
 use std::io::{BufReader, Read};
 
 const MAX_ROM_PROGRAM_SIZE: u16 = 0xFFF - PROCESSOR_INTERNAL_PROGRAM_COUNTER_START;
-const FONTSET_SIZE: u8 = 80;
-const FONTSET: [u8; FONTSET_SIZE as usize] = [
-    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-    0x20, 0x60, 0x20, 0x20, 0x70, // 1
-    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-];
 
 pub struct VirtualMachine {
     virtual_processor: VirtualProcessor,
     memory: Memory,
     render_table: RenderTable,
-    audio_system: AudioSystem,
+    rom: Vec<u8>,
+    executed_instructions: u64,
+    tracer: Option<Tracer>,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    cheats: Cheats,
+    fontset: Fontset,
+    font_address: u16,
+    frame_position: u16,  // Instructions already run in the current frame.
+    fault: Option<Fault>, // No instruction runs until it is cleared.
 }
 
 impl Default for VirtualMachine {
@@ -42,22 +42,46 @@ impl Default for VirtualMachine {
 
 impl VirtualMachine {
     pub fn new() -> VirtualMachine {
+        Self::with_seed(DEFAULT_RANDOM_SEED)
+    }
+
+    pub fn with_seed(seed: u64) -> VirtualMachine {
         VirtualMachine {
-            virtual_processor: VirtualProcessor::new(),
+            virtual_processor: VirtualProcessor::with_seed(seed),
             memory: Memory::new(),
             render_table: RenderTable::new(),
-            audio_system: AudioSystem::new(),
+            rom: Vec::new(),
+            executed_instructions: 0,
+            tracer: None,
+            profiler: None,
+            coverage: None,
+            cheats: Cheats::new(),
+            fontset: Fontset::default(),
+            font_address: 0x0,
+            frame_position: 0,
+            fault: None,
         }
     }
 
-    pub fn init_audio(&mut self) {
-        self.audio_system.init();
+    // FX29 and FX30 only follow a new font address once the font is written there.
+    fn load_fontset(&mut self) {
+        self.virtual_processor.set_font_address(self.font_address);
+        for (offset, byte) in self.fontset.bytes().enumerate() {
+            self.memory.write(self.font_address + offset as u16, byte);
+        }
     }
 
-    fn load_fontset(&mut self) {
-        for i in 0..FONTSET_SIZE {
-            self.memory.write(i as u16, FONTSET[i as usize]);
+    fn load_program(&mut self) {
+        self.load_fontset(); // Load fontset into memory before anything else.
+        self.memory.set_program_length(self.rom.len() as u16);
+
+        for (byte_index, byte_value) in self.rom.iter().enumerate() {
+            self.memory.write(
+                PROCESSOR_INTERNAL_PROGRAM_COUNTER_START + byte_index as u16,
+                *byte_value,
+            );
         }
+        self.cheats.apply(&mut self.memory);
     }
 
     pub fn load_rom(&mut self, rom_path: &str) -> Result<(), &str> {
@@ -75,23 +99,122 @@ impl VirtualMachine {
             return Err("[Error] Unable to read the given ROM file !");
         }
 
-        let rom_buffer_len = rom_buffer.len();
+        self.load_rom_bytes(&rom_buffer)
+    }
 
-        if rom_buffer_len > MAX_ROM_PROGRAM_SIZE as usize {
+    pub fn load_rom_bytes(&mut self, rom_buffer: &[u8]) -> Result<(), &'static str> {
+        if rom_buffer.len() > MAX_ROM_PROGRAM_SIZE as usize {
             return Err("[Error] The ROM file given is too big to fit into memory !");
         }
 
-        self.load_fontset(); // Load fontset into memory before anything else.
+        // The cheats belong to the previous ROM.
+        self.rom = rom_buffer.to_vec();
+        self.cheats = Cheats::new();
+        self.load_program();
 
-        // TEST WARN
-        for (byte_index, byte_value) in rom_buffer.iter().enumerate() {
-            self.memory.write(
-                PROCESSOR_INTERNAL_PROGRAM_COUNTER_START + byte_index as u16,
-                *byte_value,
-            );
+        log_info!("ROM successfully loaded into memory !");
+
+        Ok(())
+    }
+
+    // Restarts the loaded ROM from scratch, as if the machine was power cycled.
+    pub fn reset(&mut self) {
+        self.virtual_processor.reset();
+        self.memory = Memory::new();
+        self.render_table.clear();
+        self.load_program();
+        self.frame_position = 0;
+        self.fault = None;
+
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.reset_call_stack();
         }
+    }
 
-        println!("[Info] ROM successfully loaded into memory !");
+    // Both take effect on the next reset or loaded ROM, and are not part of
+    // the save states.
+    pub fn set_fontset(&mut self, fontset: Fontset) {
+        self.fontset = fontset;
+    }
+
+    pub fn set_font_address(&mut self, font_address: u16) -> Result<(), &'static str> {
+        if font_address > MAX_FONT_ADDRESS {
+            return Err("[Error] The font does not fit below 0x200 at this address !");
+        }
+
+        self.font_address = font_address;
+        Ok(())
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.virtual_processor.set_quirks(quirks);
+    }
+
+    pub fn set_stack_depth(&mut self, stack_depth: StackDepth) {
+        self.virtual_processor.set_stack_depth(stack_depth);
+    }
+
+    pub fn set_stack_in_memory(&mut self, is_stack_in_memory: bool) {
+        self.virtual_processor
+            .set_stack_in_memory(is_stack_in_memory);
+    }
+
+    pub fn set_memory_policy(&mut self, memory_policy: MemoryPolicy) {
+        self.virtual_processor.set_memory_policy(memory_policy);
+    }
+
+    pub fn set_memory_protection(&mut self, memory_protection: MemoryProtection) {
+        self.virtual_processor
+            .set_memory_protection(memory_protection);
+    }
+
+    // A write the memory protection stopped the machine on, the frame is left
+    // unfinished until the machine runs again.
+    pub fn memory_break(&self) -> Option<FlaggedWrite> {
+        self.virtual_processor.memory_break()
+    }
+
+    pub fn take_memory_break(&mut self) -> Option<FlaggedWrite> {
+        self.virtual_processor.take_memory_break()
+    }
+
+    // Why the machine stopped, if the ROM did something it cannot recover from.
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = StateWriter::new();
+
+        self.virtual_processor.save_state(&mut writer);
+        self.memory.save_state(&mut writer);
+        self.render_table.save_state(&mut writer);
+
+        writer.into_bytes()
+    }
+
+    // The machine is left untouched when the state cannot be loaded.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), &'static str> {
+        let mut reader = StateReader::new(state)?;
+
+        let mut virtual_processor = self.virtual_processor.clone();
+        let mut memory = Memory::new();
+        let mut render_table = RenderTable::new();
+
+        virtual_processor.load_state(&mut reader)?;
+        memory.load_state(&mut reader)?;
+        memory.set_program_length(self.rom.len() as u16);
+        render_table.load_state(&mut reader)?;
+
+        self.virtual_processor = virtual_processor;
+        self.memory = memory;
+        self.render_table = render_table;
+        self.fault = None;
+        self.cheats.apply(&mut self.memory);
+
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.reset_call_stack();
+        }
 
         Ok(())
     }
@@ -108,20 +231,204 @@ impl VirtualMachine {
         self.virtual_processor.reset_draw_flag();
     }
 
+    pub fn screen_width(&self) -> u8 {
+        self.render_table.width()
+    }
+
+    pub fn screen_height(&self) -> u8 {
+        self.render_table.height()
+    }
+
+    // For debuggers, reading and changing the machine between instructions.
+    pub fn virtual_processor(&self) -> &VirtualProcessor {
+        &self.virtual_processor
+    }
+
+    pub fn virtual_processor_mut(&mut self) -> &mut VirtualProcessor {
+        &mut self.virtual_processor
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
     pub fn is_pixel_switched_on(&self, x: u8, y: u8) -> bool {
         self.render_table.is_pixel_switched_on(x, y)
     }
 
+    // Since the machine was created, for speed counters.
+    pub fn executed_instructions(&self) -> u64 {
+        self.executed_instructions
+    }
+
+    // Tracing is off until a tracer is given, None turns it off again.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        self.tracer = tracer;
+    }
+
+    // Profiling is off until a profiler is given, it can then be read back to
+    // produce the reports.
+    pub fn set_profiler(&mut self, profiler: Option<Profiler>) {
+        self.profiler = profiler;
+    }
+
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    // Applied right away, then at the end of every frame.
+    pub fn set_cheats(&mut self, cheats: Cheats) {
+        self.cheats = cheats;
+        self.cheats.apply(&mut self.memory);
+    }
+
+    pub fn cheats(&self) -> &Cheats {
+        &self.cheats
+    }
+
+    pub fn add_cheat(&mut self, cheat: Cheat) {
+        self.cheats.add(cheat);
+        self.cheats.apply(&mut self.memory);
+    }
+
+    pub fn toggle_cheat(&mut self, index: usize) -> Option<&Cheat> {
+        self.cheats.toggle(index)?;
+        self.cheats.apply(&mut self.memory);
+        self.cheats.iter().nth(index)
+    }
+
+    // Tools watching the ROM run without a hook of their own in the machine,
+    // see observer::MachineEvent.
+    pub fn subscribe(&mut self, observer: SharedObserver) -> ObserverId {
+        self.virtual_processor.subscribe(observer)
+    }
+
+    pub fn unsubscribe(&mut self, id: ObserverId) -> bool {
+        self.virtual_processor.unsubscribe(id)
+    }
+
+    // Same as the profiler, the coverage keeps adding up across resets.
+    pub fn set_coverage(&mut self, coverage: Option<Coverage>) {
+        self.coverage = coverage;
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn trace_instruction(&mut self, opcode: u16) {
+        let Some(tracer) = self.tracer.as_mut() else {
+            return;
+        };
+
+        let processor = &self.virtual_processor;
+        if tracer.accepts(processor.program_counter(), opcode) {
+            tracer.record(&TraceRecord {
+                cycle: self.executed_instructions,
+                program_counter: processor.program_counter(),
+                opcode,
+                registers: *processor.registers(),
+                index_register: processor.index_register(),
+                stack_pointer: processor.stack_pointer(),
+                delay_timer: processor.delay_timer(),
+                sound_timer: processor.sound_timer(),
+            });
+        }
+    }
+
     pub fn execute_processor_instruction(&mut self) {
-        let opcode = self.virtual_processor.fetch_next_opcode(&self.memory);
-        self.virtual_processor.execute_instruction(
+        if self.fault.is_some() {
+            return;
+        }
+
+        let opcode = match self.virtual_processor.fetch_next_opcode(&self.memory) {
+            Ok(opcode) => opcode,
+            Err(fault) => {
+                self.raise_fault(fault);
+                return;
+            }
+        };
+        self.memory
+            .mark_executed(self.virtual_processor.program_counter());
+        self.trace_instruction(opcode);
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.record_instruction(
+                self.virtual_processor.program_counter(),
+                opcode,
+                self.virtual_processor.delay_timer(),
+            );
+        }
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.record_instruction(
+                self.virtual_processor.program_counter(),
+                opcode,
+                self.virtual_processor.index_register(),
+            );
+        }
+        self.executed_instructions += 1;
+
+        if let Err(fault) = self.virtual_processor.execute_instruction(
             opcode,
             &mut self.memory,
             &mut self.render_table,
-        );
+        ) {
+            self.raise_fault(fault);
+        }
+    }
+
+    fn raise_fault(&mut self, fault: Fault) {
+        log_warning!("{}", fault);
+        self.fault = Some(fault);
+    }
+
+    pub fn is_sound_active(&self) -> bool {
+        self.virtual_processor.is_sound_active()
     }
 
     pub fn update_processor_timers(&mut self) {
-        self.virtual_processor.update_timers(&self.audio_system);
+        self.virtual_processor.update_timers();
+    }
+
+    // Runs one 60 Hz frame : a batch of instructions followed by a single timers tick.
+    // Used by frontends driven by the host's refresh rate instead of their own loop.
+    // Ends early when a debugger stopped the previous frame halfway through,
+    // and stops halfway on a memory break.
+    pub fn run_frame(&mut self, instructions_per_frame: u16) {
+        while !self.step_instruction(instructions_per_frame) {
+            if self.memory_break().is_some() {
+                return;
+            }
+        }
+    }
+
+    // Runs a single instruction, and the timers tick once the frame's batch is
+    // done. Returns true when the instruction was the last of its frame.
+    pub fn step_instruction(&mut self, instructions_per_frame: u16) -> bool {
+        if instructions_per_frame > 0 {
+            self.execute_processor_instruction();
+            self.frame_position += 1;
+        }
+
+        if self.frame_position < instructions_per_frame {
+            return false;
+        }
+
+        self.frame_position = 0;
+        self.update_processor_timers();
+        self.cheats.apply(&mut self.memory);
+
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.end_frame();
+        }
+
+        true
     }
 }
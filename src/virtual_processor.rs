@@ -1,14 +1,63 @@
-use crate::audio_system::AudioSystem;
+use crate::fault::Fault;
+use crate::fontset::{BIG_FONT_BYTES, SMALL_FONT_BYTES};
 use crate::globals::*;
-use crate::memory::Memory;
+use crate::log_warning;
+use crate::memory::{FlaggedWrite, Memory, MemoryPolicy, MemoryProtection, TOTAL_MEMORY_BYTES};
+use crate::observer::{MachineEvent, ObserverId, Observers, SharedObserver, Timer};
+use crate::quirks::Quirks;
 use crate::render_table::PixelState;
 use crate::render_table::RenderTable;
+use crate::save_state::{StateReader, StateWriter};
 
-use rand::{rngs, Rng};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
 
 const REGISTERS_COUNT: u8 = 16;
 const KEYS_COUNT: u8 = 16;
+// Where the COSMAC VIP interpreter kept its stack, room for 24 return addresses.
+const STACK_MEMORY_START: u16 = 0xEA0;
+const STACK_MEMORY_LEVELS: usize = 24;
+// As many return addresses as a save state can hold.
+const UNLIMITED_STACK_LEVELS: usize = u16::MAX as usize;
+
+// How many return addresses fit on the stack : 12 on the COSMAC VIP, 16 on
+// the SCHIP. Unlimited is only meant to debug runaway recursion, it still
+// stops at 65535 levels.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StackDepth {
+    Limited(u8),
+    Unlimited,
+}
+
+impl Default for StackDepth {
+    fn default() -> Self {
+        StackDepth::Limited(16)
+    }
+}
 
+impl StackDepth {
+    // "vip", "schip", "unlimited" or a number of levels.
+    pub fn from_name(name: &str) -> Option<StackDepth> {
+        match name.to_ascii_lowercase().as_str() {
+            "vip" => Some(StackDepth::Limited(12)),
+            "schip" => Some(StackDepth::Limited(16)),
+            "unlimited" => Some(StackDepth::Unlimited),
+            levels => levels
+                .parse::<u8>()
+                .ok()
+                .filter(|levels| *levels > 0)
+                .map(StackDepth::Limited),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            StackDepth::Limited(levels) => levels.to_string(),
+            StackDepth::Unlimited => "unlimited".to_string(),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct VirtualProcessor {
     registers: [u8; REGISTERS_COUNT as usize],
     keys: [bool; KEYS_COUNT as usize],
@@ -18,7 +67,16 @@ pub struct VirtualProcessor {
     sound_timer: u8,
     program_counter: u16,
     draw_flag: bool,
-    rng: rngs::ThreadRng,
+    rng: SmallRng,
+    quirks: Quirks,
+    stack_depth: StackDepth,
+    is_stack_in_memory: bool, // Return addresses are also written to memory, as on the VIP.
+    memory_policy: MemoryPolicy,
+    has_warned_out_of_range: bool,
+    memory_protection: MemoryProtection,
+    memory_break: Option<FlaggedWrite>, // Not taken by the machine yet.
+    observers: Observers,
+    font_address: u16, // Where the small font starts, the big one following it.
 }
 
 impl Default for VirtualProcessor {
@@ -29,6 +87,12 @@ impl Default for VirtualProcessor {
 
 impl VirtualProcessor {
     pub fn new() -> VirtualProcessor {
+        Self::with_seed(DEFAULT_RANDOM_SEED)
+    }
+
+    // The seed is provided by the frontend so the core stays free of any
+    // platform entropy source (there is none on wasm32-unknown-unknown).
+    pub fn with_seed(seed: u64) -> VirtualProcessor {
         VirtualProcessor {
             registers: [0x0; REGISTERS_COUNT as usize],
             keys: [false; KEYS_COUNT as usize],
@@ -38,14 +102,208 @@ impl VirtualProcessor {
             sound_timer: 0x0,
             program_counter: PROCESSOR_INTERNAL_PROGRAM_COUNTER_START,
             draw_flag: false,
-            rng: rand::thread_rng(),
+            rng: SmallRng::seed_from_u64(seed),
+            quirks: Quirks::default(),
+            stack_depth: StackDepth::default(),
+            is_stack_in_memory: false,
+            memory_policy: MemoryPolicy::default(),
+            has_warned_out_of_range: false,
+            memory_protection: MemoryProtection::default(),
+            memory_break: None,
+            observers: Observers::new(),
+            font_address: 0x0,
+        }
+    }
+
+    // Back to power-on state, keeping the random generator, the quirks, the
+    // stack settings, the memory policy and protection, the observers and the
+    // font address.
+    pub fn reset(&mut self) {
+        self.registers = [0x0; REGISTERS_COUNT as usize];
+        self.keys = [false; KEYS_COUNT as usize];
+        self.stack.clear();
+        self.index_register = 0x0;
+        self.delay_timer = 0x0;
+        self.sound_timer = 0x0;
+        self.program_counter = PROCESSOR_INTERNAL_PROGRAM_COUNTER_START;
+        self.draw_flag = true;
+        self.has_warned_out_of_range = false;
+        self.memory_break = None;
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_stack_depth(&mut self, stack_depth: StackDepth) {
+        self.stack_depth = stack_depth;
+    }
+
+    pub fn stack_depth(&self) -> StackDepth {
+        self.stack_depth
+    }
+
+    // A ROM writing over 0xEA0-0xECF then corrupts its own return addresses,
+    // which some VIP programs rely on. The depth is capped to the 24 levels
+    // that fit there.
+    pub fn set_stack_in_memory(&mut self, is_stack_in_memory: bool) {
+        self.is_stack_in_memory = is_stack_in_memory;
+    }
+
+    pub fn is_stack_in_memory(&self) -> bool {
+        self.is_stack_in_memory
+    }
+
+    pub fn set_memory_policy(&mut self, memory_policy: MemoryPolicy) {
+        self.memory_policy = memory_policy;
+    }
+
+    pub fn memory_policy(&self) -> MemoryPolicy {
+        self.memory_policy
+    }
+
+    pub fn set_memory_protection(&mut self, memory_protection: MemoryProtection) {
+        self.memory_protection = memory_protection;
+    }
+
+    pub fn memory_protection(&self) -> MemoryProtection {
+        self.memory_protection
+    }
+
+    // The write the protection stopped on, with MemoryProtection::Break.
+    pub fn memory_break(&self) -> Option<FlaggedWrite> {
+        self.memory_break
+    }
+
+    pub fn take_memory_break(&mut self) -> Option<FlaggedWrite> {
+        self.memory_break.take()
+    }
+
+    // Only tells FX29 and FX30 where the fonts are, the machine writes them.
+    pub fn set_font_address(&mut self, font_address: u16) {
+        self.font_address = font_address;
+    }
+
+    pub fn font_address(&self) -> u16 {
+        self.font_address
+    }
+
+    // Observers stay subscribed across resets and loaded states.
+    pub fn subscribe(&mut self, observer: SharedObserver) -> ObserverId {
+        self.observers.subscribe(observer)
+    }
+
+    pub fn unsubscribe(&mut self, id: ObserverId) -> bool {
+        self.observers.unsubscribe(id)
+    }
+
+    fn stack_limit(&self) -> usize {
+        let limit = match self.stack_depth {
+            StackDepth::Limited(levels) => levels as usize,
+            StackDepth::Unlimited => UNLIMITED_STACK_LEVELS,
+        };
+
+        if self.is_stack_in_memory {
+            limit.min(STACK_MEMORY_LEVELS)
+        } else {
+            limit
         }
     }
 
+    // Keys are not saved : they belong to the frontend's current input.
+    pub fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.registers);
+        writer.write_u16(self.stack.len() as u16);
+        for return_address in &self.stack {
+            writer.write_u16(*return_address);
+        }
+        writer.write_u16(self.index_register);
+        writer.write_u8(self.delay_timer);
+        writer.write_u8(self.sound_timer);
+        writer.write_u16(self.program_counter);
+    }
+
+    pub fn load_state(&mut self, reader: &mut StateReader) -> Result<(), &'static str> {
+        self.registers
+            .copy_from_slice(reader.read_bytes(REGISTERS_COUNT as usize)?);
+        let stack_len = reader.read_u16()?;
+        self.stack.clear();
+        for _ in 0..stack_len {
+            self.stack.push(reader.read_u16()?);
+        }
+        self.index_register = reader.read_u16()?;
+        self.delay_timer = reader.read_u8()?;
+        self.sound_timer = reader.read_u8()?;
+        self.program_counter = reader.read_u16()?;
+        self.draw_flag = true;
+        self.memory_break = None;
+
+        Ok(())
+    }
+
     pub fn set_key(&mut self, n: u8, is_down: bool) {
         self.keys[n as usize] = is_down;
     }
 
+    pub fn program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn registers(&self) -> &[u8; REGISTERS_COUNT as usize] {
+        &self.registers
+    }
+
+    // Return addresses, the most recent call last.
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn keys(&self) -> &[bool; KEYS_COUNT as usize] {
+        &self.keys
+    }
+
+    pub fn index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    // Number of return addresses on the stack.
+    pub fn stack_pointer(&self) -> u8 {
+        self.stack.len() as u8
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    // Setters for debuggers, the ROM itself only goes through execute_instruction.
+    pub fn set_register(&mut self, index: usize, value: u8) {
+        self.registers[index] = value;
+    }
+
+    pub fn set_index_register(&mut self, value: u16) {
+        self.index_register = value;
+    }
+
+    pub fn set_program_counter(&mut self, value: u16) {
+        self.program_counter = value;
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.delay_timer = value;
+    }
+
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.sound_timer = value;
+    }
+
     pub fn get_draw_flag(&self) -> bool {
         self.draw_flag
     }
@@ -54,23 +312,133 @@ impl VirtualProcessor {
         self.draw_flag = false;
     }
 
-    pub fn update_timers(&mut self, audio_system: &AudioSystem) {
+    pub fn is_sound_active(&self) -> bool {
+        self.sound_timer > 0x0
+    }
+
+    pub fn update_timers(&mut self) {
         if self.delay_timer > 0x0 {
             self.delay_timer -= 1;
         }
         if self.sound_timer > 0x0 {
             self.sound_timer -= 1;
-            if self.sound_timer == 0x1 {
-                audio_system.play_beep_sound();
+        }
+    }
+
+    // VF is written last, so an instruction using VF as Vx keeps the flag
+    // rather than the result.
+    fn set_result_and_flag(&mut self, x: u8, result: u8, flag: bool) {
+        self.registers[x as usize] = result;
+        self.registers[0xF] = flag as u8;
+    }
+
+    fn unknown_opcode(&self, opcode: u16) -> Fault {
+        Fault::UnknownOpcode {
+            address: self.program_counter,
+            opcode,
+        }
+    }
+
+    // Where an access lands in the 4 KiB, None when the policy drops it.
+    fn resolve_address(&mut self, address: u16) -> Result<Option<u16>, Fault> {
+        if address < TOTAL_MEMORY_BYTES {
+            return Ok(Some(address));
+        }
+
+        match self.memory_policy {
+            MemoryPolicy::Wrap => Ok(Some(address % TOTAL_MEMORY_BYTES)),
+            MemoryPolicy::Fault => Err(Fault::MemoryOutOfRange {
+                address: self.program_counter,
+                target: address,
+            }),
+            MemoryPolicy::Warn => {
+                if !self.has_warned_out_of_range {
+                    log_warning!(
+                        "Memory access to {:#06X} out of range at {:#05X}, ignoring them from now on",
+                        address,
+                        self.program_counter
+                    );
+                    self.has_warned_out_of_range = true;
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    // PC stays in the 4 KiB address space, even when a ROM runs past its end
+    // or a debugger set it further.
+    fn advance_program_counter(&mut self, bytes: u16) {
+        self.program_counter = self.program_counter.wrapping_add(bytes) % TOTAL_MEMORY_BYTES;
+    }
+
+    // Faults before an instruction changes anything, rather than halfway
+    // through a range.
+    fn check_range(&mut self, start: u16, length: u16) -> Result<(), Fault> {
+        for offset in 0..length {
+            let address = start.wrapping_add(offset);
+            if address >= TOTAL_MEMORY_BYTES && self.memory_policy == MemoryPolicy::Fault {
+                self.resolve_address(address)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_memory(&mut self, memory: &Memory, address: u16) -> Result<u8, Fault> {
+        let Some(address) = self.resolve_address(address)? else {
+            return Ok(0x0);
+        };
+
+        let value = memory.read(address);
+        self.observers.notify(|| MachineEvent::MemoryRead {
+            program_counter: self.program_counter,
+            address,
+            value,
+        });
+        Ok(value)
+    }
+
+    fn write_memory(&mut self, memory: &mut Memory, address: u16, value: u8) -> Result<(), Fault> {
+        let Some(address) = self.resolve_address(address)? else {
+            return Ok(());
+        };
+
+        self.observers.notify(|| MachineEvent::MemoryWrite {
+            program_counter: self.program_counter,
+            address,
+            value,
+        });
+        if self.memory_protection == MemoryProtection::Off {
+            memory.write(address, value);
+        } else if let Some(write) = memory.write_checked(address, value, self.program_counter) {
+            match self.memory_protection {
+                // The first write of the instruction is the one reported.
+                MemoryProtection::Break => {
+                    self.memory_break.get_or_insert(write);
+                }
+                _ => log_warning!("{}", write),
             }
         }
+
+        Ok(())
     }
 
-    pub fn fetch_next_opcode(&self, memory: &Memory) -> u16 {
-        let msb = memory.read(self.program_counter) as u16;
-        let lsb = memory.read(self.program_counter + 1) as u16;
+    pub fn fetch_next_opcode(&mut self, memory: &Memory) -> Result<u16, Fault> {
+        self.check_range(self.program_counter, 2)?;
+        let mut bytes = [0x0; 2];
+        for (offset, byte) in bytes.iter_mut().enumerate() {
+            let address = self.program_counter.wrapping_add(offset as u16);
+            if let Some(address) = self.resolve_address(address)? {
+                *byte = memory.read(address);
+            }
+        }
 
-        msb << 8 | lsb
+        let opcode = u16::from_be_bytes(bytes);
+        self.observers.notify(|| MachineEvent::Fetch {
+            address: self.program_counter,
+            opcode,
+        });
+        Ok(opcode)
     }
 
     pub fn execute_instruction(
@@ -78,9 +446,7 @@ impl VirtualProcessor {
         opcode: u16,
         memory: &mut Memory,
         render_table: &mut RenderTable,
-    ) {
-        println!("[Info] Currently executed Opcode -> {:#06x}", opcode);
-
+    ) -> Result<(), Fault> {
         let nnn = opcode & 0x0FFF;
         let nn = (opcode & 0x00FF) as u8;
         let n = (opcode & 0x000F) as u8;
@@ -89,19 +455,35 @@ impl VirtualProcessor {
 
         match opcode & 0xF000 {
             0x0000 => {
-                match opcode & 0x00FF {
+                match opcode {
                     0x00E0 => {
                         // CLS
                         render_table.clear();
+                        self.observers.notify(|| MachineEvent::Clear {
+                            program_counter: self.program_counter,
+                        });
                         self.draw_flag = true;
-                        self.program_counter += 2;
+                        self.advance_program_counter(2);
                     }
                     0x00EE => {
                         // RET
-                        self.program_counter = self.stack.pop().unwrap();
-                        self.program_counter += 2;
+                        let Some(return_address) = self.stack.pop() else {
+                            return Err(Fault::StackUnderflow {
+                                address: self.program_counter,
+                            });
+                        };
+
+                        self.program_counter = if self.is_stack_in_memory {
+                            // A ROM overwriting the slot may leave any 16 bits in it.
+                            let slot = STACK_MEMORY_START + self.stack.len() as u16 * 2;
+                            ((memory.read(slot) as u16) << 8 | memory.read(slot + 1) as u16)
+                                & 0x0FFF
+                        } else {
+                            return_address
+                        };
+                        self.advance_program_counter(2);
                     }
-                    _ => panic!("[Error] Unknown opcode -> {:#06x}", opcode),
+                    _ => return Err(self.unknown_opcode(opcode)),
                 }
             }
             0x1000 => {
@@ -110,153 +492,163 @@ impl VirtualProcessor {
             }
             0x2000 => {
                 // CALL addr
+                let depth = self.stack.len();
+                if depth >= self.stack_limit() {
+                    return Err(Fault::StackOverflow {
+                        address: self.program_counter,
+                        depth: depth as u16,
+                    });
+                }
+
+                if self.is_stack_in_memory {
+                    let slot = STACK_MEMORY_START + depth as u16 * 2;
+                    let [high, low] = self.program_counter.to_be_bytes();
+                    self.write_memory(memory, slot, high)?;
+                    self.write_memory(memory, slot + 1, low)?;
+                }
                 self.stack.push(self.program_counter);
                 self.program_counter = nnn;
             }
             0x3000 => {
                 // SE Vx, byte
                 if self.registers[x as usize] == nn {
-                    self.program_counter += 4;
+                    self.advance_program_counter(4);
                 } else {
-                    self.program_counter += 2;
+                    self.advance_program_counter(2);
                 }
             }
             0x4000 => {
                 // SNE Vx, byte
                 if self.registers[x as usize] != nn {
-                    self.program_counter += 4;
+                    self.advance_program_counter(4);
                 } else {
-                    self.program_counter += 2;
+                    self.advance_program_counter(2);
                 }
             }
             0x5000 => {
                 // SE Vx, Vy
                 if self.registers[x as usize] == self.registers[y as usize] {
-                    self.program_counter += 4;
+                    self.advance_program_counter(4);
                 } else {
-                    self.program_counter += 2;
+                    self.advance_program_counter(2);
                 }
             }
             0x6000 => {
                 // LD Vx, byte
                 self.registers[x as usize] = nn;
-                self.program_counter += 2;
+                self.advance_program_counter(2);
             }
             0x7000 => {
-                // ADD Vx, byte
-                self.registers[x as usize] += nn;
-                self.program_counter += 2;
+                // ADD Vx, byte, VF untouched.
+                self.registers[x as usize] = self.registers[x as usize].wrapping_add(nn);
+                self.advance_program_counter(2);
             }
             0x8000 => {
                 match opcode & 0x000F {
                     0x0000 => {
                         // LD Vx, Vy
                         self.registers[x as usize] = self.registers[y as usize];
-                        self.program_counter += 2;
+                        self.advance_program_counter(2);
                     }
                     0x0001 => {
                         // OR Vx, Vy
                         self.registers[x as usize] |= self.registers[y as usize];
-                        self.program_counter += 2;
+                        if self.quirks.logic_resets_vf {
+                            self.registers[0xF] = 0;
+                        }
+                        self.advance_program_counter(2);
                     }
                     0x0002 => {
                         // AND Vx, Vy
                         self.registers[x as usize] &= self.registers[y as usize];
-                        self.program_counter += 2;
+                        if self.quirks.logic_resets_vf {
+                            self.registers[0xF] = 0;
+                        }
+                        self.advance_program_counter(2);
                     }
                     0x0003 => {
                         // XOR Vx, Vy
                         self.registers[x as usize] ^= self.registers[y as usize];
-                        self.program_counter += 2;
+                        if self.quirks.logic_resets_vf {
+                            self.registers[0xF] = 0;
+                        }
+                        self.advance_program_counter(2);
                     }
                     0x0004 => {
                         // ADD Vx, Vy
-                        let r =
-                            self.registers[x as usize] as u16 + self.registers[y as usize] as u16;
-
-                        self.registers[0xF] = if r > 0xFF { 1 } else { 0 };
-
-                        self.registers[x as usize] = (r & 0xFF) as u8;
-
-                        self.program_counter += 2;
+                        let (result, carry) =
+                            self.registers[x as usize].overflowing_add(self.registers[y as usize]);
+                        self.set_result_and_flag(x, result, carry);
+                        self.advance_program_counter(2);
                     }
                     0x0005 => {
                         // SUB Vx, Vy
-                        self.registers[0xF] =
-                            if self.registers[x as usize] > self.registers[y as usize] {
-                                1
-                            } else {
-                                0
-                            };
-
-                        self.registers[x as usize] -= self.registers[y as usize];
-
-                        self.program_counter += 2;
+                        let (result, borrow) =
+                            self.registers[x as usize].overflowing_sub(self.registers[y as usize]);
+                        self.set_result_and_flag(x, result, !borrow);
+                        self.advance_program_counter(2);
                     }
                     0x0006 => {
                         // SHR Vx {, Vy}
-                        self.registers[0xF] = self.registers[x as usize] & 0x1;
-                        self.registers[x as usize] >>= 1;
-
-                        self.program_counter += 2;
+                        let source = if self.quirks.shift_uses_vy { y } else { x };
+                        let value = self.registers[source as usize];
+                        self.set_result_and_flag(x, value >> 1, value & 0x01 != 0);
+                        self.advance_program_counter(2);
                     }
                     0x0007 => {
                         // SUBN Vx, Vy
-                        self.registers[0xF] =
-                            if self.registers[y as usize] > self.registers[x as usize] {
-                                1
-                            } else {
-                                0
-                            };
-
-                        self.registers[x as usize] =
-                            self.registers[y as usize] - self.registers[x as usize];
-
-                        self.program_counter += 2;
+                        let (result, borrow) =
+                            self.registers[y as usize].overflowing_sub(self.registers[x as usize]);
+                        self.set_result_and_flag(x, result, !borrow);
+                        self.advance_program_counter(2);
                     }
                     0x000E => {
                         // SHL Vx {, Vy}
-                        self.registers[0xF] = (self.registers[x as usize] & 128) >> 7;
-                        self.registers[x as usize] <<= 1;
-
-                        self.program_counter += 2;
+                        let source = if self.quirks.shift_uses_vy { y } else { x };
+                        let value = self.registers[source as usize];
+                        self.set_result_and_flag(x, value << 1, value & 0x80 != 0);
+                        self.advance_program_counter(2);
                     }
-                    _ => panic!("[Error] Unknown opcode -> {:#06x}", opcode),
+                    _ => return Err(self.unknown_opcode(opcode)),
                 }
             }
             0x9000 => {
                 // SNE Vx, Vy
                 if self.registers[x as usize] != self.registers[y as usize] {
-                    self.program_counter += 4;
+                    self.advance_program_counter(4);
                 } else {
-                    self.program_counter += 2;
+                    self.advance_program_counter(2);
                 }
             }
             0xA000 => {
                 // LD I, addr
                 self.index_register = nnn;
-                self.program_counter += 2;
+                self.advance_program_counter(2);
             }
             0xB000 => {
                 // JP V0, addr
-                self.program_counter = self.registers[0x0] as u16 + nnn;
-                self.program_counter += 2;
+                let offset_register = if self.quirks.jump_uses_vx { x } else { 0x0 };
+                self.program_counter =
+                    (self.registers[offset_register as usize] as u16 + nnn) % TOTAL_MEMORY_BYTES;
             }
             0xC000 => {
                 // RND Vx, byte
-                self.registers[x as usize] = self.rng.gen_range(0..0xFF) & nn;
-                self.program_counter += 2;
+                self.registers[x as usize] = self.rng.gen::<u8>() & nn;
+                self.advance_program_counter(2);
             }
             0xD000 => {
                 // DRW Vx, Vy, nibble
-                self.registers[0xF] = 0;
+                self.check_range(self.index_register, n as u16)?;
 
+                // Read before VF is cleared, in case it holds a coordinate.
                 let origin_x = self.registers[x as usize];
                 let origin_y = self.registers[y as usize];
+                self.registers[0xF] = 0;
 
                 for y_coord in 0..n {
+                    let pixel =
+                        self.read_memory(memory, self.index_register.wrapping_add(y_coord as u16))?;
                     for x_coord in 0..8 {
-                        let pixel = memory.read(self.index_register + y_coord as u16);
                         if pixel & (0x80 >> x_coord) != 0 {
                             let pixel_x = origin_x.wrapping_add(x_coord) % CHIP8_SCREEN_WIDTH;
                             let pixel_y = origin_y.wrapping_add(y_coord) % CHIP8_SCREEN_HEIGHT;
@@ -279,28 +671,36 @@ impl VirtualProcessor {
                     }
                 }
 
+                self.observers.notify(|| MachineEvent::Draw {
+                    program_counter: self.program_counter,
+                    x: origin_x,
+                    y: origin_y,
+                    address: self.index_register,
+                    height: n,
+                    is_collision: self.registers[0xF] == 1,
+                });
                 self.draw_flag = true;
-                self.program_counter += 2;
+                self.advance_program_counter(2);
             }
             0xE000 => {
                 match opcode & 0x00FF {
                     0x009E => {
                         // SKP Vx
-                        if self.keys[self.registers[x as usize] as usize] {
-                            self.program_counter += 4;
+                        if self.keys[(self.registers[x as usize] & 0x0F) as usize] {
+                            self.advance_program_counter(4);
                         } else {
-                            self.program_counter += 2
+                            self.advance_program_counter(2)
                         };
                     }
                     0x00A1 => {
                         // SKNP VX
-                        if !self.keys[self.registers[x as usize] as usize] {
-                            self.program_counter += 4;
+                        if !self.keys[(self.registers[x as usize] & 0x0F) as usize] {
+                            self.advance_program_counter(4);
                         } else {
-                            self.program_counter += 2;
+                            self.advance_program_counter(2);
                         }
                     }
-                    _ => panic!("[Error] Unknown opcode -> {:#06x}", opcode),
+                    _ => return Err(self.unknown_opcode(opcode)),
                 }
             }
             0xF000 => {
@@ -308,7 +708,7 @@ impl VirtualProcessor {
                     0x0007 => {
                         // LD Vx, DT
                         self.registers[x as usize] = self.delay_timer;
-                        self.program_counter += 2;
+                        self.advance_program_counter(2);
                     }
                     0x000A => {
                         // LD Vx, K
@@ -322,64 +722,108 @@ impl VirtualProcessor {
                         }
 
                         if !pressed {
-                            return;
+                            self.observers.notify(|| MachineEvent::KeyWait {
+                                program_counter: self.program_counter,
+                                register: x,
+                            });
+                            return Ok(());
                         }
 
-                        self.program_counter += 2;
+                        self.advance_program_counter(2);
                     }
                     0x0015 => {
                         // LD DT, Vx
                         self.delay_timer = self.registers[x as usize];
-                        self.program_counter += 2;
+                        self.observers.notify(|| MachineEvent::TimerWrite {
+                            program_counter: self.program_counter,
+                            timer: Timer::Delay,
+                            value: self.delay_timer,
+                        });
+                        self.advance_program_counter(2);
                     }
                     0x0018 => {
                         // LD ST, Vx
                         self.sound_timer = self.registers[x as usize];
-                        self.program_counter += 2;
+                        self.observers.notify(|| MachineEvent::TimerWrite {
+                            program_counter: self.program_counter,
+                            timer: Timer::Sound,
+                            value: self.sound_timer,
+                        });
+                        self.advance_program_counter(2);
                     }
                     0x001E => {
                         // ADD I, Vx
-                        self.index_register += self.registers[x as usize] as u16;
-                        self.program_counter += 2;
+                        self.index_register = self
+                            .index_register
+                            .wrapping_add(self.registers[x as usize] as u16);
+                        self.advance_program_counter(2);
                     }
                     0x0029 => {
                         // LD F, Vx
-                        self.index_register = self.registers[x as usize] as u16 * 5;
-                        self.program_counter += 2;
+                        let character = self.registers[x as usize] & 0x0F;
+                        self.index_register =
+                            self.font_address + character as u16 * (SMALL_FONT_BYTES / 16);
+                        self.advance_program_counter(2);
+                    }
+                    0x0030 => {
+                        // LD HF, Vx (SCHIP)
+                        let character = self.registers[x as usize] & 0x0F;
+                        self.index_register = self.font_address
+                            + SMALL_FONT_BYTES
+                            + character as u16 * (BIG_FONT_BYTES / 16);
+                        self.advance_program_counter(2);
                     }
                     0x0033 => {
                         // LD B, Vx
+                        self.check_range(self.index_register, 3)?;
 
                         let reg_val = self.registers[x as usize];
+                        let digits = [reg_val / 100, (reg_val % 100) / 10, reg_val % 10];
+                        for (offset, digit) in digits.into_iter().enumerate() {
+                            let address = self.index_register.wrapping_add(offset as u16);
+                            self.write_memory(memory, address, digit)?;
+                        }
 
-                        memory.write(self.index_register, reg_val / 100); // Hundreds.
-                        memory.write(self.index_register + 1, (reg_val % 100) / 10); // Tens.
-                        memory.write(self.index_register + 2, reg_val % 10);
-
-                        self.program_counter += 2;
+                        self.advance_program_counter(2);
                     }
                     0x0055 => {
                         // LD [I], Vx
+                        self.check_range(self.index_register, x as u16 + 1)?;
                         for i in 0..(x + 1) {
                             let reg = self.registers[i as usize];
-                            memory.write(self.index_register + i as u16, reg);
+                            let address = self.index_register.wrapping_add(i as u16);
+                            self.write_memory(memory, address, reg)?;
+                        }
+
+                        if self.quirks.load_store_increments_index {
+                            self.index_register = self.index_register.wrapping_add(x as u16 + 1);
                         }
 
-                        self.program_counter += 2;
+                        self.advance_program_counter(2);
                     }
                     0x0065 => {
                         // LD Vx, [I]
+                        self.check_range(self.index_register, x as u16 + 1)?;
                         for i in 0..(x + 1) {
-                            let reg_from_mem = memory.read(self.index_register + i as u16);
-                            self.registers[i as usize] = reg_from_mem;
+                            let address = self.index_register.wrapping_add(i as u16);
+                            self.registers[i as usize] = self.read_memory(memory, address)?;
                         }
 
-                        self.program_counter += 2;
+                        if self.quirks.load_store_increments_index {
+                            self.index_register = self.index_register.wrapping_add(x as u16 + 1);
+                        }
+
+                        self.advance_program_counter(2);
                     }
-                    _ => panic!("[Error] Unknown opcode -> {:#06x}", opcode),
+                    _ => return Err(self.unknown_opcode(opcode)),
                 }
             }
-            _ => panic!("[Error] Unknown opcode -> {:#06x}", opcode),
+            _ => return Err(self.unknown_opcode(opcode)),
         }
+
+        Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests;
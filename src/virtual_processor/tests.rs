@@ -0,0 +1,1086 @@
+use super::*;
+use crate::disassembler::{decode, InstructionKind};
+use crate::memory::MemoryRegion;
+use crate::observer::{MachineEvent, Timer};
+use std::sync::{Arc, Mutex};
+
+const START: u16 = PROCESSOR_INTERNAL_PROGRAM_COUNTER_START;
+
+// A processor with the memory and screen it runs against.
+struct Machine {
+    processor: VirtualProcessor,
+    memory: Memory,
+    render_table: RenderTable,
+}
+
+impl Machine {
+    fn new() -> Machine {
+        Machine {
+            processor: VirtualProcessor::new(),
+            memory: Memory::new(),
+            render_table: RenderTable::new(),
+        }
+    }
+
+    fn with_quirks(quirks: Quirks) -> Machine {
+        let mut machine = Machine::new();
+        machine.processor.set_quirks(quirks);
+        machine
+    }
+
+    fn with_registers(registers: &[(usize, u8)]) -> Machine {
+        let mut machine = Machine::new();
+        for (index, value) in registers {
+            machine.processor.set_register(*index, *value);
+        }
+        machine
+    }
+
+    fn execute(&mut self, opcode: u16) -> Result<(), Fault> {
+        self.processor
+            .execute_instruction(opcode, &mut self.memory, &mut self.render_table)
+    }
+
+    fn run(&mut self, opcode: u16) {
+        self.execute(opcode).unwrap();
+    }
+
+    fn register(&self, index: usize) -> u8 {
+        self.processor.registers()[index]
+    }
+
+    fn lit_pixels(&self) -> usize {
+        self.render_table
+            .pixels()
+            .iter()
+            .filter(|pixel| **pixel)
+            .count()
+    }
+}
+
+// An instruction only changing the registers and PC : the registers it starts
+// with, the ones expected to change and how far PC moves.
+struct RegisterCase {
+    name: &'static str,
+    opcode: u16,
+    before: &'static [(usize, u8)],
+    after: &'static [(usize, u8)],
+    program_counter_step: u16,
+}
+
+const REGISTER_CASES: &[RegisterCase] = &[
+    RegisterCase {
+        name: "SE Vx, byte equal",
+        opcode: 0x3142,
+        before: &[(0x1, 0x42)],
+        after: &[],
+        program_counter_step: 4,
+    },
+    RegisterCase {
+        name: "SE Vx, byte different",
+        opcode: 0x3142,
+        before: &[(0x1, 0x41)],
+        after: &[],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SNE Vx, byte equal",
+        opcode: 0x4142,
+        before: &[(0x1, 0x42)],
+        after: &[],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SNE Vx, byte different",
+        opcode: 0x4142,
+        before: &[(0x1, 0x41)],
+        after: &[],
+        program_counter_step: 4,
+    },
+    RegisterCase {
+        name: "SE Vx, Vy equal",
+        opcode: 0x5120,
+        before: &[(0x1, 0x42), (0x2, 0x42)],
+        after: &[],
+        program_counter_step: 4,
+    },
+    RegisterCase {
+        name: "SE Vx, Vy different",
+        opcode: 0x5120,
+        before: &[(0x1, 0x42), (0x2, 0x24)],
+        after: &[],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SNE Vx, Vy equal",
+        opcode: 0x9120,
+        before: &[(0x1, 0x42), (0x2, 0x42)],
+        after: &[],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SNE Vx, Vy different",
+        opcode: 0x9120,
+        before: &[(0x1, 0x42), (0x2, 0x24)],
+        after: &[],
+        program_counter_step: 4,
+    },
+    RegisterCase {
+        name: "LD Vx, byte",
+        opcode: 0x6AFE,
+        before: &[(0xA, 0x12)],
+        after: &[(0xA, 0xFE)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "ADD Vx, byte",
+        opcode: 0x7102,
+        before: &[(0x1, 0x40)],
+        after: &[(0x1, 0x42)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "ADD Vx, byte wraps without touching VF",
+        opcode: 0x7110,
+        before: &[(0x1, 0xF8), (0xF, 0x42)],
+        after: &[(0x1, 0x08)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "LD Vx, Vy",
+        opcode: 0x8120,
+        before: &[(0x1, 0x11), (0x2, 0x22)],
+        after: &[(0x1, 0x22)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "OR Vx, Vy",
+        opcode: 0x8121,
+        before: &[(0x1, 0x0C), (0x2, 0x0A), (0xF, 0x42)],
+        after: &[(0x1, 0x0E)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "AND Vx, Vy",
+        opcode: 0x8122,
+        before: &[(0x1, 0x0C), (0x2, 0x0A), (0xF, 0x42)],
+        after: &[(0x1, 0x08)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "XOR Vx, Vy",
+        opcode: 0x8123,
+        before: &[(0x1, 0x0C), (0x2, 0x0A), (0xF, 0x42)],
+        after: &[(0x1, 0x06)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "ADD Vx, Vy without carry",
+        opcode: 0x8124,
+        before: &[(0x1, 0xFE), (0x2, 0x01), (0xF, 0x42)],
+        after: &[(0x1, 0xFF), (0xF, 0)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "ADD Vx, Vy with carry",
+        opcode: 0x8124,
+        before: &[(0x1, 0xFF), (0x2, 0x01)],
+        after: &[(0x1, 0x00), (0xF, 1)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "ADD VF, Vy keeps the carry",
+        opcode: 0x8F14,
+        before: &[(0xF, 0xFF), (0x1, 0x01)],
+        after: &[(0xF, 1)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SUB Vx, Vy without borrow",
+        opcode: 0x8125,
+        before: &[(0x1, 0x30), (0x2, 0x10)],
+        after: &[(0x1, 0x20), (0xF, 1)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SUB Vx, Vy of equal values",
+        opcode: 0x8125,
+        before: &[(0x1, 0x10), (0x2, 0x10)],
+        after: &[(0x1, 0x00), (0xF, 1)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SUB Vx, Vy with borrow",
+        opcode: 0x8125,
+        before: &[(0x1, 0x00), (0x2, 0x01), (0xF, 0x42)],
+        after: &[(0x1, 0xFF), (0xF, 0)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SUB VF, Vy keeps the borrow",
+        opcode: 0x8F15,
+        before: &[(0xF, 0x00), (0x1, 0x01)],
+        after: &[(0xF, 0)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SHR Vx",
+        opcode: 0x8106,
+        before: &[(0x1, 0x81)],
+        after: &[(0x1, 0x40), (0xF, 1)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SHR Vx of an even value",
+        opcode: 0x8106,
+        before: &[(0x1, 0x80), (0xF, 0x42)],
+        after: &[(0x1, 0x40), (0xF, 0)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SHR VF keeps the shifted bit",
+        opcode: 0x8F06,
+        before: &[(0xF, 0x02)],
+        after: &[(0xF, 0)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SUBN Vx, Vy without borrow",
+        opcode: 0x8127,
+        before: &[(0x1, 0x10), (0x2, 0x30)],
+        after: &[(0x1, 0x20), (0xF, 1)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SUBN Vx, Vy of equal values",
+        opcode: 0x8127,
+        before: &[(0x1, 0x10), (0x2, 0x10)],
+        after: &[(0x1, 0x00), (0xF, 1)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SUBN Vx, Vy with borrow",
+        opcode: 0x8127,
+        before: &[(0x1, 0x01), (0x2, 0x00)],
+        after: &[(0x1, 0xFF), (0xF, 0)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SUBN VF, Vy keeps the borrow",
+        opcode: 0x8F17,
+        before: &[(0xF, 0x01), (0x1, 0x00)],
+        after: &[(0xF, 0)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SHL Vx",
+        opcode: 0x810E,
+        before: &[(0x1, 0x81)],
+        after: &[(0x1, 0x02), (0xF, 1)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SHL Vx without high bit",
+        opcode: 0x810E,
+        before: &[(0x1, 0x01), (0xF, 0x42)],
+        after: &[(0x1, 0x02), (0xF, 0)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "SHL VF keeps the shifted bit",
+        opcode: 0x8F0E,
+        before: &[(0xF, 0x80)],
+        after: &[(0xF, 1)],
+        program_counter_step: 2,
+    },
+    RegisterCase {
+        name: "RND Vx, 0",
+        opcode: 0xC100,
+        before: &[(0x1, 0x42)],
+        after: &[(0x1, 0x00)],
+        program_counter_step: 2,
+    },
+];
+
+#[test]
+fn register_instructions() {
+    for case in REGISTER_CASES {
+        let mut machine = Machine::with_registers(case.before);
+        machine.run(case.opcode);
+
+        let mut expected = [0; REGISTERS_COUNT as usize];
+        for (index, value) in case.before.iter().chain(case.after) {
+            expected[*index] = *value;
+        }
+        assert_eq!(machine.processor.registers(), &expected, "{}", case.name);
+        assert_eq!(
+            machine.processor.program_counter(),
+            START + case.program_counter_step,
+            "{}",
+            case.name
+        );
+    }
+}
+
+#[test]
+fn logic_can_reset_vf() {
+    for opcode in [0x8121, 0x8122, 0x8123] {
+        let mut machine = Machine::with_quirks(Quirks {
+            logic_resets_vf: true,
+            ..Quirks::default()
+        });
+        machine.processor.set_register(0xF, 0x42);
+        machine.run(opcode);
+        assert_eq!(machine.register(0xF), 0, "{:04X}", opcode);
+    }
+}
+
+#[test]
+fn shifts_can_use_vy() {
+    let mut machine = Machine::with_quirks(Quirks {
+        shift_uses_vy: true,
+        ..Quirks::default()
+    });
+    machine.processor.set_register(0x1, 0xFF);
+    machine.processor.set_register(0x2, 0x03);
+    machine.run(0x8126);
+    assert_eq!(machine.register(0x1), 0x01);
+    assert_eq!(machine.register(0x2), 0x03);
+    assert_eq!(machine.register(0xF), 1);
+
+    machine.run(0x812E);
+    assert_eq!(machine.register(0x1), 0x06);
+    assert_eq!(machine.register(0xF), 0);
+}
+
+#[test]
+fn random_is_masked() {
+    let mut machine = Machine::new();
+    for _ in 0..256 {
+        machine.run(0xC10F);
+        assert!(machine.register(0x1) <= 0x0F);
+    }
+}
+
+#[test]
+fn unknown_opcodes_stop_the_machine() {
+    for opcode in [0x0123, 0x01E0, 0x8128, 0xE1FF, 0xF1FF] {
+        let mut machine = Machine::new();
+        assert_eq!(
+            machine.execute(opcode),
+            Err(Fault::UnknownOpcode {
+                address: START,
+                opcode
+            })
+        );
+        assert_eq!(machine.processor.program_counter(), START, "{:04X}", opcode);
+    }
+}
+
+#[test]
+fn clear_screen() {
+    let mut machine = Machine::new();
+    machine
+        .render_table
+        .change_pixel_state_to(10, 10, PixelState::SwitchedOn);
+    machine.run(0x00E0);
+    assert_eq!(machine.lit_pixels(), 0);
+    assert!(machine.processor.get_draw_flag());
+    assert_eq!(machine.processor.program_counter(), START + 2);
+}
+
+#[test]
+fn jump() {
+    let mut machine = Machine::new();
+    machine.run(0x1ABC);
+    assert_eq!(machine.processor.program_counter(), 0xABC);
+    assert!(machine.processor.stack().is_empty());
+}
+
+#[test]
+fn jump_with_offset() {
+    let mut machine = Machine::with_registers(&[(0x0, 0x10), (0x3, 0x20)]);
+    machine.run(0xB300);
+    assert_eq!(machine.processor.program_counter(), 0x310);
+
+    let mut machine = Machine::with_quirks(Quirks {
+        jump_uses_vx: true,
+        ..Quirks::default()
+    });
+    machine.processor.set_register(0x0, 0x10);
+    machine.processor.set_register(0x3, 0x20);
+    machine.run(0xB300);
+    assert_eq!(machine.processor.program_counter(), 0x320);
+}
+
+#[test]
+fn call_and_return() {
+    let mut machine = Machine::new();
+    machine.run(0x2300);
+    assert_eq!(machine.processor.program_counter(), 0x300);
+    assert_eq!(machine.processor.stack(), &[START]);
+    assert_eq!(machine.processor.stack_pointer(), 1);
+
+    machine.run(0x2400);
+    assert_eq!(machine.processor.stack(), &[START, 0x300]);
+
+    machine.run(0x00EE);
+    assert_eq!(machine.processor.program_counter(), 0x302);
+    machine.run(0x00EE);
+    assert_eq!(machine.processor.program_counter(), START + 2);
+    assert!(machine.processor.stack().is_empty());
+}
+
+#[test]
+fn stack_overflow_stops_on_the_call() {
+    let mut machine = Machine::new();
+    machine.processor.set_stack_depth(StackDepth::Limited(12));
+    for _ in 0..12 {
+        machine.processor.set_program_counter(START);
+        machine.run(0x2200);
+    }
+
+    assert_eq!(
+        machine.execute(0x2200),
+        Err(Fault::StackOverflow {
+            address: START,
+            depth: 12
+        })
+    );
+    assert_eq!(machine.processor.program_counter(), START);
+    assert_eq!(machine.processor.stack().len(), 12);
+
+    machine.processor.set_stack_depth(StackDepth::Unlimited);
+    for _ in 0..100 {
+        machine.run(0x2200);
+    }
+    assert_eq!(machine.processor.stack().len(), 112);
+}
+
+#[test]
+fn stack_underflow_stops_on_the_return() {
+    let mut machine = Machine::new();
+    assert_eq!(
+        machine.execute(0x00EE),
+        Err(Fault::StackUnderflow { address: START })
+    );
+    assert_eq!(machine.processor.program_counter(), START);
+}
+
+#[test]
+fn stack_in_memory() {
+    let mut machine = Machine::new();
+    machine.processor.set_stack_in_memory(true);
+    machine.run(0x2300);
+    assert_eq!(machine.memory.read(STACK_MEMORY_START), 0x02);
+    assert_eq!(machine.memory.read(STACK_MEMORY_START + 1), 0x00);
+
+    // The ROM changes its own return address.
+    machine.memory.write(STACK_MEMORY_START, 0x04);
+    machine.run(0x00EE);
+    assert_eq!(machine.processor.program_counter(), 0x402);
+
+    // Only the 12 bits of an address are kept from a corrupted slot.
+    machine.run(0x2300);
+    machine.memory.write(STACK_MEMORY_START, 0xF3);
+    machine.memory.write(STACK_MEMORY_START + 1, 0x00);
+    machine.run(0x00EE);
+    assert_eq!(machine.processor.program_counter(), 0x302);
+
+    machine.processor.set_stack_depth(StackDepth::Unlimited);
+    for _ in 0..STACK_MEMORY_LEVELS {
+        machine.run(0x2200);
+    }
+    assert!(machine.execute(0x2200).is_err());
+}
+
+#[test]
+fn unlimited_stack_stops_where_save_states_end() {
+    let mut machine = Machine::new();
+    machine.processor.set_stack_depth(StackDepth::Unlimited);
+    for _ in 0..u16::MAX {
+        machine.run(0x2200);
+    }
+    assert_eq!(
+        machine.execute(0x2200),
+        Err(Fault::StackOverflow {
+            address: START,
+            depth: u16::MAX
+        })
+    );
+
+    let mut writer = StateWriter::new();
+    machine.processor.save_state(&mut writer);
+    let state = writer.into_bytes();
+    let mut processor = VirtualProcessor::new();
+    processor
+        .load_state(&mut StateReader::new(&state).unwrap())
+        .unwrap();
+    assert_eq!(processor.stack(), machine.processor.stack());
+}
+
+#[test]
+fn stack_in_memory_writes_are_observed_and_protected() {
+    let mut machine = Machine::new();
+    machine.processor.set_stack_in_memory(true);
+    let events = record_events(&mut machine, &[0x2300]);
+    assert_eq!(
+        events[..2],
+        [
+            MachineEvent::MemoryWrite {
+                program_counter: START,
+                address: STACK_MEMORY_START,
+                value: 0x02
+            },
+            MachineEvent::MemoryWrite {
+                program_counter: START,
+                address: STACK_MEMORY_START + 1,
+                value: 0x00
+            },
+        ]
+    );
+
+    // A ROM running code where the next return address goes.
+    machine
+        .processor
+        .set_memory_protection(MemoryProtection::Break);
+    machine.memory.mark_executed(STACK_MEMORY_START + 2);
+    machine.run(0x2400);
+    assert_eq!(
+        machine.processor.take_memory_break(),
+        Some(FlaggedWrite {
+            program_counter: 0x300,
+            address: STACK_MEMORY_START + 2,
+            is_into_code: true
+        })
+    );
+}
+
+#[test]
+fn load_index() {
+    let mut machine = Machine::new();
+    machine.run(0xA123);
+    assert_eq!(machine.processor.index_register(), 0x123);
+    assert_eq!(machine.processor.program_counter(), START + 2);
+}
+
+#[test]
+fn add_index() {
+    let mut machine = Machine::with_registers(&[(0x1, 0x20), (0xF, 0x42)]);
+    machine.processor.set_index_register(0x100);
+    machine.run(0xF11E);
+    assert_eq!(machine.processor.index_register(), 0x120);
+    assert_eq!(machine.register(0xF), 0x42);
+
+    machine.processor.set_index_register(0xFFFF);
+    machine.processor.set_register(0x1, 0x02);
+    machine.run(0xF11E);
+    assert_eq!(machine.processor.index_register(), 0x0001);
+}
+
+#[test]
+fn font_character() {
+    let mut machine = Machine::with_registers(&[(0x1, 0xA)]);
+    machine.run(0xF129);
+    assert_eq!(machine.processor.index_register(), 50);
+
+    // Only the low nibble is a digit.
+    machine.processor.set_register(0x1, 0x1A);
+    machine.run(0xF129);
+    assert_eq!(machine.processor.index_register(), 50);
+}
+
+#[test]
+fn big_font_character() {
+    let mut machine = Machine::with_registers(&[(0x1, 0x1A)]);
+    machine.run(0xF130);
+    assert_eq!(machine.processor.index_register(), 80 + 100);
+}
+
+#[test]
+fn font_characters_follow_the_font_address() {
+    let mut machine = Machine::with_registers(&[(0x1, 0x3)]);
+    machine.processor.set_font_address(0x050);
+    machine.run(0xF129);
+    assert_eq!(machine.processor.index_register(), 0x050 + 15);
+    machine.run(0xF130);
+    assert_eq!(machine.processor.index_register(), 0x050 + 80 + 30);
+}
+
+#[test]
+fn binary_coded_decimal() {
+    let mut machine = Machine::with_registers(&[(0x1, 254)]);
+    machine.processor.set_index_register(0x300);
+    machine.run(0xF133);
+    assert_eq!(machine.memory.bytes()[0x300..0x303], [2, 5, 4]);
+    assert_eq!(machine.processor.index_register(), 0x300);
+}
+
+#[test]
+fn store_and_load_registers() {
+    let mut machine =
+        Machine::with_registers(&[(0x0, 0x10), (0x1, 0x11), (0x2, 0x12), (0x3, 0x13)]);
+    machine.processor.set_index_register(0x300);
+    machine.run(0xF255);
+    assert_eq!(
+        machine.memory.bytes()[0x300..0x304],
+        [0x10, 0x11, 0x12, 0x00]
+    );
+    assert_eq!(machine.processor.index_register(), 0x300);
+
+    let mut machine = Machine::new();
+    for (offset, value) in [0x20, 0x21, 0x22, 0x23].iter().enumerate() {
+        machine.memory.write(0x300 + offset as u16, *value);
+    }
+    machine.processor.set_index_register(0x300);
+    machine.run(0xF265);
+    assert_eq!(machine.processor.registers()[..4], [0x20, 0x21, 0x22, 0x00]);
+    assert_eq!(machine.processor.index_register(), 0x300);
+}
+
+#[test]
+fn store_and_load_can_increment_index() {
+    for opcode in [0xF255, 0xF265] {
+        let mut machine = Machine::with_quirks(Quirks {
+            load_store_increments_index: true,
+            ..Quirks::default()
+        });
+        machine.processor.set_index_register(0x300);
+        machine.run(opcode);
+        assert_eq!(machine.processor.index_register(), 0x303, "{:04X}", opcode);
+    }
+}
+
+#[test]
+fn draw_sprite() {
+    let mut machine = Machine::with_registers(&[(0x1, 4), (0x2, 2)]);
+    for (offset, row) in [0xF0, 0x90, 0xF0].iter().enumerate() {
+        machine.memory.write(0x300 + offset as u16, *row);
+    }
+    machine.processor.set_index_register(0x300);
+
+    machine.run(0xD123);
+    assert_eq!(machine.lit_pixels(), 10);
+    assert!(machine.render_table.is_pixel_switched_on(4, 2));
+    assert!(machine.render_table.is_pixel_switched_on(7, 3));
+    assert!(!machine.render_table.is_pixel_switched_on(5, 3));
+    assert!(!machine.render_table.is_pixel_switched_on(8, 2));
+    assert_eq!(machine.register(0xF), 0);
+    assert!(machine.processor.get_draw_flag());
+    assert_eq!(machine.processor.program_counter(), START + 2);
+
+    // Drawing it again erases it, with a collision.
+    machine.run(0xD123);
+    assert_eq!(machine.lit_pixels(), 0);
+    assert_eq!(machine.register(0xF), 1);
+}
+
+#[test]
+fn draw_sprite_at_vf() {
+    let mut machine = Machine::with_registers(&[(0xF, 10), (0x1, 5)]);
+    machine.memory.write(0x300, 0x80);
+    machine.processor.set_index_register(0x300);
+    machine.run(0xDF11);
+    assert!(machine.render_table.is_pixel_switched_on(10, 5));
+    assert_eq!(machine.register(0xF), 0);
+}
+
+#[test]
+fn draw_sprite_wraps_around_the_screen() {
+    let mut machine = Machine::with_registers(&[(0x1, 62), (0x2, 31)]);
+    machine.memory.write(0x300, 0xF0);
+    machine.memory.write(0x301, 0x80);
+    machine.processor.set_index_register(0x300);
+    machine.run(0xD122);
+
+    assert!(machine.render_table.is_pixel_switched_on(62, 31));
+    assert!(machine.render_table.is_pixel_switched_on(63, 31));
+    assert!(machine.render_table.is_pixel_switched_on(0, 31));
+    assert!(machine.render_table.is_pixel_switched_on(1, 31));
+    assert!(machine.render_table.is_pixel_switched_on(62, 0));
+    assert_eq!(machine.lit_pixels(), 5);
+}
+
+#[test]
+fn skip_on_key() {
+    let mut machine = Machine::with_registers(&[(0x1, 0xB)]);
+    machine.run(0xE19E);
+    assert_eq!(machine.processor.program_counter(), START + 2);
+    machine.run(0xE1A1);
+    assert_eq!(machine.processor.program_counter(), START + 6);
+
+    machine.processor.set_key(0xB, true);
+    machine.processor.set_program_counter(START);
+    machine.run(0xE19E);
+    assert_eq!(machine.processor.program_counter(), START + 4);
+    machine.run(0xE1A1);
+    assert_eq!(machine.processor.program_counter(), START + 6);
+
+    // Only the low nibble is a key.
+    machine.processor.set_register(0x1, 0xFB);
+    machine.processor.set_program_counter(START);
+    machine.run(0xE19E);
+    assert_eq!(machine.processor.program_counter(), START + 4);
+}
+
+#[test]
+fn wait_for_key() {
+    let mut machine = Machine::new();
+    machine.run(0xF10A);
+    assert_eq!(machine.processor.program_counter(), START);
+
+    machine.processor.set_key(0x7, true);
+    machine.run(0xF10A);
+    assert_eq!(machine.register(0x1), 0x7);
+    assert_eq!(machine.processor.program_counter(), START + 2);
+}
+
+#[test]
+fn timers() {
+    let mut machine = Machine::with_registers(&[(0x1, 3), (0x2, 1)]);
+    machine.run(0xF115);
+    machine.run(0xF218);
+    assert_eq!(machine.processor.delay_timer(), 3);
+    assert_eq!(machine.processor.sound_timer(), 1);
+    assert!(machine.processor.is_sound_active());
+
+    machine.processor.update_timers();
+    assert_eq!(machine.processor.sound_timer(), 0);
+    assert!(!machine.processor.is_sound_active());
+
+    machine.processor.update_timers();
+    machine.run(0xF307);
+    assert_eq!(machine.register(0x3), 1);
+
+    machine.processor.update_timers();
+    machine.processor.update_timers();
+    assert_eq!(machine.processor.delay_timer(), 0);
+    assert_eq!(machine.processor.program_counter(), START + 6);
+}
+
+#[test]
+fn memory_past_the_end_wraps() {
+    let mut machine = Machine::with_registers(&[(0x0, 0x10), (0x1, 0x11), (0x2, 0x12)]);
+    machine.processor.set_index_register(0xFFF);
+    machine.run(0xF255);
+    assert_eq!(machine.memory.read(0xFFF), 0x10);
+    assert_eq!(machine.memory.bytes()[..2], [0x11, 0x12]);
+
+    machine.processor.set_program_counter(0x1000);
+    machine.memory.write(0x000, 0x6A);
+    machine.memory.write(0x001, 0x42);
+    assert_eq!(
+        machine.processor.fetch_next_opcode(&machine.memory),
+        Ok(0x6A42)
+    );
+}
+
+#[test]
+fn program_counter_wraps_around_memory() {
+    let mut machine = Machine::with_registers(&[(0x0, 0xFF), (0x1, 0x42)]);
+    for (program_counter, opcode, expected) in [
+        (0xFFE, 0x6A42, 0x000),
+        (0xFFE, 0x3142, 0x002),
+        (0xFFFE, 0x6A42, 0x000),
+        (0xFFFE, 0x3142, 0x002),
+        (0x200, 0xBFFF, 0x0FE),
+    ] {
+        machine.processor.set_program_counter(program_counter);
+        machine.run(opcode);
+        assert_eq!(
+            machine.processor.program_counter(),
+            expected,
+            "{:04X} at {:04X}",
+            opcode,
+            program_counter
+        );
+    }
+
+    machine.processor.set_program_counter(0xFFFE);
+    assert_eq!(
+        machine.processor.fetch_next_opcode(&machine.memory),
+        Ok(0x0000)
+    );
+
+    // A return address of 0xFFFF, written by the ROM over the stack.
+    machine.processor.set_stack_in_memory(true);
+    machine.run(0x2300);
+    machine.memory.write(STACK_MEMORY_START, 0xFF);
+    machine.memory.write(STACK_MEMORY_START + 1, 0xFF);
+    machine.run(0x00EE);
+    assert_eq!(machine.processor.program_counter(), 0x001);
+}
+
+#[test]
+fn memory_past_the_end_can_fault() {
+    let mut machine = Machine::with_registers(&[(0x0, 0x10), (0x1, 0x11)]);
+    machine.processor.set_memory_policy(MemoryPolicy::Fault);
+    machine.processor.set_index_register(0xFFF);
+
+    // Nothing is written when part of the range is out.
+    assert_eq!(
+        machine.execute(0xF155),
+        Err(Fault::MemoryOutOfRange {
+            address: START,
+            target: 0x1000
+        })
+    );
+    assert_eq!(machine.memory.read(0xFFF), 0x00);
+    assert_eq!(machine.processor.program_counter(), START);
+
+    for opcode in [0xF133, 0xF165, 0xD012] {
+        assert!(machine.execute(opcode).is_err(), "{:04X}", opcode);
+    }
+
+    machine.processor.set_program_counter(0xFFF);
+    assert!(machine
+        .processor
+        .fetch_next_opcode(&machine.memory)
+        .is_err());
+}
+
+#[test]
+fn memory_past_the_end_can_be_ignored() {
+    let mut machine = Machine::with_registers(&[(0x0, 0x10), (0x1, 0x11)]);
+    machine.processor.set_memory_policy(MemoryPolicy::Warn);
+    machine.processor.set_index_register(0xFFF);
+    machine.run(0xF155);
+    assert_eq!(machine.memory.read(0xFFF), 0x10);
+    assert_eq!(machine.memory.read(0x000), 0x00);
+
+    machine.run(0xF165);
+    assert_eq!(machine.register(0x0), 0x10);
+    assert_eq!(machine.register(0x1), 0x00);
+}
+
+#[test]
+fn memory_protection_flags_the_interpreter_area_once() {
+    let mut machine = Machine::with_registers(&[(0x0, 0x10)]);
+    machine
+        .processor
+        .set_memory_protection(MemoryProtection::Break);
+    machine.processor.set_index_register(0x050);
+    machine.run(0xF055);
+    assert_eq!(machine.memory.read(0x050), 0x10);
+    assert_eq!(
+        machine.processor.take_memory_break(),
+        Some(FlaggedWrite {
+            program_counter: START,
+            address: 0x050,
+            is_into_code: false
+        })
+    );
+
+    machine.processor.set_index_register(0x050);
+    machine.run(0xF055);
+    assert_eq!(machine.processor.take_memory_break(), None);
+}
+
+#[test]
+fn memory_protection_flags_self_modifying_code() {
+    let mut machine = Machine::with_registers(&[(0x0, 0x10)]);
+    machine
+        .processor
+        .set_memory_protection(MemoryProtection::Break);
+    machine.memory.set_program_length(0x10);
+
+    // Program bytes not run yet are data.
+    machine.processor.set_index_register(0x208);
+    machine.run(0xF055);
+    assert_eq!(machine.processor.take_memory_break(), None);
+
+    machine.memory.mark_executed(0x20A);
+    machine.processor.set_index_register(0x20B);
+    machine.run(0xF055);
+    assert_eq!(
+        machine.processor.take_memory_break(),
+        Some(FlaggedWrite {
+            program_counter: START + 2,
+            address: 0x20B,
+            is_into_code: true
+        })
+    );
+}
+
+#[test]
+fn memory_protection_off_reports_nothing() {
+    let mut machine = Machine::with_registers(&[(0x0, 0x10)]);
+    machine.memory.mark_executed(0x300);
+    for address in [0x050, 0x300] {
+        machine.processor.set_index_register(address);
+        machine.run(0xF055);
+        assert_eq!(machine.memory.read(address), 0x10);
+    }
+    assert_eq!(machine.processor.take_memory_break(), None);
+}
+
+#[test]
+fn memory_regions() {
+    let mut memory = Memory::new();
+    memory.set_program_length(0x100);
+    assert_eq!(memory.region(0x1FF), MemoryRegion::Interpreter);
+    assert_eq!(memory.region(0x200), MemoryRegion::Program);
+    assert_eq!(memory.region(0x2FF), MemoryRegion::Program);
+    assert_eq!(memory.region(0x300), MemoryRegion::Free);
+}
+
+// Every event the machine sends from now on.
+fn subscribe_recorder(machine: &mut Machine) -> Arc<Mutex<Vec<MachineEvent>>> {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorder = Arc::clone(&events);
+    machine
+        .processor
+        .subscribe(Arc::new(Mutex::new(move |event: &MachineEvent| {
+            recorder.lock().unwrap().push(*event)
+        })));
+    events
+}
+
+fn record_events(machine: &mut Machine, opcodes: &[u16]) -> Vec<MachineEvent> {
+    let events = subscribe_recorder(machine);
+    for opcode in opcodes {
+        machine.run(*opcode);
+    }
+
+    let events = events.lock().unwrap().clone();
+    events
+}
+
+#[test]
+fn observers_see_memory_accesses() {
+    let mut machine = Machine::with_registers(&[(0x0, 0x7B)]);
+    machine.processor.set_index_register(0x300);
+    let events = record_events(&mut machine, &[0xF055, 0xF065]);
+    assert_eq!(
+        events,
+        [
+            MachineEvent::MemoryWrite {
+                program_counter: START,
+                address: 0x300,
+                value: 0x7B
+            },
+            MachineEvent::MemoryRead {
+                program_counter: START + 2,
+                address: 0x300,
+                value: 0x7B
+            },
+        ]
+    );
+}
+
+#[test]
+fn observers_see_fetches_without_reads() {
+    let mut machine = Machine::new();
+    machine.memory.write(START, 0x12);
+    machine.memory.write(START + 1, 0x34);
+    let events = subscribe_recorder(&mut machine);
+    assert_eq!(
+        machine.processor.fetch_next_opcode(&machine.memory),
+        Ok(0x1234)
+    );
+    assert_eq!(
+        *events.lock().unwrap(),
+        [MachineEvent::Fetch {
+            address: START,
+            opcode: 0x1234
+        }]
+    );
+}
+
+#[test]
+fn observers_see_the_screen_keys_and_timers() {
+    let mut machine = Machine::with_registers(&[(0x0, 0x05), (0x1, 0x03), (0x2, 0x3C)]);
+    machine.processor.set_index_register(0x300);
+    machine.memory.write(0x300, 0x80);
+    let events = record_events(
+        &mut machine,
+        &[0x00E0, 0xD011, 0xD011, 0xF215, 0xF218, 0xF30A],
+    );
+    assert_eq!(
+        events,
+        [
+            MachineEvent::Clear {
+                program_counter: START
+            },
+            MachineEvent::MemoryRead {
+                program_counter: START + 2,
+                address: 0x300,
+                value: 0x80
+            },
+            MachineEvent::Draw {
+                program_counter: START + 2,
+                x: 0x05,
+                y: 0x03,
+                address: 0x300,
+                height: 1,
+                is_collision: false
+            },
+            MachineEvent::MemoryRead {
+                program_counter: START + 4,
+                address: 0x300,
+                value: 0x80
+            },
+            MachineEvent::Draw {
+                program_counter: START + 4,
+                x: 0x05,
+                y: 0x03,
+                address: 0x300,
+                height: 1,
+                is_collision: true
+            },
+            MachineEvent::TimerWrite {
+                program_counter: START + 6,
+                timer: Timer::Delay,
+                value: 0x3C
+            },
+            MachineEvent::TimerWrite {
+                program_counter: START + 8,
+                timer: Timer::Sound,
+                value: 0x3C
+            },
+            MachineEvent::KeyWait {
+                program_counter: START + 10,
+                register: 0x3
+            },
+        ]
+    );
+}
+
+#[test]
+fn unsubscribed_observers_see_nothing() {
+    let mut machine = Machine::new();
+    let events = Arc::new(Mutex::new(0));
+    let counter = Arc::clone(&events);
+    let id = machine
+        .processor
+        .subscribe(Arc::new(Mutex::new(move |_: &MachineEvent| {
+            *counter.lock().unwrap() += 1
+        })));
+
+    machine.run(0x00E0);
+    assert!(machine.processor.unsubscribe(id));
+    assert!(!machine.processor.unsubscribe(id));
+    machine.run(0x00E0);
+    assert_eq!(*events.lock().unwrap(), 1);
+}
+
+#[test]
+fn disassembler_and_processor_agree_on_unknown_opcodes() {
+    for opcode in 0..=u16::MAX {
+        let mut machine = Machine::new();
+        let is_unknown = matches!(machine.execute(opcode), Err(Fault::UnknownOpcode { .. }));
+        let instruction = decode(opcode);
+
+        // Machine code routines are listed as SYS but can't be run.
+        if instruction.mnemonic.starts_with("SYS") {
+            assert!(is_unknown, "{:04X}", opcode);
+        } else {
+            assert_eq!(
+                is_unknown,
+                instruction.kind == InstructionKind::Unknown,
+                "{:04X}",
+                opcode
+            );
+        }
+    }
+}
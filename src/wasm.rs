@@ -0,0 +1,132 @@
+use crate::frontend::{Frontend, FrontendEvent, Hotkey, RunLoop};
+use crate::globals::*;
+use crate::palette::Palette;
+use crate::virtual_machine::VirtualMachine;
+use wasm_bindgen::prelude::*;
+
+// Input is queued by the JS event handlers and consumed on the next frame.
+struct WasmFrontend {
+    pending_events: Vec<FrontendEvent>,
+    is_audio_active: bool,
+}
+
+impl Frontend for WasmFrontend {
+    fn present_frame(&mut self, _virtual_machine: &VirtualMachine) {}
+
+    fn poll_input(&mut self) -> Vec<FrontendEvent> {
+        std::mem::take(&mut self.pending_events)
+    }
+
+    fn set_audio(&mut self, is_active: bool) {
+        self.is_audio_active = is_active;
+    }
+}
+
+// JS-facing wrapper around the VirtualMachine, driven by requestAnimationFrame.
+#[wasm_bindgen]
+pub struct WasmMachine {
+    virtual_machine: VirtualMachine,
+    run_loop: RunLoop,
+    frontend: WasmFrontend,
+    palette: Palette,
+}
+
+#[wasm_bindgen]
+impl WasmMachine {
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u32) -> WasmMachine {
+        WasmMachine {
+            virtual_machine: VirtualMachine::with_seed(seed as u64),
+            run_loop: RunLoop::new(),
+            frontend: WasmFrontend {
+                pending_events: Vec::new(),
+                is_audio_active: false,
+            },
+            palette: Palette::default(),
+        }
+    }
+
+    pub fn load_rom(&mut self, rom_bytes: &[u8]) -> Result<(), JsValue> {
+        self.virtual_machine
+            .load_rom_bytes(rom_bytes)
+            .map_err(JsValue::from_str)
+    }
+
+    pub fn set_instructions_per_frame(&mut self, instructions_per_frame: u16) {
+        self.run_loop
+            .set_instructions_per_frame(instructions_per_frame);
+    }
+
+    pub fn run_frame(&mut self) {
+        self.run_loop
+            .step(&mut self.virtual_machine, &mut self.frontend);
+    }
+
+    pub fn width(&self) -> u8 {
+        CHIP8_SCREEN_WIDTH
+    }
+
+    pub fn height(&self) -> u8 {
+        CHIP8_SCREEN_HEIGHT
+    }
+
+    // One byte per pixel, row by row : 1 when switched on, 0 otherwise.
+    pub fn framebuffer(&self) -> Vec<u8> {
+        let mut framebuffer =
+            Vec::with_capacity(CHIP8_SCREEN_WIDTH as usize * CHIP8_SCREEN_HEIGHT as usize);
+
+        for y in 0..CHIP8_SCREEN_HEIGHT {
+            for x in 0..CHIP8_SCREEN_WIDTH {
+                framebuffer.push(self.virtual_machine.is_pixel_switched_on(x, y) as u8);
+            }
+        }
+
+        framebuffer
+    }
+
+    // Returns false when there is no palette with this name.
+    pub fn set_palette(&mut self, name: &str) -> bool {
+        match Palette::by_name(name) {
+            Some(palette) => {
+                self.palette = palette;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 0xRRGGBB colours : background, first plane, second plane and both planes.
+    pub fn palette(&self) -> Vec<u32> {
+        self.palette
+            .colors
+            .iter()
+            .map(|color| color.to_u32())
+            .collect()
+    }
+
+    pub fn set_key(&mut self, n: u8, is_down: bool) {
+        if n < 16 {
+            self.frontend.pending_events.push(if is_down {
+                FrontendEvent::KeyDown(n)
+            } else {
+                FrontendEvent::KeyUp(n)
+            });
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.frontend
+            .pending_events
+            .push(FrontendEvent::Hotkey(Hotkey::TogglePause));
+    }
+
+    pub fn reset(&mut self) {
+        self.frontend
+            .pending_events
+            .push(FrontendEvent::Hotkey(Hotkey::Reset));
+    }
+
+    pub fn is_sound_active(&self) -> bool {
+        self.frontend.is_audio_active
+    }
+}
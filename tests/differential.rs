@@ -0,0 +1,145 @@
+// Random programs and machine states run through VirtualProcessor and through
+// the reference interpreter, which must agree after every instruction.
+
+mod reference;
+
+use mch8::memory::MemoryPolicy;
+use mch8::quirks::Quirks;
+use proptest::prelude::*;
+use reference::{find_divergence, Scenario, MEMORY_SIZE, PROGRAM_START};
+
+const PROGRAM_INSTRUCTIONS: usize = 256;
+
+// Opcodes with their operand bits, filled at random.
+const TEMPLATES: [(u16, u16); 31] = [
+    (0x00E0, 0x0000),
+    (0x3000, 0x0FFF),
+    (0x4000, 0x0FFF),
+    (0x5000, 0x0FF0),
+    (0x6000, 0x0FFF),
+    (0x7000, 0x0FFF),
+    (0x8000, 0x0FF0),
+    (0x8001, 0x0FF0),
+    (0x8002, 0x0FF0),
+    (0x8003, 0x0FF0),
+    (0x8004, 0x0FF0),
+    (0x8005, 0x0FF0),
+    (0x8006, 0x0FF0),
+    (0x8007, 0x0FF0),
+    (0x800E, 0x0FF0),
+    (0x9000, 0x0FF0),
+    (0xA000, 0x0FFF),
+    (0xC000, 0x0FFF),
+    (0xD000, 0x0FFF),
+    (0xE09E, 0x0F00),
+    (0xE0A1, 0x0F00),
+    (0xF007, 0x0F00),
+    (0xF00A, 0x0F00),
+    (0xF015, 0x0F00),
+    (0xF018, 0x0F00),
+    (0xF01E, 0x0F00),
+    (0xF029, 0x0F00),
+    (0xF030, 0x0F00),
+    (0xF033, 0x0F00),
+    (0xF055, 0x0F00),
+    (0xF065, 0x0F00),
+];
+
+fn template_opcode() -> impl Strategy<Value = u16> {
+    (0..TEMPLATES.len(), any::<u16>()).prop_map(|(template, operands)| {
+        let (opcode, mask) = TEMPLATES[template];
+        opcode | operands & mask
+    })
+}
+
+// Jumps and calls land on the program's instructions, to keep it running.
+fn flow_opcode() -> impl Strategy<Value = u16> {
+    (
+        prop::sample::select(vec![0x1000u16, 0x2000, 0xB000]),
+        0..PROGRAM_INSTRUCTIONS as u16,
+    )
+        .prop_map(|(opcode, instruction)| opcode | (PROGRAM_START + instruction * 2))
+}
+
+// Returns and random words, often a fault ending the run, are kept rare.
+fn opcode() -> impl Strategy<Value = u16> {
+    prop_oneof![
+        36 => template_opcode(),
+        4 => flow_opcode(),
+        1 => Just(0x00EE),
+        1 => any::<u16>(),
+    ]
+}
+
+fn quirks() -> impl Strategy<Value = Quirks> {
+    any::<[bool; 4]>().prop_map(|flags| Quirks {
+        shift_uses_vy: flags[0],
+        load_store_increments_index: flags[1],
+        jump_uses_vx: flags[2],
+        logic_resets_vf: flags[3],
+    })
+}
+
+fn memory_policy() -> impl Strategy<Value = MemoryPolicy> {
+    prop::sample::select(vec![
+        MemoryPolicy::Wrap,
+        MemoryPolicy::Fault,
+        MemoryPolicy::Warn,
+    ])
+}
+
+fn scenario() -> impl Strategy<Value = Scenario> {
+    (
+        (quirks(), memory_policy()),
+        any::<u64>(),
+        any::<[u8; 16]>(),
+        0..MEMORY_SIZE as u16,
+        any::<(u8, u8)>(),
+        any::<[bool; 16]>(),
+        prop::collection::vec(any::<u8>(), MEMORY_SIZE),
+        prop::collection::vec(opcode(), PROGRAM_INSTRUCTIONS),
+        1..500usize,
+    )
+        .prop_map(
+            |(
+                (quirks, memory_policy),
+                seed,
+                registers,
+                index_register,
+                timers,
+                keys,
+                mut memory,
+                program,
+                instructions,
+            )| {
+                for (index, opcode) in program.iter().enumerate() {
+                    let address = PROGRAM_START as usize + index * 2;
+                    memory[address..address + 2].copy_from_slice(&opcode.to_be_bytes());
+                }
+
+                Scenario {
+                    quirks,
+                    memory_policy,
+                    seed,
+                    registers,
+                    index_register,
+                    delay_timer: timers.0,
+                    sound_timer: timers.1,
+                    keys,
+                    memory,
+                    instructions,
+                }
+            },
+        )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn processor_matches_the_reference(scenario in scenario()) {
+        if let Some(divergence) = find_divergence(&scenario) {
+            return Err(TestCaseError::fail(divergence));
+        }
+    }
+}
@@ -0,0 +1,384 @@
+// A deliberately naive CHIP-8 interpreter written from the specification
+// rather than from mch8's code, for the differential tests and fuzz targets
+// to compare VirtualProcessor against. Shared with fuzz/ through #[path].
+
+use mch8::memory::{Memory, MemoryPolicy};
+use mch8::quirks::Quirks;
+use mch8::render_table::RenderTable;
+use mch8::virtual_processor::VirtualProcessor;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+pub const MEMORY_SIZE: usize = 4096;
+pub const PROGRAM_START: u16 = 0x200;
+const SCREEN_WIDTH: usize = 64;
+const SCREEN_HEIGHT: usize = 32;
+const STACK_DEPTH: usize = 16;
+const BIG_FONT_START: u16 = 80; // After the 16 characters of the small font.
+const TIMER_TICK_INSTRUCTIONS: usize = 10;
+
+// The program did something impossible, mch8 must fault too.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Fault;
+
+pub struct Reference {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub memory: Vec<u8>,
+    pub screen: Vec<bool>,
+    pub keys: [bool; 16],
+    quirks: Quirks,
+    memory_policy: MemoryPolicy,
+    random: SmallRng, // Seeded like the processor's, CXNN draws the same bytes.
+}
+
+impl Reference {
+    pub fn new(scenario: &Scenario) -> Reference {
+        Reference {
+            v: scenario.registers,
+            i: scenario.index_register,
+            pc: PROGRAM_START,
+            stack: Vec::new(),
+            delay_timer: scenario.delay_timer,
+            sound_timer: scenario.sound_timer,
+            memory: scenario.memory.clone(),
+            screen: vec![false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            keys: scenario.keys,
+            quirks: scenario.quirks,
+            memory_policy: scenario.memory_policy,
+            random: SmallRng::seed_from_u64(scenario.seed),
+        }
+    }
+
+    // Nothing is read or written when any of the addresses faults.
+    fn check(&self, start: u16, length: u16) -> Result<(), Fault> {
+        let end = start as usize + length as usize;
+        if self.memory_policy == MemoryPolicy::Fault && end > MEMORY_SIZE {
+            return Err(Fault);
+        }
+        Ok(())
+    }
+
+    // None when the access is ignored.
+    fn locate(&self, address: u16) -> Option<usize> {
+        match self.memory_policy {
+            MemoryPolicy::Wrap => Some(address as usize % MEMORY_SIZE),
+            _ => Some(address as usize).filter(|address| *address < MEMORY_SIZE),
+        }
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        self.locate(address)
+            .map_or(0, |address| self.memory[address])
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if let Some(address) = self.locate(address) {
+            self.memory[address] = value;
+        }
+    }
+
+    pub fn fetch(&self) -> Result<u16, Fault> {
+        self.check(self.pc, 2)?;
+        Ok((self.read(self.pc) as u16) << 8 | self.read(self.pc.wrapping_add(1)) as u16)
+    }
+
+    pub fn tick_timers(&mut self) {
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+    }
+
+    // The state is left as it was when the instruction faults.
+    pub fn step(&mut self) -> Result<(), Fault> {
+        let opcode = self.fetch()?;
+        let nibbles = (
+            opcode >> 12,
+            (opcode >> 8 & 0xF) as usize,
+            (opcode >> 4 & 0xF) as usize,
+            opcode & 0xF,
+        );
+        let (x, y) = (nibbles.1, nibbles.2);
+        let nn = opcode as u8;
+        let nnn = opcode & 0xFFF;
+        let mut next = self.pc.wrapping_add(2);
+
+        match nibbles {
+            (0x0, 0x0, 0xE, 0x0) => self.screen.fill(false),
+            (0x0, 0x0, 0xE, 0xE) => next = self.stack.pop().ok_or(Fault)? + 2,
+            (0x1, ..) => next = nnn,
+            (0x2, ..) => {
+                if self.stack.len() >= STACK_DEPTH {
+                    return Err(Fault);
+                }
+                self.stack.push(self.pc);
+                next = nnn;
+            }
+            (0x3, ..) if self.v[x] == nn => next += 2,
+            (0x4, ..) if self.v[x] != nn => next += 2,
+            (0x5, ..) if self.v[x] == self.v[y] => next += 2,
+            (0x9, ..) if self.v[x] != self.v[y] => next += 2,
+            (0x3..=0x5 | 0x9, ..) => {}
+            (0x6, ..) => self.v[x] = nn,
+            (0x7, ..) => self.v[x] = self.v[x].wrapping_add(nn),
+            (0x8, _, _, 0x0) => self.v[x] = self.v[y],
+            (0x8, _, _, 0x1..=0x3) => {
+                self.v[x] = match nibbles.3 {
+                    0x1 => self.v[x] | self.v[y],
+                    0x2 => self.v[x] & self.v[y],
+                    _ => self.v[x] ^ self.v[y],
+                };
+                if self.quirks.logic_resets_vf {
+                    self.v[0xF] = 0;
+                }
+            }
+            (0x8, _, _, 0x4) => {
+                let sum = self.v[x] as u16 + self.v[y] as u16;
+                self.v[x] = sum as u8;
+                self.v[0xF] = (sum > 0xFF) as u8;
+            }
+            (0x8, _, _, 0x5 | 0x7) => {
+                let (a, b) = if nibbles.3 == 0x5 {
+                    (self.v[x], self.v[y])
+                } else {
+                    (self.v[y], self.v[x])
+                };
+                self.v[x] = a.wrapping_sub(b);
+                self.v[0xF] = (a >= b) as u8;
+            }
+            (0x8, _, _, 0x6 | 0xE) => {
+                let value = if self.quirks.shift_uses_vy {
+                    self.v[y]
+                } else {
+                    self.v[x]
+                };
+                if nibbles.3 == 0x6 {
+                    self.v[x] = value >> 1;
+                    self.v[0xF] = value & 1;
+                } else {
+                    self.v[x] = value << 1;
+                    self.v[0xF] = value >> 7;
+                }
+            }
+            (0xA, ..) => self.i = nnn,
+            (0xB, ..) => {
+                let offset = if self.quirks.jump_uses_vx { x } else { 0 };
+                next = nnn + self.v[offset] as u16;
+            }
+            (0xC, ..) => self.v[x] = self.random.gen::<u8>() & nn,
+            (0xD, ..) => {
+                self.check(self.i, nibbles.3)?;
+                let (left, top) = (self.v[x] as usize, self.v[y] as usize);
+                let mut collision = false;
+                for row in 0..nibbles.3 {
+                    let bits = self.read(self.i.wrapping_add(row));
+                    for column in 0..8 {
+                        if bits & (0x80 >> column) != 0 {
+                            let pixel = (top + row as usize) % SCREEN_HEIGHT * SCREEN_WIDTH
+                                + (left + column) % SCREEN_WIDTH;
+                            collision |= self.screen[pixel];
+                            self.screen[pixel] = !self.screen[pixel];
+                        }
+                    }
+                }
+                self.v[0xF] = collision as u8;
+            }
+            (0xE, _, 0x9, 0xE) if self.keys[self.v[x] as usize & 0xF] => next += 2,
+            (0xE, _, 0xA, 0x1) if !self.keys[self.v[x] as usize & 0xF] => next += 2,
+            (0xE, _, 0x9, 0xE) | (0xE, _, 0xA, 0x1) => {}
+            (0xF, _, 0x0, 0x7) => self.v[x] = self.delay_timer,
+            (0xF, _, 0x0, 0xA) => match (0..16).rev().find(|key| self.keys[*key]) {
+                Some(key) => self.v[x] = key as u8,
+                None => next = self.pc,
+            },
+            (0xF, _, 0x1, 0x5) => self.delay_timer = self.v[x],
+            (0xF, _, 0x1, 0x8) => self.sound_timer = self.v[x],
+            (0xF, _, 0x1, 0xE) => self.i = self.i.wrapping_add(self.v[x] as u16),
+            (0xF, _, 0x2, 0x9) => self.i = (self.v[x] & 0xF) as u16 * 5,
+            (0xF, _, 0x3, 0x0) => self.i = BIG_FONT_START + (self.v[x] & 0xF) as u16 * 10,
+            (0xF, _, 0x3, 0x3) => {
+                self.check(self.i, 3)?;
+                let value = self.v[x];
+                for (offset, digit) in [value / 100, value / 10 % 10, value % 10]
+                    .into_iter()
+                    .enumerate()
+                {
+                    self.write(self.i.wrapping_add(offset as u16), digit);
+                }
+            }
+            (0xF, _, 0x5, 0x5) | (0xF, _, 0x6, 0x5) => {
+                self.check(self.i, x as u16 + 1)?;
+                for register in 0..=x {
+                    let address = self.i.wrapping_add(register as u16);
+                    if nibbles.2 == 0x5 {
+                        self.write(address, self.v[register]);
+                    } else {
+                        self.v[register] = self.read(address);
+                    }
+                }
+                if self.quirks.load_store_increments_index {
+                    self.i = self.i.wrapping_add(x as u16 + 1);
+                }
+            }
+            _ => return Err(Fault),
+        }
+
+        self.pc = next % MEMORY_SIZE as u16;
+        Ok(())
+    }
+}
+
+// A random machine : its quirks and starting state, the program being
+// whatever `memory` holds from 0x200.
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub quirks: Quirks,
+    pub memory_policy: MemoryPolicy,
+    pub seed: u64,
+    pub registers: [u8; 16],
+    pub index_register: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    pub keys: [bool; 16],
+    pub memory: Vec<u8>,
+    pub instructions: usize,
+}
+
+// mch8 running the same scenario.
+struct Machine {
+    processor: VirtualProcessor,
+    memory: Memory,
+    render_table: RenderTable,
+}
+
+impl Machine {
+    fn new(scenario: &Scenario) -> Machine {
+        let mut processor = VirtualProcessor::with_seed(scenario.seed);
+        processor.set_quirks(scenario.quirks);
+        processor.set_memory_policy(scenario.memory_policy);
+        for (index, value) in scenario.registers.iter().enumerate() {
+            processor.set_register(index, *value);
+        }
+        processor.set_index_register(scenario.index_register);
+        processor.set_delay_timer(scenario.delay_timer);
+        processor.set_sound_timer(scenario.sound_timer);
+        for (key, is_down) in scenario.keys.iter().enumerate() {
+            processor.set_key(key as u8, *is_down);
+        }
+
+        let mut memory = Memory::new();
+        for (address, value) in scenario.memory.iter().enumerate() {
+            memory.write(address as u16, *value);
+        }
+
+        Machine {
+            processor,
+            memory,
+            render_table: RenderTable::new(),
+        }
+    }
+
+    // The first difference with the reference, if any.
+    fn divergence(&self, reference: &Reference) -> Option<String> {
+        let processor = &self.processor;
+        let differences = [
+            ("registers", processor.registers() != &reference.v),
+            ("I", processor.index_register() != reference.i),
+            ("PC", processor.program_counter() != reference.pc),
+            ("stack", processor.stack() != reference.stack.as_slice()),
+            (
+                "delay timer",
+                processor.delay_timer() != reference.delay_timer,
+            ),
+            (
+                "sound timer",
+                processor.sound_timer() != reference.sound_timer,
+            ),
+            ("memory", self.memory.bytes() != reference.memory.as_slice()),
+            (
+                "screen",
+                self.render_table.pixels() != reference.screen.as_slice(),
+            ),
+        ];
+
+        differences
+            .iter()
+            .find(|(_, is_different)| *is_different)
+            .map(|(name, _)| {
+                format!(
+                    "{} differ\n  mch8      : V={:02X?} I={:#05X} PC={:#05X} stack={:03X?} DT={} ST={}\n  reference : V={:02X?} I={:#05X} PC={:#05X} stack={:03X?} DT={} ST={}",
+                    name,
+                    processor.registers(),
+                    processor.index_register(),
+                    processor.program_counter(),
+                    processor.stack(),
+                    processor.delay_timer(),
+                    processor.sound_timer(),
+                    reference.v,
+                    reference.i,
+                    reference.pc,
+                    reference.stack,
+                    reference.delay_timer,
+                    reference.sound_timer
+                )
+            })
+    }
+}
+
+// Runs the scenario on both interpreters side by side, describing the first
+// instruction after which they disagree.
+pub fn find_divergence(scenario: &Scenario) -> Option<String> {
+    let mut machine = Machine::new(scenario);
+    let mut reference = Reference::new(scenario);
+
+    for instruction in 0..scenario.instructions {
+        if instruction > 0 && instruction % TIMER_TICK_INSTRUCTIONS == 0 {
+            machine.processor.update_timers();
+            reference.tick_timers();
+        }
+
+        let address = reference.pc;
+        let opcode = match (
+            machine.processor.fetch_next_opcode(&machine.memory),
+            reference.fetch(),
+        ) {
+            (Ok(fetched), Ok(opcode)) if fetched == opcode => opcode,
+            (Err(_), Err(_)) => return None,
+            (fetched, opcode) => {
+                return Some(format!(
+                    "fetch at {:#05X} : mch8 {:04X?}, reference {:04X?}",
+                    address, fetched, opcode
+                ))
+            }
+        };
+
+        let expected = reference.step();
+
+        let result = machine.processor.execute_instruction(
+            opcode,
+            &mut machine.memory,
+            &mut machine.render_table,
+        );
+        let context = format!(
+            "after {:04X} at {:#05X} (instruction {})",
+            opcode, address, instruction
+        );
+
+        if result.is_err() != expected.is_err() {
+            return Some(format!(
+                "{} : mch8 {:?}, reference {:?}",
+                context, result, expected
+            ));
+        }
+        if let Some(divergence) = machine.divergence(&reference) {
+            return Some(format!("{} : {}", context, divergence));
+        }
+        if expected.is_err() {
+            return None;
+        }
+    }
+
+    None
+}
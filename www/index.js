@@ -0,0 +1,148 @@
+// Expects the wasm-bindgen output in ./pkg (see README). A ROM can be chosen
+// with the file input or given in the URL : index.html?rom=../roms/pong.rom
+import init, { WasmMachine } from "./pkg/mch8.js";
+
+const KEYPAD_LAYOUT = {
+    "1": 0x1, "2": 0x2, "3": 0x3, "4": 0xC,
+    "q": 0x4, "w": 0x5, "e": 0x6, "r": 0xD,
+    "a": 0x7, "s": 0x8, "d": 0x9, "f": 0xE,
+    "z": 0xA, "x": 0x0, "c": 0xB, "v": 0xF,
+};
+
+// Emulated frames run at 60 Hz whatever the display refresh rate is.
+const FRAME_DURATION_MS = 1000 / 60;
+// Catching up is limited after the tab was hidden or the browser stalled.
+const MAX_FRAMES_PER_CALLBACK = 4;
+
+const canvas = document.getElementById("screen");
+const context = canvas.getContext("2d");
+const errorText = document.getElementById("error");
+
+let machine = null;
+let audio = null;
+let isLoopStarted = false;
+let lastTimestamp = null;
+let pendingTime = 0;
+
+// A square wave that is muted/unmuted following the sound timer.
+function buzzer() {
+    if (audio === null) {
+        const audioContext = new AudioContext();
+        const oscillator = audioContext.createOscillator();
+        const gain = audioContext.createGain();
+        oscillator.type = "square";
+        oscillator.frequency.value = 440;
+        gain.gain.value = 0;
+        oscillator.connect(gain).connect(audioContext.destination);
+        oscillator.start();
+        audio = { audioContext, gain };
+    }
+    return audio;
+}
+
+function drawFrame() {
+    const width = machine.width();
+    const height = machine.height();
+    const framebuffer = machine.framebuffer();
+    const palette = machine.palette();
+    const image = context.createImageData(width, height);
+
+    for (let i = 0; i < framebuffer.length; i++) {
+        const color = palette[framebuffer[i]];
+        image.data[i * 4] = (color >> 16) & 0xFF;
+        image.data[i * 4 + 1] = (color >> 8) & 0xFF;
+        image.data[i * 4 + 2] = color & 0xFF;
+        image.data[i * 4 + 3] = 255;
+    }
+
+    context.putImageData(image, 0, 0);
+}
+
+function frame(timestamp) {
+    if (lastTimestamp !== null) {
+        pendingTime += timestamp - lastTimestamp;
+    }
+    lastTimestamp = timestamp;
+    pendingTime = Math.min(pendingTime, FRAME_DURATION_MS * MAX_FRAMES_PER_CALLBACK);
+
+    if (pendingTime >= FRAME_DURATION_MS) {
+        while (pendingTime >= FRAME_DURATION_MS) {
+            machine.run_frame();
+            pendingTime -= FRAME_DURATION_MS;
+        }
+        drawFrame();
+    }
+
+    if (audio !== null) {
+        audio.gain.gain.value = machine.is_sound_active() ? 0.1 : 0;
+    }
+
+    requestAnimationFrame(frame);
+}
+
+// The previous game keeps running when the new ROM can't be loaded.
+function start(romBytes) {
+    const newMachine = new WasmMachine(Math.floor(Math.random() * 0xFFFFFFFF));
+    try {
+        newMachine.set_palette(document.getElementById("palette").value);
+        newMachine.load_rom(romBytes);
+    } catch (error) {
+        newMachine.free();
+        errorText.textContent = String(error);
+        return;
+    }
+    errorText.textContent = "";
+
+    // The machine lives in the wasm memory, it is not garbage collected.
+    if (machine !== null) {
+        machine.free();
+    }
+    machine = newMachine;
+
+    if (!isLoopStarted) {
+        isLoopStarted = true;
+        requestAnimationFrame(frame);
+    }
+}
+
+function onKey(event, isDown) {
+    if (isDown && machine !== null && !event.repeat) {
+        if (event.key === "p") {
+            machine.toggle_pause();
+        } else if (event.key === "Backspace") {
+            machine.reset();
+        }
+    }
+
+    const key = KEYPAD_LAYOUT[event.key.toLowerCase()];
+    if (key !== undefined && machine !== null) {
+        // Browsers only allow audio to start after a user gesture.
+        buzzer().audioContext.resume();
+        machine.set_key(key, isDown);
+        event.preventDefault();
+    }
+}
+
+await init();
+
+document.addEventListener("keydown", (event) => onKey(event, true));
+document.addEventListener("keyup", (event) => onKey(event, false));
+
+document.getElementById("rom-input").addEventListener("change", async (event) => {
+    const file = event.target.files[0];
+    if (file) {
+        start(new Uint8Array(await file.arrayBuffer()));
+    }
+});
+
+document.getElementById("palette").addEventListener("change", (event) => {
+    if (machine !== null) {
+        machine.set_palette(event.target.value);
+    }
+});
+
+const romUrl = new URLSearchParams(window.location.search).get("rom");
+if (romUrl) {
+    const response = await fetch(romUrl);
+    start(new Uint8Array(await response.arrayBuffer()));
+}